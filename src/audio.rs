@@ -0,0 +1,93 @@
+//! Cue chime playback.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+// Owns the audio output. None plays the built-in tone; a file that can't be
+// played falls back to it too, so a cue is never silent by mistake.
+pub fn spawn_chime_player() -> Sender<Option<PathBuf>> {
+    let (tx, rx) = mpsc::channel::<Option<PathBuf>>();
+    thread::spawn(move || {
+        let mut stream = match rodio::OutputStreamBuilder::open_default_stream() {
+            Ok(stream) => stream,
+            Err(err) => {
+                tracing::warn!("No audio output for chimes: {err}");
+                return;
+            }
+        };
+        stream.log_on_drop(false);
+        for sound in rx {
+            let file = sound.as_deref().map(|path| {
+                fs::File::open(path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|file| rodio::Decoder::try_from(file).map_err(|err| err.to_string()))
+                    .inspect_err(|err| tracing::warn!("Can't play {}: {err}", path.display()))
+            });
+            match file {
+                Some(Ok(decoder)) => stream.mixer().add(decoder),
+                _ => {
+                    use rodio::Source;
+                    let tone = rodio::source::SineWave::new(880.0)
+                        .take_duration(Duration::from_millis(150))
+                        .amplify(0.25);
+                    stream.mixer().add(tone);
+                }
+            }
+        }
+    });
+    tx
+}
+
+// Reads `text` aloud with the system's own voice. The text goes in as a single
+// argument or environment variable, never through a shell.
+pub fn speak(text: String) {
+    thread::spawn(move || {
+        let spoken = speech_commands(&text).into_iter().any(|mut cmd| {
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok()
+        });
+        if !spoken {
+            tracing::warn!("No text-to-speech available to read out \"{text}\"");
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn speech_commands(text: &str) -> Vec<Command> {
+    let mut say = Command::new("say");
+    say.arg(text);
+    vec![say]
+}
+
+#[cfg(windows)]
+fn speech_commands(text: &str) -> Vec<Command> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut ps = Command::new("powershell");
+    ps.args([
+        "-NoProfile",
+        "-Command",
+        "Add-Type -AssemblyName System.Speech; \
+         (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:CALL_TEXT)",
+    ])
+    .env("CALL_TEXT", text)
+    .creation_flags(CREATE_NO_WINDOW);
+    vec![ps]
+}
+
+// Speech Dispatcher where it's set up, eSpeak otherwise.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn speech_commands(text: &str) -> Vec<Command> {
+    let mut spd = Command::new("spd-say");
+    spd.arg("--wait").arg(text);
+    let mut espeak = Command::new("espeak");
+    espeak.arg(text);
+    vec![spd, espeak]
+}
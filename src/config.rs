@@ -0,0 +1,895 @@
+//! Settings and their persistence in the config directory.
+
+use crate::event::CueInfo;
+use crate::net::{
+    AddrChoice, DEFAULT_LOST_AFTER, DEFAULT_OSC_PORT, RetryPolicy, SourceKind, Transport,
+};
+use crate::qlab::DEFAULT_QLAB_PORT;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayoutPreset {
+    #[default]
+    Standard,
+    /// Standard plus the cue list alongside
+    CueList,
+}
+
+// Bump when a config field changes meaning and append the upgrade step to
+// `MIGRATIONS`.
+pub const CONFIG_VERSION: u32 = 1;
+
+// MIGRATIONS[i] upgrades a raw config from version i + 1 to i + 2.
+type Migration = fn(&mut toml::Table);
+
+const MIGRATIONS: &[Migration] = &[];
+
+const _: () = assert!(MIGRATIONS.len() + 1 == CONFIG_VERSION as usize);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LossAlert {
+    #[default]
+    Subtle,
+    Banner,
+    BorderPulse,
+}
+
+impl LossAlert {
+    pub const ALL: &[LossAlert] = &[LossAlert::Subtle, LossAlert::Banner, LossAlert::BorderPulse];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LossAlert::Subtle => "Status text only",
+            LossAlert::Banner => "Full-screen red banner",
+            LossAlert::BorderPulse => "Pulsing red border",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShowCall {
+    pub name: String,
+    pub minutes_before: u32,
+    // Display groups the call goes to, comma separated; blank for every display.
+    #[serde(default)]
+    pub groups: String,
+    // Read out as "Beginners please" when it fires.
+    #[serde(default)]
+    pub speak: bool,
+    // A sound file played when it fires; blank for none.
+    #[serde(default)]
+    pub sound: String,
+    #[serde(skip)]
+    pub fired_on: Option<NaiveDate>,
+}
+
+impl ShowCall {
+    pub fn new(name: &str, minutes_before: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            minutes_before,
+            groups: String::new(),
+            speak: false,
+            sound: String::new(),
+            fired_on: None,
+        }
+    }
+
+    pub fn reaches(&self, group: &str) -> bool {
+        in_groups(self.groups.split(','), group)
+    }
+
+    // Standard UK calls, given five minutes ahead of the time they name.
+    pub fn defaults() -> Vec<ShowCall> {
+        vec![
+            ShowCall::new("Half hour", 35),
+            ShowCall::new("Quarter", 20),
+            ShowCall::new("Five", 10),
+            ShowCall::new("Beginners", 5),
+        ]
+    }
+}
+
+// A display outside every group only gets what is meant for all.
+fn in_groups<'a>(groups: impl Iterator<Item = &'a str>, group: &str) -> bool {
+    let group = group.trim();
+    let mut named = groups.map(str::trim).filter(|g| !g.is_empty()).peekable();
+    named.peek().is_none() || named.any(|g| !group.is_empty() && g.eq_ignore_ascii_case(group))
+}
+
+// A wall-clock reminder that stays on screen until acknowledged.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Reminder {
+    pub time: String,
+    pub text: String,
+    // Display groups it shows on, as for show calls; empty for every display.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(skip)]
+    pub fired_on: Option<NaiveDate>,
+    #[serde(skip)]
+    pub active: bool,
+    #[serde(skip)]
+    pub snoozed_until: Option<Instant>,
+}
+
+impl Reminder {
+    pub fn reaches(&self, group: &str) -> bool {
+        in_groups(self.groups.iter().map(String::as_str), group)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    System,
+    // Dim red on black for a monitor in the wings.
+    Backstage,
+}
+
+impl Theme {
+    pub const ALL: &[Theme] = &[Theme::Dark, Theme::Light, Theme::System, Theme::Backstage];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::System => "Follow system",
+            Theme::Backstage => "Backstage (red on black)",
+        }
+    }
+}
+
+// A sound on each cue fire for crew who can't watch the screen.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Chime {
+    pub enabled: bool,
+    // Played for cues without a color sound; a short tone if unset.
+    pub sound: Option<PathBuf>,
+    // Cue color, as TheatreMix sends it, to a sound file.
+    pub colors: HashMap<String, PathBuf>,
+}
+
+impl Chime {
+    pub fn sound_for(&self, cue: &CueInfo) -> Option<PathBuf> {
+        cue.color
+            .as_deref()
+            .and_then(|color| {
+                self.colors
+                    .iter()
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case(color.trim()))
+            })
+            .map(|(_, path)| path.clone())
+            .or_else(|| self.sound.clone())
+    }
+}
+
+// Brief wash over the cue area when a cue fires, for peripheral vision.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CueFlash {
+    pub enabled: bool,
+    // Peak opacity, 0 to 1.
+    pub intensity: f32,
+    pub duration_ms: u32,
+}
+
+impl Default for CueFlash {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 0.35,
+            duration_ms: 600,
+        }
+    }
+}
+
+// A second window, e.g. on a stage-facing monitor, with just the cue in
+// huge text and its own layout.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageDisplay {
+    pub enabled: bool,
+    pub show_next: bool,
+    pub text_scale: f32,
+    pub fullscreen: bool,
+}
+
+impl Default for StageDisplay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            show_next: true,
+            text_scale: 1.0,
+            fullscreen: false,
+        }
+    }
+}
+
+// Cue number, text and color published to an MQTT broker on each fire, for
+// building automation and home-automation hubs to follow the show.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mqtt {
+    pub enabled: bool,
+    // Host or host:port; 1883 without a port.
+    pub broker: String,
+    // A blank topic isn't published.
+    pub number_topic: String,
+    pub text_topic: String,
+    pub color_topic: String,
+    // Retained, so anything subscribing later gets the current cue at once.
+    pub retain: bool,
+}
+
+impl Default for Mqtt {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker: String::new(),
+            number_topic: "theatremix/cue/number".to_string(),
+            text_topic: "theatremix/cue/text".to_string(),
+            color_topic: "theatremix/cue/color".to_string(),
+            retain: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiMessage {
+    #[default]
+    MscGo,
+    Note,
+    // The cue number as the program, for whole-numbered cues up to 127.
+    ProgramChange,
+}
+
+impl MidiMessage {
+    pub const ALL: [MidiMessage; 3] = [
+        MidiMessage::MscGo,
+        MidiMessage::Note,
+        MidiMessage::ProgramChange,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MidiMessage::MscGo => "MSC GO",
+            MidiMessage::Note => "Note",
+            MidiMessage::ProgramChange => "Program change",
+        }
+    }
+}
+
+// A MIDI message on each cue fire, for systems that can't take OSC.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiOut {
+    pub enabled: bool,
+    // Output port, by the name the system gives it.
+    pub port: String,
+    pub message: MidiMessage,
+    // MSC device ID; 127 addresses every device.
+    pub device_id: u8,
+    // MSC command format; 127 is all types, 1 lighting, 16 sound, 48 video.
+    pub command_format: u8,
+    // 1 to 16, for a note or program change.
+    pub channel: u8,
+    pub note: u8,
+    pub velocity: u8,
+}
+
+impl Default for MidiOut {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: String::new(),
+            message: MidiMessage::default(),
+            device_id: 127,
+            command_format: 127,
+            channel: 1,
+            note: 60,
+            velocity: 127,
+        }
+    }
+}
+
+// Cue lights and beacons driven over Art-Net as cues fire.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtNet {
+    pub enabled: bool,
+    // A node, or a broadcast address to reach every node on the subnet.
+    pub target: IpAddr,
+    // Net, sub-net and universe as one port address, 0 to 32767.
+    pub universe: u16,
+    // Where the cue's color goes as red, green and blue; 0 for nowhere.
+    pub color_channel: u16,
+    pub rules: Vec<DmxRule>,
+}
+
+impl Default for ArtNet {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: IpAddr::V4(std::net::Ipv4Addr::BROADCAST),
+            universe: 0,
+            color_channel: 0,
+            rules: Vec::new(),
+        }
+    }
+}
+
+// Levels set from `channel` on for cues with this color, this number or both.
+// A rule with neither applies to every cue; channels no rule sets are at zero.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DmxRule {
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub cue: Option<String>,
+    pub channel: u16,
+    pub levels: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpioEvent {
+    Cue,
+    // The connection to TheatreMix dropped.
+    Lost,
+}
+
+// One pin pulsed on an event. Pins are the chip's line numbers, which on a
+// Raspberry Pi are the BCM GPIO numbers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GpioPulse {
+    pub event: GpioEvent,
+    pub pin: u32,
+    pub ms: u32,
+    #[serde(default)]
+    pub active_low: bool,
+}
+
+// Physical cue lights and buzzers wired to the board running the display.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gpio {
+    pub enabled: bool,
+    // gpiochip0 on most Pis; the header is gpiochip4 on a Pi 5 with older kernels.
+    pub chip: PathBuf,
+    pub pulses: Vec<GpioPulse>,
+}
+
+impl Default for Gpio {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            chip: PathBuf::from("/dev/gpiochip0"),
+            pulses: Vec::new(),
+        }
+    }
+}
+
+// Shell commands run on each event. Placeholders ({event}, {cue_number},
+// {cue_text}, {cue_color}, {host}, {time}) are filled in already quoted.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandHooks {
+    pub cue: Option<String>,
+    pub connect: Option<String>,
+    pub disconnect: Option<String>,
+}
+
+// What "Save show report" writes: which part of today, which kinds of
+// event and which columns. The defaults give one row per cue fired.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShowReport {
+    // "19:30"; blank for the start or end of the day.
+    pub from: String,
+    pub to: String,
+    pub cues: bool,
+    pub markers: bool,
+    // Connects and disconnects.
+    pub connection: bool,
+    // A heading in the script, for one act or scene; blank for the whole show.
+    pub act: String,
+    // A stretch of the cue sheet, in its order; blank for its first or last cue.
+    pub first_cue: String,
+    pub last_cue: String,
+    pub columns: ReportColumns,
+}
+
+impl Default for ShowReport {
+    fn default() -> Self {
+        Self {
+            from: String::new(),
+            to: String::new(),
+            cues: true,
+            markers: false,
+            connection: false,
+            act: String::new(),
+            first_cue: String::new(),
+            last_cue: String::new(),
+            columns: ReportColumns::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportColumns {
+    pub time: bool,
+    pub event: bool,
+    pub cue: bool,
+    pub text: bool,
+    pub since_previous: bool,
+}
+
+impl Default for ReportColumns {
+    fn default() -> Self {
+        Self {
+            time: true,
+            event: false,
+            cue: true,
+            text: true,
+            since_previous: true,
+        }
+    }
+}
+
+// A calling script or libretto, shown in a panel that scrolls to each cue's
+// marker as it fires.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibrettoPanel {
+    pub path: String,
+    pub open: bool,
+}
+
+// A second show control system, such as QLab or Eos for lighting, shown on
+// the right half of the display with a connection of its own.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitSource {
+    pub enabled: bool,
+    // Heading over its cues.
+    pub label: String,
+    pub host: String,
+    pub osc_port: u16,
+    pub source: SourceKind,
+    pub qlab_passcode: String,
+    // For desks that push their OSC, like Eos; read through osc-map.txt.
+    pub listen_port: Option<u16>,
+}
+
+impl Default for SplitSource {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            label: "Lighting".to_string(),
+            host: String::new(),
+            osc_port: DEFAULT_QLAB_PORT,
+            source: SourceKind::Qlab,
+            qlab_passcode: String::new(),
+            listen_port: None,
+        }
+    }
+}
+
+// A saved message for the OSC console; args as typed, space separated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OscPreset {
+    pub name: String,
+    pub address: String,
+    #[serde(default)]
+    pub args: String,
+}
+
+// A page of soft buttons, laid out `columns` wide, for a spare touchscreen.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ButtonPage {
+    pub name: String,
+    #[serde(default = "ButtonPage::default_columns")]
+    pub columns: usize,
+    #[serde(default)]
+    pub buttons: Vec<SoftButton>,
+}
+
+impl ButtonPage {
+    pub fn default_columns() -> usize {
+        4
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SoftButton {
+    pub label: String,
+    pub action: ButtonAction,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ButtonAction {
+    // Sent to the TheatreMix host; args are space separated and typed as
+    // int, float or string by what they parse as.
+    Osc {
+        address: String,
+        #[serde(default)]
+        args: String,
+    },
+    Page {
+        page: String,
+    },
+    // Press to start a stopwatch on the button, press again to stop it.
+    Timer,
+    Blackout,
+}
+
+// One show's connection and layout, for venues running several a day.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub host: String,
+    pub osc_port: u16,
+    pub sheet_url: String,
+    pub layout: LayoutPreset,
+    #[serde(default)]
+    pub theme: Theme,
+    // Loaded without asking when its host answers or is found on the network.
+    #[serde(default)]
+    pub auto_select: bool,
+}
+
+// Position is kept while fullscreen or maximized too, so the window comes
+// back on the same monitor; the size is the last windowed one.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub fullscreen: bool,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+// Everything kept between runs, stored as config.toml.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub host: String,
+    pub osc_port: u16,
+    pub local_port: u16,
+    pub address: AddrChoice,
+    pub interface: Option<IpAddr>,
+    pub transport: Transport,
+    pub source: SourceKind,
+    pub qlab_passcode: String,
+    pub listen: bool,
+    pub listen_port: u16,
+    pub backup_host: Option<String>,
+    // An X32 or M32 whose scene shows while no host is answering.
+    pub console: Option<String>,
+    pub backup_route: Option<IpAddr>,
+    // Other OSC receivers that get each cue re-sent.
+    pub forward_to: Vec<SocketAddr>,
+    pub always_on_top: bool,
+    pub theme: Theme,
+    pub text_scale: f32,
+    pub cue_text_scale: f32,
+    pub auto_fit: bool,
+    pub cue_timer: bool,
+    pub show_translation: bool,
+    pub loss_alert: LossAlert,
+    // Seconds of silence before the connection counts as lost.
+    pub lost_after_secs: u32,
+    pub clock_offset_minutes: i32,
+    pub web_display: bool,
+    pub web_port: u16,
+    pub brightness: f32,
+    // Minutes without input or cues before dimming; 0 turns it off.
+    pub auto_dim_mins: u32,
+    pub auto_dim_level: f32,
+    pub osc_control: bool,
+    pub osc_control_port: u16,
+    pub retry: RetryPolicy,
+    pub sheet_url: String,
+    pub sheet_refresh_mins: u32,
+    // Another display's web address, host:port, to sync cue notes and
+    // markers with; blank for none.
+    pub notes_peer: String,
+    pub show_name: String,
+    pub house_open: String,
+    pub curtain: String,
+    pub calls: Vec<ShowCall>,
+    // Which calls this display shows, e.g. "Dressing rooms".
+    pub display_group: String,
+    pub reminders: Vec<Reminder>,
+    pub profiles: Vec<Profile>,
+    // The profile last switched to or saved, named in the switchers.
+    pub profile: Option<String>,
+    pub button_pages: Vec<ButtonPage>,
+    pub operator: bool,
+    pub osc_presets: Vec<OscPreset>,
+    pub chime: Chime,
+    // Desktop notification per cue while the window is minimised or unfocused.
+    pub notify_cues: bool,
+    pub flash: CueFlash,
+    pub stage_display: StageDisplay,
+    pub mqtt: Mqtt,
+    pub midi: MidiOut,
+    pub art_net: ArtNet,
+    pub gpio: Gpio,
+    pub hooks: CommandHooks,
+    pub split: SplitSource,
+    pub libretto: LibrettoPanel,
+    pub show_report: ShowReport,
+    pub window: Option<WindowGeometry>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            host: String::new(),
+            osc_port: DEFAULT_OSC_PORT,
+            local_port: 0,
+            address: AddrChoice::default(),
+            interface: None,
+            transport: Transport::default(),
+            source: SourceKind::default(),
+            qlab_passcode: String::new(),
+            listen: false,
+            listen_port: 32100,
+            backup_host: None,
+            console: None,
+            backup_route: None,
+            forward_to: Vec::new(),
+            always_on_top: false,
+            theme: Theme::default(),
+            text_scale: 1.0,
+            cue_text_scale: 1.0,
+            auto_fit: false,
+            cue_timer: true,
+            show_translation: true,
+            loss_alert: LossAlert::default(),
+            lost_after_secs: DEFAULT_LOST_AFTER.as_secs() as u32,
+            clock_offset_minutes: 0,
+            web_display: false,
+            web_port: 8080,
+            brightness: 1.0,
+            auto_dim_mins: 0,
+            auto_dim_level: 0.3,
+            osc_control: false,
+            osc_control_port: 9000,
+            retry: RetryPolicy::default(),
+            sheet_url: String::new(),
+            sheet_refresh_mins: 5,
+            notes_peer: String::new(),
+            show_name: String::new(),
+            house_open: String::new(),
+            curtain: String::new(),
+            calls: ShowCall::defaults(),
+            display_group: String::new(),
+            reminders: Vec::new(),
+            profiles: Vec::new(),
+            profile: None,
+            button_pages: Vec::new(),
+            operator: false,
+            osc_presets: Vec::new(),
+            chime: Chime::default(),
+            notify_cues: false,
+            flash: CueFlash::default(),
+            stage_display: StageDisplay::default(),
+            mqtt: Mqtt::default(),
+            midi: MidiOut::default(),
+            art_net: ArtNet::default(),
+            gpio: Gpio::default(),
+            hooks: CommandHooks::default(),
+            split: SplitSource::default(),
+            libretto: LibrettoPanel::default(),
+            show_report: ShowReport::default(),
+            window: None,
+        }
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(base.join("theatremix-remote-display").join("config.toml"))
+}
+
+// A config that can't be parsed is set aside rather than overwritten, so a
+// hand-edit typo doesn't silently cost the user their settings.
+pub fn load_config(path: &Path) -> Config {
+    let Ok(text) = fs::read_to_string(path) else {
+        return migrate_legacy_config(path);
+    };
+    let config = toml::from_str::<toml::Table>(&text)
+        .map_err(|err| err.to_string())
+        .and_then(|table| migrate_config(path, table));
+    match config {
+        Ok(config) => config,
+        Err(err) => {
+            tracing::warn!("Ignoring {}: {err}", path.display());
+            let _ = fs::rename(path, path.with_extension("toml.bad"));
+            Config::default()
+        }
+    }
+}
+
+// Upgrades the raw table one version at a time. Any file we'd rewrite in a
+// different shape (older, or from a newer build whose fields we'd drop) is
+// copied aside first.
+fn migrate_config(path: &Path, mut table: toml::Table) -> Result<Config, String> {
+    run_migrations(path, &mut table, MIGRATIONS)?;
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(|err| err.to_string())
+}
+
+// Brings the table up to the version after the last of `migrations`.
+fn run_migrations(
+    path: &Path,
+    table: &mut toml::Table,
+    migrations: &[Migration],
+) -> Result<(), String> {
+    let current = migrations.len() as u32 + 1;
+    let version = table
+        .get("version")
+        .and_then(toml::Value::as_integer)
+        .and_then(|v| u32::try_from(v).ok())
+        .unwrap_or(1)
+        .max(1);
+    if version != current {
+        let backup = path.with_extension(format!("toml.v{version}.bak"));
+        fs::copy(path, &backup).map_err(|err| format!("backing up before migration: {err}"))?;
+        tracing::info!(
+            "Config is version {version}, this build uses {current}; saved a copy as {}",
+            backup.display()
+        );
+    }
+    for step in migrations.iter().skip(version as usize - 1) {
+        step(table);
+    }
+    table.insert("version".into(), i64::from(current).into());
+    Ok(())
+}
+
+// Before config.toml each setting lived in its own text file next to it.
+fn migrate_legacy_config(path: &Path) -> Config {
+    const LEGACY_FILES: &[&str] = &["host.txt", "local_port.txt", "osc_port.txt"];
+    let read = |name: &str| {
+        fs::read_to_string(path.with_file_name(name))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let mut config = Config::default();
+    if let Some(host) = read("host.txt") {
+        config.host = host;
+    }
+    if let Some(port) = read("local_port.txt").and_then(|p| p.parse().ok()) {
+        config.local_port = port;
+    }
+    if let Some(port) = read("osc_port.txt").and_then(|p| p.parse().ok()) {
+        config.osc_port = port;
+    }
+    let found = LEGACY_FILES
+        .iter()
+        .any(|name| path.with_file_name(name).exists());
+    if found && save_config(path, &config).is_ok() {
+        for name in LEGACY_FILES {
+            let _ = fs::remove_file(path.with_file_name(name));
+        }
+    }
+    config
+}
+
+fn startups_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(base.join("theatremix-remote-display").join("startups"))
+}
+
+// Counts starts that haven't yet run for STABLE_AFTER, this one included.
+pub fn record_startup() -> u32 {
+    let Some(path) = startups_path() else {
+        return 1;
+    };
+    let count = fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or(0)
+        + 1;
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let _ = fs::write(&path, count.to_string());
+    count
+}
+
+pub fn clear_startups() {
+    if let Some(path) = startups_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+pub fn save_config(path: &Path, config: &Config) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let text = toml::to_string_pretty(config).map_err(std::io::Error::other)?;
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_reach_their_groups() {
+        let mut call = ShowCall::new("Beginners", 5);
+        assert!(call.reaches(""));
+        assert!(call.reaches("FOH"));
+        call.groups = "Dressing rooms, Green room".to_string();
+        assert!(call.reaches("dressing rooms"));
+        assert!(call.reaches(" Green room "));
+        assert!(!call.reaches("FOH"));
+        assert!(!call.reaches(""));
+    }
+
+    #[test]
+    fn reminders_reach_their_groups() {
+        let mut reminder = Reminder::default();
+        assert!(reminder.reaches("FOH"));
+        reminder.groups = vec!["Dressing rooms".to_string(), " ".to_string()];
+        assert!(reminder.reaches(" dressing rooms"));
+        assert!(!reminder.reaches("FOH"));
+        assert!(!reminder.reaches(""));
+    }
+
+    #[test]
+    fn migrations_back_up_and_bump_the_version() {
+        let dir = std::env::temp_dir().join(format!("theatremix-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let text = "version = 1\naddress = \"10.0.0.1\"\n";
+        fs::write(&path, text).unwrap();
+
+        // A stand-in for a real step: version 2 renamed address to host.
+        let rename: Migration = |table| {
+            if let Some(address) = table.remove("address") {
+                table.insert("host".into(), address);
+            }
+        };
+        let mut table: toml::Table = toml::from_str(text).unwrap();
+        run_migrations(&path, &mut table, &[rename]).unwrap();
+        assert_eq!(table["version"].as_integer(), Some(2));
+        assert_eq!(table["host"].as_str(), Some("10.0.0.1"));
+        let backup = dir.join("config.toml.v1.bak");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), text);
+
+        // Already current: nothing to run and no second copy.
+        fs::remove_file(&backup).unwrap();
+        run_migrations(&path, &mut table, &[rename]).unwrap();
+        assert!(!backup.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,105 @@
+//! DMX levels over Art-Net as cues fire, for cue lights and backstage beacons.
+
+use crate::config::ArtNet;
+use crate::event::CueInfo;
+use crate::ui::parse_cue_color;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+pub const DMX_CHANNELS: usize = 512;
+
+const ARTNET_PORT: u16 = 6454;
+
+// Nodes hold their last frame for a few seconds at most, so the current
+// levels are sent again this often even when nothing changes.
+const REFRESH_EVERY: Duration = Duration::from_secs(1);
+
+pub type Levels = [u8; DMX_CHANNELS];
+
+// Sends the latest levels to the node (or broadcast address) until the
+// sender is dropped.
+pub fn spawn_artnet(target: IpAddr, universe: u16) -> Result<Sender<Levels>, String> {
+    let local: IpAddr = match target {
+        IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    let socket = UdpSocket::bind((local, 0))
+        .and_then(|s| s.set_broadcast(true).map(|_| s))
+        .map_err(|e| format!("Art-Net socket: {e}"))?;
+    let target = SocketAddr::new(target, ARTNET_PORT);
+    let (tx, rx) = mpsc::channel::<Levels>();
+    thread::spawn(move || {
+        let mut levels = [0; DMX_CHANNELS];
+        let mut sequence = 0u8;
+        // Only the first failure in a row is logged.
+        let mut failing = false;
+        loop {
+            match rx.recv_timeout(REFRESH_EVERY) {
+                Ok(new) => levels = new,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            // Zero means "no sequencing", so it is skipped on wrapping.
+            sequence = sequence.wrapping_add(1).max(1);
+            match socket.send_to(&art_dmx(universe, sequence, &levels), target) {
+                Ok(_) => failing = false,
+                Err(err) if !failing => {
+                    tracing::warn!("Art-Net to {target} failed: {err}");
+                    failing = true;
+                }
+                Err(_) => {}
+            }
+        }
+    });
+    Ok(tx)
+}
+
+// Levels for a fired cue: every matching rule in order, later ones winning,
+// then the cue's color if a color channel is set.
+pub fn cue_levels(settings: &ArtNet, cue: &CueInfo) -> Levels {
+    let mut levels = [0; DMX_CHANNELS];
+    let mut set = |channel: u16, values: &[u8]| {
+        let start = usize::from(channel.max(1)) - 1;
+        for (slot, value) in levels.iter_mut().skip(start).zip(values) {
+            *slot = *value;
+        }
+    };
+    for rule in &settings.rules {
+        let cue_matches = rule
+            .cue
+            .as_deref()
+            .is_none_or(|number| number.trim() == cue.number.trim());
+        let color_matches = rule.color.as_deref().is_none_or(|color| {
+            cue.color
+                .as_deref()
+                .is_some_and(|c| c.trim().eq_ignore_ascii_case(color.trim()))
+        });
+        if cue_matches && color_matches {
+            set(rule.channel, &rule.levels);
+        }
+    }
+    if settings.color_channel > 0
+        && let Some(color) = cue.color.as_deref().and_then(parse_cue_color)
+    {
+        set(settings.color_channel, &[color.r(), color.g(), color.b()]);
+    }
+    levels
+}
+
+// An ArtDmx packet, protocol version 14, carrying a full universe.
+fn art_dmx(universe: u16, sequence: u8, levels: &Levels) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(18 + DMX_CHANNELS);
+    packet.extend_from_slice(b"Art-Net\0");
+    packet.extend_from_slice(&0x5000u16.to_le_bytes());
+    packet.extend_from_slice(&14u16.to_be_bytes());
+    packet.push(sequence);
+    // Physical input port; informational only.
+    packet.push(0);
+    // Net, sub-net and universe as one 15-bit port address.
+    packet.extend_from_slice(&(universe & 0x7fff).to_le_bytes());
+    packet.extend_from_slice(&(DMX_CHANNELS as u16).to_be_bytes());
+    packet.extend_from_slice(levels);
+    packet
+}
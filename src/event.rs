@@ -0,0 +1,130 @@
+//! What the network thread tells the UI, and the cue data it carries.
+
+use chrono::{DateTime, Local};
+use rosc::{OscMessage, OscType};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub enum NetEvent {
+    CueFired(CueInfo),
+    NextCue(CueInfo),
+    // The cue already live when we subscribed; shown without firing anything.
+    CurrentCue(CueInfo),
+    // Seconds until the subscription lapses unless renewed.
+    SubscribeOk(u32),
+    SubscribeFail,
+    Thump,
+    Resumed,
+    RouteChanged(Route),
+    // True while the display is following the backup TheatreMix machine.
+    BackupHostActive(bool),
+    // True while the display shows the mixing console's scene instead.
+    ConsoleActive(bool),
+    // /display/hold from the display forwarding to this one.
+    Hold(bool),
+    NoReplies {
+        refused: bool,
+    },
+    // Every address the host resolved to, and the one in use.
+    Resolved {
+        addrs: Vec<IpAddr>,
+        using: Option<IpAddr>,
+    },
+    // True while unsolicited OSC is arriving on the listen port.
+    Pushed(bool),
+    // Subscribed, but nothing has arrived for the configured timeout.
+    ConnectionLost,
+    // When the next /subscribe goes out while we aren't subscribed.
+    RetryAt(Instant),
+    Stats(NetStats),
+    BindFailed(String),
+    CaptureSaved(Result<PathBuf, String>),
+    // Sent just before the CueFired it describes.
+    CueTiming(CueTiming),
+    // /subscribe to /subscribeok, as a stand-in for network latency.
+    RoundTrip(Duration),
+    Traffic(TrafficEntry),
+    // Whether a script is running, or why it didn't load.
+    ScriptLoaded(Result<bool, String>),
+    // Set by the script; an empty value removes the widget.
+    ScriptWidget {
+        name: String,
+        value: String,
+    },
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CueInfo {
+    pub number: String,
+    pub text: String,
+    pub color: Option<String>,
+}
+
+// When the network thread got a cue packet off the socket, finished
+// decoding it and handed it to the UI.
+#[derive(Clone, Copy, Debug)]
+pub struct CueTiming {
+    pub received: Instant,
+    pub decoded: Instant,
+    pub queued: Instant,
+}
+
+#[derive(Clone, Debug)]
+pub struct TrafficEntry {
+    pub at: DateTime<Local>,
+    pub outgoing: bool,
+    pub address: String,
+    pub args: String,
+}
+
+impl TrafficEntry {
+    pub fn new(msg: &OscMessage, outgoing: bool) -> TrafficEntry {
+        let args: Vec<String> = msg
+            .args
+            .iter()
+            .map(|arg| match arg {
+                OscType::Int(i) => i.to_string(),
+                OscType::Float(f) => f.to_string(),
+                OscType::String(s) => format!("{s:?}"),
+                other => format!("{other:?}"),
+            })
+            .collect();
+        TrafficEntry {
+            at: Local::now(),
+            outgoing,
+            address: msg.addr.clone(),
+            args: args.join(" "),
+        }
+    }
+}
+
+// Running totals since the network thread started, for the diagnostics
+// panel. Packets are counted once however many routes they went out on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetStats {
+    pub sent: u64,
+    pub received: u64,
+    pub thumps: u64,
+    pub missed_thumps: u64,
+    pub decode_failures: u64,
+}
+
+impl NetStats {
+    pub fn since(&self, base: &NetStats) -> NetStats {
+        NetStats {
+            sent: self.sent.saturating_sub(base.sent),
+            received: self.received.saturating_sub(base.received),
+            thumps: self.thumps.saturating_sub(base.thumps),
+            missed_thumps: self.missed_thumps.saturating_sub(base.missed_thumps),
+            decode_failures: self.decode_failures.saturating_sub(base.decode_failures),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Route {
+    Primary,
+    Backup,
+}
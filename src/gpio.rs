@@ -0,0 +1,89 @@
+//! Cue lights and buzzers on GPIO pins, for a Raspberry Pi running the display.
+
+use crate::config::{Gpio, GpioEvent};
+use std::sync::mpsc::Sender;
+
+// Only Linux ARM builds can drive pins; elsewhere the settings are kept but
+// turning the output on reports why it can't.
+pub const GPIO_SUPPORTED: bool = cfg!(all(
+    target_os = "linux",
+    any(target_arch = "arm", target_arch = "aarch64")
+));
+
+// Claims every configured pin, held off, and pulses them on each event until
+// the sender is dropped.
+#[cfg(all(target_os = "linux", any(target_arch = "arm", target_arch = "aarch64")))]
+pub fn spawn_gpio(settings: &Gpio) -> Result<Sender<GpioEvent>, String> {
+    use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+    use std::sync::mpsc::{self, RecvTimeoutError};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let mut chip =
+        Chip::new(&settings.chip).map_err(|e| format!("{}: {e}", settings.chip.display()))?;
+    // A pin shared by several pulses is claimed once, as the first one says.
+    let mut pins: Vec<(u32, LineHandle)> = Vec::new();
+    let mut pulses = Vec::new();
+    for pulse in &settings.pulses {
+        let idx = match pins.iter().position(|(pin, _)| *pin == pulse.pin) {
+            Some(idx) => idx,
+            None => {
+                let mut flags = LineRequestFlags::OUTPUT;
+                if pulse.active_low {
+                    flags |= LineRequestFlags::ACTIVE_LOW;
+                }
+                let handle = chip
+                    .get_line(pulse.pin)
+                    .and_then(|line| line.request(flags, 0, "theatremix-remote-display"))
+                    .map_err(|e| format!("Pin {}: {e}", pulse.pin))?;
+                pins.push((pulse.pin, handle));
+                pins.len() - 1
+            }
+        };
+        pulses.push((pulse.event, idx, Duration::from_millis(pulse.ms.into())));
+    }
+
+    let (tx, rx) = mpsc::channel::<GpioEvent>();
+    thread::spawn(move || {
+        // When each lit pin goes off again.
+        let mut off_at: Vec<Option<Instant>> = vec![None; pins.len()];
+        loop {
+            let next_off = off_at.iter().flatten().min().copied();
+            let event = match next_off {
+                Some(at) => match rx.recv_timeout(at.saturating_duration_since(Instant::now())) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match rx.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => break,
+                },
+            };
+            let now = Instant::now();
+            for (_, idx, length) in pulses.iter().filter(|(on, ..)| Some(*on) == event) {
+                if let Err(err) = pins[*idx].1.set_value(1) {
+                    tracing::warn!("GPIO pin {} not set: {err}", pins[*idx].0);
+                }
+                // Overlapping pulses on one pin run to the later end.
+                off_at[*idx] = off_at[*idx].max(Some(now + *length));
+            }
+            for (idx, at) in off_at.iter_mut().enumerate() {
+                if at.is_some_and(|at| at <= now) {
+                    let _ = pins[idx].1.set_value(0);
+                    *at = None;
+                }
+            }
+        }
+        // Nothing is left lit once the output is turned off.
+        for (_, handle) in &pins {
+            let _ = handle.set_value(0);
+        }
+    });
+    Ok(tx)
+}
+
+#[cfg(not(all(target_os = "linux", any(target_arch = "arm", target_arch = "aarch64"))))]
+pub fn spawn_gpio(_settings: &Gpio) -> Result<Sender<GpioEvent>, String> {
+    Err("GPIO needs a Linux ARM build".to_string())
+}
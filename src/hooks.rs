@@ -0,0 +1,71 @@
+//! Shell commands run on cues, connects and disconnects.
+
+use crate::config::CommandHooks;
+use crate::web::{HookEvent, fill_placeholders};
+use std::process::{Command, Stdio};
+use std::thread;
+
+// Fire and forget, like webhooks: the command runs on its own thread and
+// only a failure is logged.
+pub fn run_command_hook(hooks: &CommandHooks, event: HookEvent, values: &[(&str, &str)]) {
+    let template = match event {
+        HookEvent::Cue => &hooks.cue,
+        HookEvent::Connect => &hooks.connect,
+        HookEvent::Disconnect => &hooks.disconnect,
+    };
+    let Some(template) = template.as_deref().map(str::trim).filter(|t| !t.is_empty()) else {
+        return;
+    };
+    let line = fill_placeholders(template, values, shell_quote);
+    thread::spawn(move || {
+        let output = shell(&line)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output();
+        match output {
+            Ok(output) if !output.status.success() => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                tracing::warn!(
+                    "Hook `{line}` exited with {}: {}",
+                    output.status,
+                    stderr.lines().next().unwrap_or("")
+                );
+            }
+            Ok(_) => {}
+            Err(err) => tracing::warn!("Hook `{line}` not run: {err}"),
+        }
+    });
+}
+
+#[cfg(not(windows))]
+fn shell(line: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(line);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell(line: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut cmd = Command::new("cmd");
+    // cmd does its own parsing, so the line goes through untouched.
+    cmd.arg("/C").raw_arg(line).creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+// Values arrive already quoted as one argument, so cue text can't run
+// anything.
+#[cfg(not(windows))]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// cmd has no escape for a quote inside quotes and expands %VAR% even there,
+// so both are dropped.
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    let kept: String = value.chars().filter(|c| !matches!(c, '"' | '%')).collect();
+    format!("\"{kept}\"")
+}
@@ -0,0 +1,457 @@
+//! Session logs, the cue journal and end-of-show reports.
+
+use crate::config::ShowReport;
+use crate::event::CueInfo;
+use crate::state::CueState;
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Cue fires and operator markers for the SM's show report, appended to a
+// CSV file as they happen so a crash doesn't lose the evening.
+pub struct SessionLog {
+    pub path: Option<PathBuf>,
+}
+
+impl Default for SessionLog {
+    fn default() -> Self {
+        Self {
+            path: session_log_path(),
+        }
+    }
+}
+
+impl SessionLog {
+    pub fn record(&self, kind: &str, text: &str, at: DateTime<Local>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(err) = Self::append(path, kind, text, at) {
+            tracing::warn!("Session log {}: {err}", path.display());
+        }
+    }
+
+    pub fn append(path: &Path, kind: &str, text: &str, at: DateTime<Local>) -> csv::Result<()> {
+        let new_file = !path.exists();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let mut writer = csv::Writer::from_writer(file);
+        if new_file {
+            writer.write_record(["time", "kind", "text"])?;
+        }
+        let time = at.format("%Y-%m-%d %H:%M:%S").to_string();
+        writer.write_record([time.as_str(), kind, text])?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// A session marker, as shared with displays syncing notes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    pub at: DateTime<Local>,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub time: DateTime<Local>,
+    // `cue`, `current`, `next`, `connect`, `disconnect` or `marker`, whose
+    // note is the cue's text.
+    pub kind: String,
+    #[serde(default)]
+    pub cue: CueInfo,
+}
+
+// Days of journal kept, as for the logs.
+const JOURNAL_DAYS: u64 = 7;
+
+// Append-only record of everything the display has shown, one JSON object
+// per line and one file per day. Cue fires are synced to disk before they're
+// drawn so the record survives the app or machine dying mid-show.
+pub struct Journal {
+    dir: Option<PathBuf>,
+    day: NaiveDate,
+    file: Option<fs::File>,
+}
+
+impl Journal {
+    // Opens today's journal for appending and returns the latest day's
+    // entries, for the restore. A torn last line from a crash is skipped
+    // rather than failing the load. Days older than a week are removed.
+    pub fn open(dir: Option<PathBuf>) -> (Self, Vec<JournalEntry>) {
+        let today = Local::now().date_naive();
+        let Some(dir) = dir else {
+            return (
+                Self {
+                    dir: None,
+                    day: today,
+                    file: None,
+                },
+                Vec::new(),
+            );
+        };
+        let _ = fs::create_dir_all(&dir);
+        let days = journal_days(&dir);
+        // Earlier versions kept a single journal.jsonl beside the directory.
+        let single = dir.with_file_name("journal.jsonl");
+        let entries = match days.last() {
+            Some((_, latest)) => read_journal(latest),
+            None => read_journal(&single),
+        };
+        let _ = fs::remove_file(&single);
+        let oldest = today.checked_sub_days(Days::new(JOURNAL_DAYS));
+        for (day, path) in &days {
+            if oldest.is_some_and(|oldest| *day < oldest) {
+                let _ = fs::remove_file(path);
+            }
+        }
+        let mut journal = Self {
+            dir: Some(dir),
+            day: today,
+            file: None,
+        };
+        journal.file = journal.open_day();
+        (journal, entries)
+    }
+
+    fn open_day(&self) -> Option<fs::File> {
+        let path = journal_path(self.dir.as_ref()?, self.day);
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .inspect_err(|err| tracing::warn!("Journal {} not opened: {err}", path.display()))
+            .ok()
+    }
+
+    pub fn record(&mut self, kind: &str, cue: &CueInfo) {
+        self.record_at(kind, cue, Local::now());
+    }
+
+    pub fn record_at(&mut self, kind: &str, cue: &CueInfo, time: DateTime<Local>) {
+        // A show running past midnight carries on in the new day's file.
+        let today = Local::now().date_naive();
+        if self.dir.is_some() && today != self.day {
+            self.day = today;
+            self.file = self.open_day();
+        }
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let entry = JournalEntry {
+            time,
+            kind: kind.to_string(),
+            cue: cue.clone(),
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+        // One write per entry keeps lines whole even if we die mid-way.
+        let written = file.write_all(line.as_bytes()).and_then(|_| {
+            if kind == "cue" || kind == "current" {
+                file.sync_data()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = written {
+            tracing::warn!("Journal write failed: {err}");
+        }
+    }
+}
+
+pub fn read_journal(path: &Path) -> Vec<JournalEntry> {
+    fs::read_to_string(path)
+        .map(|text| {
+            text.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn journal_dir() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(base.join("theatremix-remote-display").join("journal"))
+}
+
+pub fn journal_path(dir: &Path, day: NaiveDate) -> PathBuf {
+    dir.join(format!("{}.jsonl", day.format("%Y-%m-%d")))
+}
+
+// Each day's journal, oldest first.
+fn journal_days(dir: &Path) -> Vec<(NaiveDate, PathBuf)> {
+    let Ok(files) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut days: Vec<(NaiveDate, PathBuf)> = files
+        .filter_map(|file| {
+            let path = file.ok()?.path();
+            let stem = path.file_name()?.to_str()?.strip_suffix(".jsonl")?;
+            let day = NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok()?;
+            Some((day, path))
+        })
+        .collect();
+    days.sort();
+    days
+}
+
+// Put back the last cues shown before a restart. They stay marked as last
+// known until the host sends a cue, so nothing looks live that isn't.
+pub fn restore_state(history: &[JournalEntry]) -> CueState {
+    let mut state = CueState::default();
+    for entry in history {
+        match entry.kind.as_str() {
+            "cue" | "current" => {
+                state.current = entry.cue.clone();
+                let age = (Local::now() - entry.time).to_std().unwrap_or_default();
+                state.current_since = Instant::now().checked_sub(age);
+                state.last_rx_at = Some(entry.time);
+                state.last_known = Some(entry.time);
+            }
+            "next" => {
+                state.next = entry.cue.clone();
+                state.last_known = Some(entry.time);
+            }
+            _ => {}
+        }
+    }
+    state
+}
+
+pub fn report_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    let name = format!("show-report-{}.csv", Local::now().format("%Y%m%d-%H%M%S"));
+    Some(
+        base.join("theatremix-remote-display")
+            .join("reports")
+            .join(name),
+    )
+}
+
+// The report's rows, header first, from one day's journal entries. A cue's
+// gap is since the previous cue in the report, blank for the first.
+// `cues`, when given, limits the cue rows to those numbers, as picked by the
+// act and cue range.
+pub fn show_report(
+    entries: &[JournalEntry],
+    report: &ShowReport,
+    cues: Option<&HashSet<String>>,
+) -> Result<Vec<Vec<String>>, String> {
+    let from = report_time(&report.from, "From")?;
+    let to = report_time(&report.to, "To")?;
+    let columns = report.columns;
+    let header: Vec<String> = [
+        (columns.time, "time"),
+        (columns.event, "event"),
+        (columns.cue, "cue"),
+        (columns.text, "text"),
+        (columns.since_previous, "since_previous"),
+    ]
+    .into_iter()
+    .filter(|(on, _)| *on)
+    .map(|(_, name)| name.to_string())
+    .collect();
+    if header.is_empty() {
+        return Err("Pick at least one column".to_string());
+    }
+    let mut rows = vec![header];
+    let mut previous: Option<DateTime<Local>> = None;
+    for entry in entries {
+        let wanted = match entry.kind.as_str() {
+            "cue" => report.cues && cues.is_none_or(|cues| cues.contains(&entry.cue.number)),
+            "marker" => report.markers,
+            "connect" | "disconnect" => report.connection,
+            _ => false,
+        };
+        let time = entry.time.time();
+        if !wanted || from.is_some_and(|from| time < from) || to.is_some_and(|to| time > to) {
+            continue;
+        }
+        let mut gap = String::new();
+        if entry.kind == "cue" {
+            if let Some(previous) = previous {
+                gap = format_elapsed((entry.time - previous).to_std().unwrap_or_default());
+            }
+            previous = Some(entry.time);
+        }
+        let row = [
+            (columns.time, entry.time.format("%H:%M:%S").to_string()),
+            (columns.event, entry.kind.clone()),
+            (columns.cue, entry.cue.number.clone()),
+            (columns.text, entry.cue.text.clone()),
+            (columns.since_previous, gap),
+        ];
+        rows.push(
+            row.into_iter()
+                .filter_map(|(on, value)| on.then_some(value))
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+pub fn format_elapsed(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+fn report_time(text: &str, label: &str) -> Result<Option<NaiveTime>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    NaiveTime::parse_from_str(text, "%H:%M")
+        .map(Some)
+        .map_err(|_| format!("{label} time should look like 19:30"))
+}
+
+pub fn write_show_report(path: &Path, rows: &[Vec<String>]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut writer = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+    for row in rows {
+        writer.write_record(row).map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+fn session_log_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    let name = format!("session-{}.csv", Local::now().format("%Y%m%d-%H%M%S"));
+    Some(
+        base.join("theatremix-remote-display")
+            .join("sessions")
+            .join(name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ReportColumns;
+    use chrono::TimeZone;
+
+    fn entry(hms: (u32, u32, u32), kind: &str, number: &str) -> JournalEntry {
+        JournalEntry {
+            time: Local
+                .with_ymd_and_hms(2026, 3, 14, hms.0, hms.1, hms.2)
+                .unwrap(),
+            kind: kind.to_string(),
+            cue: CueInfo {
+                number: number.to_string(),
+                text: format!("Cue {number}"),
+                color: None,
+            },
+        }
+    }
+
+    fn evening() -> Vec<JournalEntry> {
+        vec![
+            entry((19, 0, 0), "connect", ""),
+            entry((19, 30, 0), "cue", "1"),
+            entry((19, 31, 5), "current", "1"),
+            entry((19, 32, 0), "marker", ""),
+            entry((19, 35, 0), "cue", "2"),
+            entry((21, 0, 0), "cue", "3"),
+            entry((22, 0, 0), "disconnect", ""),
+        ]
+    }
+
+    #[test]
+    fn default_report_lists_cues() {
+        let rows = show_report(&evening(), &ShowReport::default(), None).unwrap();
+        assert_eq!(rows[0], ["time", "cue", "text", "since_previous"]);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[1], ["19:30:00", "1", "Cue 1", ""]);
+        assert_eq!(rows[2][3], format_elapsed(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn range_kinds_and_columns() {
+        let mut report = ShowReport {
+            from: "19:31".to_string(),
+            to: "21:59".to_string(),
+            markers: true,
+            connection: true,
+            ..ShowReport::default()
+        };
+        report.columns.event = true;
+        report.columns.text = false;
+        let rows = show_report(&evening(), &report, None).unwrap();
+        assert_eq!(rows[0], ["time", "event", "cue", "since_previous"]);
+        let events: Vec<&str> = rows[1..].iter().map(|row| row[1].as_str()).collect();
+        assert_eq!(events, ["marker", "cue", "cue"]);
+        // The gap is measured within the report, so its first cue has none.
+        assert_eq!(rows[2][3], "");
+    }
+
+    #[test]
+    fn bad_report_settings() {
+        let report = ShowReport {
+            from: "7.30pm".to_string(),
+            ..ShowReport::default()
+        };
+        assert!(show_report(&evening(), &report, None).is_err());
+        let report = ShowReport {
+            columns: ReportColumns {
+                time: false,
+                event: false,
+                cue: false,
+                text: false,
+                since_previous: false,
+            },
+            ..ShowReport::default()
+        };
+        assert!(show_report(&evening(), &report, None).is_err());
+    }
+
+    #[test]
+    fn report_keeps_to_the_chosen_cues() {
+        let cues = HashSet::from(["2".to_string(), "3".to_string()]);
+        let rows = show_report(&evening(), &ShowReport::default(), Some(&cues)).unwrap();
+        let numbers: Vec<&str> = rows[1..].iter().map(|row| row[1].as_str()).collect();
+        assert_eq!(numbers, ["2", "3"]);
+    }
+
+    #[test]
+    fn journal_keeps_a_file_per_day_for_a_week() {
+        let base = std::env::temp_dir().join(format!("theatremix-journal-{}", std::process::id()));
+        let dir = base.join("journal");
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&dir).unwrap();
+        let today = Local::now().date_naive();
+        let day = |n| today.checked_sub_days(Days::new(n)).unwrap();
+        let line =
+            |number| serde_json::to_string(&entry((19, 30, 0), "cue", number)).unwrap() + "\n";
+        fs::write(journal_path(&dir, day(10)), line("1")).unwrap();
+        fs::write(journal_path(&dir, day(1)), line("2")).unwrap();
+        fs::write(dir.with_file_name("journal.jsonl"), line("3")).unwrap();
+
+        let (mut journal, history) = Journal::open(Some(dir.clone()));
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].cue.number, "2");
+        assert!(!journal_path(&dir, day(10)).exists());
+        assert!(!dir.with_file_name("journal.jsonl").exists());
+
+        journal.record("cue", &CueInfo::default());
+        assert_eq!(read_journal(&journal_path(&dir, today)).len(), 1);
+        let _ = fs::remove_dir_all(&base);
+    }
+}
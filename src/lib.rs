@@ -0,0 +1,24 @@
+//! TheatreMix Remote Display as a library: the OSC protocol, network thread and
+//! settings are independent of the egui frontend in [`ui`].
+
+pub mod audio;
+pub mod config;
+pub mod dmx;
+pub mod event;
+pub mod gpio;
+pub mod hooks;
+pub mod journal;
+pub mod libretto;
+pub mod midi;
+pub mod mqtt;
+pub mod net;
+pub mod paging;
+pub mod protocol;
+pub mod qlab;
+pub mod script;
+pub mod sheet;
+pub mod sim;
+pub mod state;
+pub mod ui;
+pub mod web;
+pub mod x32;
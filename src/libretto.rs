@@ -0,0 +1,104 @@
+//! A calling script or libretto with cue markers, followed as cues fire.
+
+use std::fs;
+use std::path::Path;
+
+pub struct LibrettoLine {
+    pub text: String,
+    // Markdown headings, shown larger.
+    pub heading: bool,
+    // Cue numbers marked on this line.
+    pub cues: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct Libretto {
+    pub lines: Vec<LibrettoLine>,
+}
+
+impl Libretto {
+    pub fn load(path: &Path) -> Result<Libretto, String> {
+        fs::read_to_string(path)
+            .map(|text| Libretto::parse(&text))
+            .map_err(|e| format!("Read failed: {e}"))
+    }
+
+    // Plain text or Markdown, with cues marked [Q 12] or [Q12] anywhere in a
+    // line. Markers stay in the text so the caller sees where they fall.
+    pub fn parse(text: &str) -> Libretto {
+        let lines = text
+            .lines()
+            .map(|line| {
+                let trimmed = line.trim_start();
+                let heading = trimmed.starts_with('#');
+                LibrettoLine {
+                    text: if heading {
+                        trimmed.trim_start_matches('#').trim().to_string()
+                    } else {
+                        line.trim_end().to_string()
+                    },
+                    heading,
+                    cues: markers(line),
+                }
+            })
+            .collect();
+        Libretto { lines }
+    }
+
+    pub fn marker_count(&self) -> usize {
+        self.lines.iter().map(|line| line.cues.len()).sum()
+    }
+
+    pub fn headings(&self) -> impl Iterator<Item = &str> {
+        self.lines
+            .iter()
+            .filter(|line| line.heading)
+            .map(|line| line.text.as_str())
+    }
+
+    // The cues marked between this heading and the next.
+    pub fn cues_under(&self, heading: &str) -> Option<Vec<String>> {
+        let start = self
+            .lines
+            .iter()
+            .position(|line| line.heading && line.text == heading)?;
+        Some(
+            self.lines[start + 1..]
+                .iter()
+                .take_while(|line| !line.heading)
+                .flat_map(|line| line.cues.iter().cloned())
+                .collect(),
+        )
+    }
+
+    // The first line marked with this cue.
+    pub fn line_for(&self, number: &str) -> Option<usize> {
+        if number.is_empty() {
+            return None;
+        }
+        self.lines
+            .iter()
+            .position(|line| line.cues.iter().any(|cue| cue == number))
+    }
+}
+
+// A marker's number has to start with a digit, so [Quietly] and Markdown
+// links aren't taken for cues.
+fn markers(line: &str) -> Vec<String> {
+    let mut cues = Vec::new();
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        rest = &rest[open + 1..];
+        let Some(close) = rest.find(']') else {
+            break;
+        };
+        if let Some(number) = rest[..close].trim().strip_prefix(['Q', 'q']) {
+            let number = number.trim();
+            if number.starts_with(|c: char| c.is_ascii_digit()) {
+                cues.push(number.to_string());
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+    cues
+}
@@ -1,402 +1,226 @@
 #![cfg_attr(windows, windows_subsystem = "windows")]
 
+use clap::Parser;
 use eframe::egui::ViewportBuilder;
-use eframe::{App, Frame, egui};
-use rosc::{OscMessage, OscPacket, OscType};
-use std::fs;
-use std::net::{SocketAddr, UdpSocket};
 use std::path::PathBuf;
-use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
-use std::thread;
-use std::time::{Duration, Instant};
-
-#[derive(Clone, Debug, Default)]
-struct CueInfo {
-    number: String,
-    text: String,
-    color: Option<String>,
-}
-
-#[derive(Clone, Debug, Default)]
-struct CueState {
-    current: CueInfo,
-    next: CueInfo,
-    connected: bool,
-    last_rx: Option<Instant>,
-}
-
-enum NetEvent {
-    CueFired(CueInfo),
-    #[allow(dead_code)]
-    SubscribeOk(u32),
-    SubscribeFail,
-    Thump,
-}
-
-enum NetCmd {
-    SetHost(String),
-}
-
-struct TheatreMixApp {
-    state: CueState,
-    rx: Receiver<NetEvent>,
-    cmd_tx: Sender<NetCmd>,
-    host: String,
-    status: String,
-    host_edit: String,
-    always_on_top: bool,
-    config_path: Option<PathBuf>,
-    show_settings: bool,
+use std::sync::mpsc;
+use theatremix_remote_display::config::{
+    Config, LayoutPreset, config_path, load_config, record_startup, save_config,
+};
+use theatremix_remote_display::event::NetEvent;
+use theatremix_remote_display::net::{NetCmd, Waker, spawn_osc_thread};
+use theatremix_remote_display::script::script_path;
+use theatremix_remote_display::sim::spawn_simulator;
+use theatremix_remote_display::ui::{TheatreMixApp, load_icon};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// TheatreMix host to connect to; remembered for next time
+    host: Option<String>,
+    /// TheatreMix OSC port; remembered for next time
+    #[arg(long)]
+    port: Option<u16>,
+    /// Start fullscreen
+    #[arg(long)]
+    fullscreen: bool,
+    /// Fullscreen and always on top, with the top bar and status row hidden (F11 leaves)
+    #[arg(long)]
+    kiosk: bool,
+    /// Read and write settings from this file instead of the default config.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// off, error, warn, info, debug or trace
+    #[arg(long, default_value = "info")]
+    log_level: LevelFilter,
+    /// Which panels to open at start
+    #[arg(long, value_enum, default_value_t)]
+    layout: LayoutPreset,
+    /// Run without a window, serving the web display only
+    #[arg(long)]
+    headless: bool,
+    /// Start with default settings and no outputs, as after repeated crashes
+    #[arg(long)]
+    safe_mode: bool,
+    /// Connect to a built-in fake TheatreMix that fires a short cue list; settings aren't saved
+    #[arg(long)]
+    simulate: bool,
+}
+
+// Starts that never ran for STABLE_AFTER before this many in a row put the
+// next start into safe mode.
+const CRASH_LIMIT: u32 = 3;
+
+// Logs go to stderr and to a file per day in the config directory's logs/,
+// keeping a week. The guard flushes the file when main returns.
+fn init_logging(level: LevelFilter) -> Option<tracing_appender::non_blocking::WorkerGuard> {
+    let stderr = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let file = dirs::config_dir().and_then(|base| {
+        tracing_appender::rolling::Builder::new()
+            .rotation(tracing_appender::rolling::Rotation::DAILY)
+            .filename_prefix("theatremix")
+            .filename_suffix("log")
+            .max_log_files(7)
+            .build(base.join("theatremix-remote-display").join("logs"))
+            .ok()
+    });
+    let (file_layer, guard) = match file {
+        Some(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    tracing_subscriber::registry()
+        .with(level)
+        .with(stderr)
+        .with(file_layer)
+        .init();
+    guard
+}
+
+// Without an activity assertion macOS App Naps the process once the window is
+// occluded, which coalesces the network thread's timers and lets thumps lapse.
+#[cfg(target_os = "macos")]
+fn disable_app_nap() {
+    use objc2_foundation::{NSActivityOptions, NSProcessInfo, NSString};
+
+    let reason = NSString::from_str("Receiving live TheatreMix OSC");
+    let activity = NSProcessInfo::processInfo().beginActivityWithOptions_reason(
+        NSActivityOptions::UserInitiatedAllowingIdleSystemSleep
+            | NSActivityOptions::LatencyCritical,
+        &reason,
+    );
+    // Held for the lifetime of the process.
+    std::mem::forget(activity);
 }
 
-impl TheatreMixApp {
-    fn new(
-        host: String,
-        rx: Receiver<NetEvent>,
-        cmd_tx: Sender<NetCmd>,
-        config_path: Option<PathBuf>,
-    ) -> Self {
-        let mut state = CueState::default();
-        state.next.text = "(not provided by OSC)".to_string();
-        Self {
-            state,
-            rx,
-            cmd_tx,
-            host,
-            status: "Connecting...".to_string(),
-            host_edit: String::new(),
-            always_on_top: false,
-            config_path,
-            show_settings: false,
-        }
+fn main() -> eframe::Result<()> {
+    #[cfg(target_os = "macos")]
+    disable_app_nap();
+
+    let cli = Cli::parse();
+    let _log_guard = init_logging(cli.log_level);
+
+    let attempts = record_startup();
+    let safe_mode = cli.safe_mode || attempts > CRASH_LIMIT;
+    let mut cfg_path = cli.config.or_else(config_path);
+    let mut config = cfg_path.as_deref().map(load_config).unwrap_or_default();
+    if safe_mode {
+        tracing::warn!(
+            "Starting in safe mode after {} unfinished starts",
+            attempts - 1
+        );
+        // Keep only what's needed to reach the host; never write back.
+        config = Config {
+            host: config.host,
+            osc_port: config.osc_port,
+            local_port: config.local_port,
+            address: config.address,
+            interface: config.interface,
+            transport: config.transport,
+            source: config.source,
+            qlab_passcode: config.qlab_passcode,
+            ..Config::default()
+        };
+        cfg_path = None;
     }
-
-    fn apply_event(&mut self, ev: NetEvent) {
-        match ev {
-            NetEvent::CueFired(info) => {
-                self.state.current = info;
-                self.state.last_rx = Some(Instant::now());
-            }
-            NetEvent::SubscribeOk(_) => {
-                self.state.connected = true;
-                self.status = "Subscribed".to_string();
-            }
-            NetEvent::SubscribeFail => {
-                self.state.connected = false;
-                self.status = "Subscription failed".to_string();
-            }
-            NetEvent::Thump => {
-                self.state.last_rx = Some(Instant::now());
-            }
+    let first_run = !cli.headless && cli.host.is_none() && config.host.is_empty();
+    if cli.host.is_some() || cli.port.is_some() {
+        if let Some(host) = cli.host {
+            config.host = host;
         }
-    }
-}
-
-impl App for TheatreMixApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        while let Ok(ev) = self.rx.try_recv() {
-            self.apply_event(ev);
+        if let Some(port) = cli.port {
+            config.osc_port = port;
         }
-
-        let connected = self.state.connected;
-        let last_rx = self.state.last_rx;
-        let host = self.host.clone();
-        let status = self.status.clone();
-
-        egui::TopBottomPanel::top("top_bar").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.heading("TheatreMix");
-                ui.add_space(8.0);
-                if ui.button("Settings").clicked() {
-                    self.show_settings = true;
-                }
-            });
-        });
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.label(format!("Host: {host}"));
-                ui.separator();
-                ui.label(format!(
-                    "Status: {status}{}",
-                    if connected { "" } else { " (waiting)" }
-                ));
-                ui.separator();
-                if let Some(t) = last_rx {
-                    let age = t.elapsed().as_secs_f32();
-                    ui.label(format!("Last OSC: {:.1}s ago", age));
-                } else {
-                    ui.label("Last OSC: n/a");
-                }
-            });
-            ui.add_space(6.0);
-
-            ui.label("Current Cue");
-            cue_block(ui, &self.state.current);
-        });
-
-        let mut settings_open = self.show_settings;
-        let mut close_clicked = false;
-        egui::Window::new("Settings")
-            .open(&mut settings_open)
-            .collapsible(false)
-            .resizable(false)
-            .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("TheatreMix Host");
-                    if self.host_edit.is_empty() {
-                        self.host_edit = self.host.clone();
-                    }
-                    ui.text_edit_singleline(&mut self.host_edit);
-                });
-
-                ui.horizontal(|ui| {
-                    if ui.button("Apply").clicked() {
-                        let new_host = self.host_edit.trim().to_string();
-                        if !new_host.is_empty() && new_host != self.host {
-                            self.host = new_host.clone();
-                            let _ = self.cmd_tx.send(NetCmd::SetHost(new_host));
-                            self.status = "Reconnecting...".to_string();
-                            self.state.connected = false;
-                            if let Some(path) = &self.config_path {
-                                let _ = save_host(path, &self.host);
-                            }
-                        }
-                    }
-                    if ui.button("Close").clicked() {
-                        close_clicked = true;
-                    }
-                });
-
-                ui.separator();
-
-                let mut on_top = self.always_on_top;
-                if ui.checkbox(&mut on_top, "Always on top").changed() {
-                    self.always_on_top = on_top;
-                    let level = if on_top {
-                        egui::WindowLevel::AlwaysOnTop
-                    } else {
-                        egui::WindowLevel::Normal
-                    };
-                    ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
-                }
-            });
-        if close_clicked {
-            settings_open = false;
+        if let Some(path) = &cfg_path
+            && let Err(err) = save_config(path, &config)
+        {
+            tracing::warn!("Settings not saved to {}: {err}", path.display());
         }
-        self.show_settings = settings_open;
-
-        // No auto-resize: keep the window size stable to avoid event-loop hangs.
-
-        ctx.request_repaint_after(Duration::from_millis(100));
     }
-}
-
-fn cue_block(ui: &mut egui::Ui, cue: &CueInfo) {
-    let title = if cue.number.is_empty() {
-        "—".to_string()
-    } else {
-        cue.number.clone()
-    };
-
-    let text = if cue.text.is_empty() {
-        "—".to_string()
-    } else {
-        cue.text.clone()
-    };
-
-    ui.horizontal(|ui| {
-        ui.add(
-            egui::Label::new(
-                egui::RichText::new(format!("Cue {title}"))
-                    .size(26.0)
-                    .strong(),
-            )
-            .wrap(),
-        );
-        ui.add(egui::Label::new(egui::RichText::new(text).size(20.0)).wrap());
-    });
-
-    ui.label(format!("Color: {}", cue.color.as_deref().unwrap_or("—")));
-}
-
-fn spawn_osc_thread(host: String, tx: Sender<NetEvent>, cmd_rx: Receiver<NetCmd>) {
-    thread::spawn(move || {
-        let local_addr: SocketAddr = "0.0.0.0:0".parse().unwrap();
-        let mut current_host = host;
-        let mut socket = bind_socket(local_addr, &current_host);
-
-        let mut last_subscribe = Instant::now() - Duration::from_secs(10);
-        let mut subscription_expiry = 0u32;
-        let mut last_thump = Instant::now() - Duration::from_secs(10);
-
-        loop {
-            match cmd_rx.try_recv() {
-                Ok(NetCmd::SetHost(new_host)) => {
-                    current_host = new_host;
-                    socket = bind_socket(local_addr, &current_host);
-                    subscription_expiry = 0;
-                    last_subscribe = Instant::now() - Duration::from_secs(10);
-                    last_thump = Instant::now() - Duration::from_secs(10);
-                }
-                Err(TryRecvError::Empty) => {}
-                Err(TryRecvError::Disconnected) => break,
-            }
-
-            let subscribe_interval = if subscription_expiry > 0 {
-                Duration::from_secs((subscription_expiry / 2).max(2) as u64)
-            } else {
-                Duration::from_secs(2)
-            };
-
-            if last_subscribe.elapsed() >= subscribe_interval {
-                send_osc(&socket, "/subscribe", &[]);
-                last_subscribe = Instant::now();
+    if cli.simulate {
+        match spawn_simulator() {
+            Ok(port) => {
+                tracing::info!("Simulated TheatreMix on 127.0.0.1:{port}");
+                config.host = "127.0.0.1".to_string();
+                config.osc_port = port;
+                config.backup_host = None;
+                config.backup_route = None;
+                // Keep the real host and port in the saved settings.
+                cfg_path = None;
             }
-
-            if last_thump.elapsed() >= Duration::from_secs(2) {
-                // Keep session alive
-                send_osc(&socket, "/thump", &[]);
-                last_thump = Instant::now();
-            }
-
-            let mut buf = [0u8; 1536];
-            match socket.recv(&mut buf) {
-                Ok(n) => {
-                    if let Ok((_, packet)) = rosc::decoder::decode_udp(&buf[..n]) {
-                        match packet {
-                            OscPacket::Message(msg) => {
-                                if let Some(ev) = handle_message(msg, &mut subscription_expiry) {
-                                    let _ = tx.send(ev);
-                                }
-                            }
-                            OscPacket::Bundle(bundle) => {
-                                for pkt in bundle.content {
-                                    if let OscPacket::Message(msg) = pkt {
-                                        if let Some(ev) =
-                                            handle_message(msg, &mut subscription_expiry)
-                                        {
-                                            let _ = tx.send(ev);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(_) => {
-                    // timeout or transient error; continue
-                }
-            }
-
-            thread::sleep(Duration::from_millis(100));
+            Err(err) => tracing::error!("Simulator not started: {err}"),
         }
-    });
-}
-
-fn bind_socket(local_addr: SocketAddr, host: &str) -> UdpSocket {
-    let remote_addr: SocketAddr = format!("{host}:32000").parse().unwrap();
-    let socket = UdpSocket::bind(local_addr).expect("bind UDP socket");
-    socket
-        .set_read_timeout(Some(Duration::from_millis(200)))
-        .ok();
-    socket.connect(remote_addr).expect("connect UDP socket");
-    socket
-}
-
-fn config_path() -> Option<PathBuf> {
-    let base = dirs::config_dir()?;
-    Some(base.join("theatremix-remote-display").join("host.txt"))
-}
-
-fn load_host(path: &PathBuf) -> Option<String> {
-    fs::read_to_string(path)
-        .ok()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-}
+    }
+    if config.host.is_empty() {
+        config.host = "127.0.0.1".to_string();
+    }
 
-fn save_host(path: &PathBuf, host: &str) -> std::io::Result<()> {
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
+    let (tx, rx) = mpsc::channel::<NetEvent>();
+    let (cmd_tx, cmd_rx) = mpsc::channel::<NetCmd>();
+    let waker = Waker::default();
+    spawn_osc_thread(
+        config.host.clone(),
+        config.local_port,
+        config.osc_port,
+        tx,
+        cmd_rx,
+        waker.clone(),
+    );
+    // Safe mode runs without the user script, as it does without webhooks.
+    if !safe_mode {
+        let _ = cmd_tx.send(NetCmd::LoadScript(script_path()));
     }
-    fs::write(path, host)
-}
 
-fn handle_message(msg: OscMessage, subscription_expiry: &mut u32) -> Option<NetEvent> {
-    match msg.addr.as_str() {
-        "/subscribeok" => {
-            if let Some(OscType::Int(exp)) = msg.args.get(0) {
-                *subscription_expiry = (*exp).max(2) as u32;
-                return Some(NetEvent::SubscribeOk(*subscription_expiry));
-            }
-            None
-        }
-        "/subscribefail" => Some(NetEvent::SubscribeFail),
-        "/thump" => Some(NetEvent::Thump),
-        "/cuefired" => {
-            let mut info = CueInfo::default();
-            if let Some(OscType::String(num)) = msg.args.get(0) {
-                info.number = num.clone();
-            }
-            if let Some(OscType::String(text)) = msg.args.get(1) {
-                info.text = text.clone();
-            }
-            if let Some(OscType::String(color)) = msg.args.get(2) {
-                info.color = Some(color.clone());
-            }
-            Some(NetEvent::CueFired(info))
+    if cli.headless {
+        let mut app = TheatreMixApp::new(config, rx, cmd_tx, cfg_path, false);
+        if safe_mode {
+            app.enter_safe_mode();
         }
-        _ => None,
+        app.run_headless();
+        return Ok(());
     }
-}
 
-fn send_osc(socket: &UdpSocket, addr: &str, args: &[OscType]) {
-    let msg = OscMessage {
-        addr: addr.to_string(),
-        args: args.to_vec(),
-    };
-    if let Ok(buf) = rosc::encoder::encode(&OscPacket::Message(msg)) {
-        let _ = socket.send(&buf);
+    let mut viewport = ViewportBuilder::default()
+        .with_inner_size([720.0, 300.0])
+        .with_icon(load_icon());
+    if config.always_on_top || cli.kiosk {
+        viewport = viewport.with_always_on_top();
     }
-}
-
-fn load_icon() -> egui::IconData {
-    let bytes = include_bytes!("../assets/Mac.png");
-    let image = image::load_from_memory(bytes)
-        .expect("load icon")
-        .to_rgba8();
-    let (width, height) = image.dimensions();
-    egui::IconData {
-        rgba: image.into_raw(),
-        width,
-        height,
+    if let Some(window) = config.window {
+        viewport = viewport
+            .with_position([window.x, window.y])
+            .with_inner_size([window.width, window.height])
+            .with_maximized(window.maximized);
     }
-}
-
-fn main() -> eframe::Result<()> {
-    let arg_host = std::env::args().nth(1);
-    let cfg_path = config_path();
-    let stored_host = cfg_path.as_ref().and_then(load_host);
-    let host = arg_host
-        .clone()
-        .or(stored_host)
-        .unwrap_or_else(|| "127.0.0.1".to_string());
-    if let (Some(path), Some(arg)) = (&cfg_path, arg_host) {
-        let _ = save_host(path, &arg);
+    let restore_fullscreen = config.window.is_some_and(|w| w.fullscreen);
+    if cli.fullscreen || cli.kiosk || restore_fullscreen {
+        viewport = viewport.with_fullscreen(true);
     }
-
-    let (tx, rx) = mpsc::channel::<NetEvent>();
-    let (cmd_tx, cmd_rx) = mpsc::channel::<NetCmd>();
-    spawn_osc_thread(host.clone(), tx, cmd_rx);
-
-    let mut native_options = eframe::NativeOptions::default();
-    native_options.viewport = ViewportBuilder::default()
-        .with_inner_size([720.0, 200.0])
-        .with_icon(load_icon());
+    let native_options = eframe::NativeOptions {
+        viewport,
+        ..Default::default()
+    };
     eframe::run_native(
         "TheatreMix Remote Display",
         native_options,
-        Box::new(|_cc| Ok(Box::new(TheatreMixApp::new(host, rx, cmd_tx, cfg_path)))),
+        Box::new(|cc| {
+            let ctx = cc.egui_ctx.clone();
+            waker.set(move || ctx.request_repaint());
+            let mut app = TheatreMixApp::new(config, rx, cmd_tx, cfg_path, first_run);
+            app.kiosk = cli.kiosk;
+            if safe_mode {
+                app.enter_safe_mode();
+            }
+            app.show_cue_list = matches!(cli.layout, LayoutPreset::CueList);
+            app.apply_appearance(&cc.egui_ctx);
+            Ok(Box::new(app))
+        }),
     )
 }
@@ -0,0 +1,85 @@
+//! MIDI output on cue fires, for video and lighting systems without OSC.
+
+use crate::config::{MidiMessage, MidiOut};
+use crate::event::CueInfo;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+const CLIENT_NAME: &str = "TheatreMix Remote Display";
+
+// Output ports as the system names them, for the Settings picker.
+pub fn midi_ports() -> Vec<String> {
+    let Ok(output) = midir::MidiOutput::new(CLIENT_NAME) else {
+        return Vec::new();
+    };
+    output
+        .ports()
+        .iter()
+        .filter_map(|port| output.port_name(port).ok())
+        .collect()
+}
+
+// Owns the connection to the named port. Dropping the sender closes it.
+pub fn spawn_midi_out(port_name: &str) -> Result<Sender<Vec<u8>>, String> {
+    let output = midir::MidiOutput::new(CLIENT_NAME).map_err(|e| e.to_string())?;
+    let port = output
+        .ports()
+        .into_iter()
+        .find(|port| output.port_name(port).is_ok_and(|name| name == port_name))
+        .ok_or_else(|| format!("No MIDI port {port_name}"))?;
+    let mut conn = output
+        .connect(&port, "cues")
+        .map_err(|e| format!("MIDI port {port_name}: {e}"))?;
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+    let name = port_name.to_string();
+    thread::spawn(move || {
+        for bytes in rx {
+            if let Err(err) = conn.send(&bytes) {
+                tracing::warn!("MIDI send to {name} failed: {err}");
+            }
+        }
+        conn.close();
+    });
+    Ok(tx)
+}
+
+// The bytes to send for a fired cue, if any; a program change needs a whole
+// cue number that fits.
+pub fn cue_messages(settings: &MidiOut, cue: &CueInfo) -> Vec<Vec<u8>> {
+    let channel = settings.channel.clamp(1, 16) - 1;
+    match settings.message {
+        MidiMessage::MscGo => vec![msc_go(settings.device_id, settings.command_format, cue)],
+        MidiMessage::Note => vec![
+            vec![
+                0x90 | channel,
+                settings.note & 0x7f,
+                settings.velocity.clamp(1, 127),
+            ],
+            vec![0x80 | channel, settings.note & 0x7f, 0],
+        ],
+        MidiMessage::ProgramChange => match cue.number.trim().parse::<u8>() {
+            Ok(program) if program < 128 => vec![vec![0xc0 | channel, program]],
+            _ => Vec::new(),
+        },
+    }
+}
+
+// MIDI Show Control GO with the cue number, which MSC allows only digits and
+// points in; without one the receiver takes its next cue.
+fn msc_go(device_id: u8, command_format: u8, cue: &CueInfo) -> Vec<u8> {
+    let mut msg = vec![
+        0xf0,
+        0x7f,
+        device_id & 0x7f,
+        0x02,
+        command_format & 0x7f,
+        0x01,
+    ];
+    msg.extend(
+        cue.number
+            .bytes()
+            .filter(|b| b.is_ascii_digit() || *b == b'.'),
+    );
+    msg.push(0xf7);
+    msg
+}
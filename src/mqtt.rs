@@ -0,0 +1,104 @@
+//! Cue state published to an MQTT broker.
+
+use crate::config::Mqtt;
+use crate::event::CueInfo;
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use std::net::{IpAddr, SocketAddr};
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_MQTT_PORT: u16 = 1883;
+
+// Pause between attempts while the broker can't be reached.
+const RECONNECT_AFTER: Duration = Duration::from_secs(5);
+
+// Publishes each fired cue to the configured topics. Dropping it ends the
+// connection thread.
+pub struct MqttPublisher {
+    client: Client,
+    settings: Mqtt,
+}
+
+impl MqttPublisher {
+    pub fn start(settings: &Mqtt) -> Result<MqttPublisher, String> {
+        let (host, port) = broker_addr(&settings.broker)?;
+        let id = format!("theatremix-display-{}", std::process::id());
+        let mut options = MqttOptions::new(id, host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        let (client, mut connection) = Client::new(options, 16);
+        let broker = settings.broker.trim().to_string();
+        thread::spawn(move || {
+            // Only the first failure in a row is logged.
+            let mut failing = false;
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        tracing::info!("MQTT connected to {broker}");
+                        failing = false;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        if !failing {
+                            tracing::warn!("MQTT broker {broker}: {err}");
+                            failing = true;
+                        }
+                        thread::sleep(RECONNECT_AFTER);
+                    }
+                }
+            }
+        });
+        Ok(MqttPublisher {
+            client,
+            settings: settings.clone(),
+        })
+    }
+
+    pub fn publish(&self, cue: &CueInfo) {
+        let values = [
+            (&self.settings.number_topic, cue.number.as_str()),
+            (&self.settings.text_topic, cue.text.as_str()),
+            (
+                &self.settings.color_topic,
+                cue.color.as_deref().unwrap_or(""),
+            ),
+        ];
+        for (topic, value) in values {
+            let topic = topic.trim();
+            if topic.is_empty() {
+                continue;
+            }
+            // Never blocks; a full queue means the broker is gone anyway.
+            let sent = self.client.try_publish(
+                topic,
+                QoS::AtLeastOnce,
+                self.settings.retain,
+                value.as_bytes().to_vec(),
+            );
+            if let Err(err) = sent {
+                tracing::warn!("MQTT publish to {topic} failed: {err}");
+            }
+        }
+    }
+}
+
+// `host`, `host:port`, an IP or `[v6]:port`.
+fn broker_addr(broker: &str) -> Result<(String, u16), String> {
+    let broker = broker.trim();
+    if let Ok(addr) = broker.parse::<SocketAddr>() {
+        return Ok((addr.ip().to_string(), addr.port()));
+    }
+    if let Ok(ip) = broker.parse::<IpAddr>() {
+        return Ok((ip.to_string(), DEFAULT_MQTT_PORT));
+    }
+    let (host, port) = match broker.rsplit_once(':') {
+        Some((host, port)) => match port.parse::<u16>() {
+            Ok(port) => (host, port),
+            Err(_) => return Err(format!("Bad port in {broker}")),
+        },
+        None => (broker, DEFAULT_MQTT_PORT),
+    };
+    if host.is_empty() {
+        return Err("No broker set".to_string());
+    }
+    Ok((host.to_string(), port))
+}
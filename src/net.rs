@@ -0,0 +1,1693 @@
+//! The OSC network thread, its sockets and the messages it exchanges with the UI.
+
+use crate::event::{CueInfo, NetEvent, NetStats};
+use crate::paging::Page;
+use crate::protocol::{
+    ControlMsg, OscMapping, SlipDecoder, osc_message, packet_messages, parse_control, slip_encode,
+};
+use crate::qlab::{DEFAULT_QLAB_PORT, Qlab};
+use chrono::Local;
+use rosc::{OscError, OscMessage, OscPacket, OscType};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+mod session;
+
+use session::run_session;
+
+// The session task wakes at least every MAX_WAIT; a gap this large on the
+// sleep clock means the machine was asleep and TheatreMix has dropped us.
+const RESUME_GAP: Duration = Duration::from_secs(5);
+
+// Longest the session task waits with nothing arriving, so failover and sleep
+// detection still run on a quiet network.
+const MAX_WAIT: Duration = Duration::from_secs(1);
+
+// How long the primary route may go quiet before backup traffic is used.
+const ROUTE_FAILOVER_AFTER: Duration = Duration::from_secs(3);
+
+// Thumps arrive every couple of seconds, so this much silence means the
+// subscription is gone even if TheatreMix never said so.
+pub const DEFAULT_LOST_AFTER: Duration = Duration::from_secs(10);
+
+// Unanswered subscribes (2 s apart) before we suspect a firewall.
+const NO_REPLY_AFTER: u32 = 5;
+
+// Keeps the session alive between subscribes.
+const THUMP_EVERY: Duration = Duration::from_secs(2);
+
+// Pause before reconnecting a dropped or refused TCP connection.
+const TCP_RECONNECT_AFTER: Duration = Duration::from_secs(2);
+
+// Least time between statistics updates to the UI.
+const STATS_EVERY: Duration = Duration::from_secs(1);
+
+// TheatreMix listens for OSC here unless a router is in between.
+pub const DEFAULT_OSC_PORT: u16 = 32000;
+
+// Service type TheatreMix hosts are browsed under, and how long to listen.
+const DISCOVERY_SERVICE: &str = "_theatremix._udp.local.";
+
+const DISCOVERY_FOR: Duration = Duration::from_secs(5);
+
+// How often to re-send /subscribe while not subscribed. An unreachable host
+// is retried quickly for the first few tries, then backed off from; a host
+// that answers with /subscribefail is backed off from straight away. Both
+// double each time up to their cap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub unreachable_secs: u32,
+    pub unreachable_max_secs: u32,
+    pub rejected_secs: u32,
+    pub rejected_max_secs: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            unreachable_secs: 2,
+            unreachable_max_secs: 30,
+            rejected_secs: 10,
+            rejected_max_secs: 120,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn interval(&self, rejections: u32, unanswered: u32) -> Duration {
+        let (base, max, failures) = if rejections > 0 {
+            (self.rejected_secs, self.rejected_max_secs, rejections)
+        } else {
+            let failures = unanswered.saturating_add(1).saturating_sub(NO_REPLY_AFTER);
+            (self.unreachable_secs, self.unreachable_max_secs, failures)
+        };
+        let secs = if failures == 0 {
+            base
+        } else {
+            let factor = 1u32 << (failures - 1).min(16);
+            base.saturating_mul(factor).min(max.max(base))
+        };
+        Duration::from_secs(secs.max(1).into())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddrFamily {
+    #[default]
+    Any,
+    V4,
+    V6,
+}
+
+impl AddrFamily {
+    pub const ALL: &[AddrFamily] = &[AddrFamily::Any, AddrFamily::V4, AddrFamily::V6];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AddrFamily::Any => "IPv4 or IPv6",
+            AddrFamily::V4 => "IPv4 only",
+            AddrFamily::V6 => "IPv6 only",
+        }
+    }
+
+    fn allows(self, ip: IpAddr) -> bool {
+        match self {
+            AddrFamily::Any => true,
+            AddrFamily::V4 => ip.is_ipv4(),
+            AddrFamily::V6 => ip.is_ipv6(),
+        }
+    }
+}
+
+// Which address to use when the host resolves to several, as a name with
+// both A and AAAA records or a multi-homed machine does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AddrChoice {
+    pub family: AddrFamily,
+    // Used for as long as the host still resolves to it, whatever the family.
+    pub pinned: Option<IpAddr>,
+}
+
+impl AddrChoice {
+    // The addresses to try, in the resolver's order. Without a pin the next
+    // one is tried whenever subscribes go unanswered.
+    fn candidates(&self, addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+        if let Some(pinned) = self.pinned
+            && let Some(addr) = addrs.iter().find(|addr| addr.ip() == pinned)
+        {
+            return vec![*addr];
+        }
+        addrs
+            .iter()
+            .copied()
+            .filter(|addr| self.family.allows(addr.ip()))
+            .collect()
+    }
+}
+
+// How OSC reaches the primary TheatreMix host. TCP is for networks whose
+// managed switches drop UDP; the backup route and host stay on UDP.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+impl Transport {
+    pub const ALL: &[Transport] = &[Transport::Udp, Transport::Tcp];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Transport::Udp => "UDP",
+            Transport::Tcp => "TCP (SLIP)",
+        }
+    }
+}
+
+// What the primary host runs. QLab is asked for its playhead and running
+// cues in place of TheatreMix's cue messages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    #[default]
+    TheatreMix,
+    Qlab,
+}
+
+impl SourceKind {
+    pub const ALL: &[SourceKind] = &[SourceKind::TheatreMix, SourceKind::Qlab];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SourceKind::TheatreMix => "TheatreMix",
+            SourceKind::Qlab => "QLab",
+        }
+    }
+
+    pub fn default_port(self) -> u16 {
+        match self {
+            SourceKind::TheatreMix => DEFAULT_OSC_PORT,
+            SourceKind::Qlab => DEFAULT_QLAB_PORT,
+        }
+    }
+}
+
+pub enum NetCmd {
+    SetHost(String),
+    Reconnect,
+    SetBackupRoute(Option<IpAddr>),
+    SetBackupHost(Option<String>),
+    // An X32 or M32 to show scenes from while no host is answering.
+    SetConsole(Option<String>),
+    SetLocalPort(u16),
+    SetOscPort(u16),
+    SetTransport(Transport),
+    // With the workspace passcode for QLab, which may be empty.
+    SetSource(SourceKind, String),
+    SetAddrChoice(AddrChoice),
+    // Local address to send from, for machines on more than one network.
+    SetInterface(Option<IpAddr>),
+    // Also accept OSC sent to this local port by any host, for TheatreMix
+    // set up to push to a fixed target rather than answer a /subscribe.
+    SetListenPort(Option<u16>),
+    // Where to re-send cues, so one subscription can feed QLab, consoles and
+    // other displays.
+    SetForwardTargets(Vec<SocketAddr>),
+    // Sends /display/hold to the forward targets, so the displays fed from
+    // this one hold and release with it.
+    ForwardHold(bool),
+    SetRetryPolicy(RetryPolicy),
+    // Silence after which a subscription is treated as lost.
+    SetLostAfter(Duration),
+    SaveCapture(PathBuf),
+    Send(OscMessage),
+    // Report every message sent and received while the monitor is open.
+    SetMonitor(bool),
+    // Other programs' OSC to read as cues, from osc-map.txt.
+    SetOscMap(Vec<OscMapping>),
+    // (Re)load the user script; None turns scripting off.
+    LoadScript(Option<PathBuf>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Priority {
+    KeepAlive,
+    Normal,
+}
+
+struct CapturedPacket {
+    at: SystemTime,
+    src: SocketAddr,
+    dst: SocketAddr,
+    bytes: Vec<u8>,
+}
+
+// A bounded ring of the most recent raw datagrams in both directions, saved
+// on demand as a pcap that Wireshark can open and decode as OSC.
+#[derive(Default)]
+struct Capture {
+    packets: VecDeque<CapturedPacket>,
+}
+
+impl Capture {
+    pub const MAX_PACKETS: usize = 4096;
+    // The most of each record the file holds, headers included.
+    const SNAP_LEN: usize = 65535;
+
+    pub fn record_sent(&mut self, socket: &UdpSocket, bytes: &[u8]) {
+        if let (Ok(src), Ok(dst)) = (socket.local_addr(), socket.peer_addr()) {
+            self.push(src, dst, bytes);
+        }
+    }
+
+    pub fn push(&mut self, src: SocketAddr, dst: SocketAddr, bytes: &[u8]) {
+        if self.packets.len() >= Self::MAX_PACKETS {
+            self.packets.pop_front();
+        }
+        self.packets.push_back(CapturedPacket {
+            at: SystemTime::now(),
+            src,
+            dst,
+            bytes: bytes.to_vec(),
+        });
+    }
+
+    // Classic pcap with LINKTYPE_RAW; each datagram gets a synthetic IPv4 or
+    // IPv6 and UDP header so dissectors see the real addresses and ports.
+    // Packets over TCP are written unframed, as if they were datagrams.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = Vec::new();
+        out.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        out.extend_from_slice(&2u16.to_le_bytes());
+        out.extend_from_slice(&4u16.to_le_bytes());
+        out.extend_from_slice(&0i32.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&(Self::SNAP_LEN as u32).to_le_bytes());
+        out.extend_from_slice(&101u32.to_le_bytes());
+
+        for pkt in &self.packets {
+            // SLIP frames over TCP can outgrow a datagram. The length fields
+            // saturate and the payload is cut at the snap length, with the
+            // full size kept as the record's original length.
+            let udp_len = u16::try_from(8 + pkt.bytes.len()).unwrap_or(u16::MAX);
+            let (src, dst) = same_family(pkt.src, pkt.dst);
+            let ip = match (src, dst) {
+                (SocketAddr::V4(src), SocketAddr::V4(dst)) => {
+                    let mut ip = vec![0u8; 20];
+                    ip[0] = 0x45;
+                    let total = u16::try_from(20 + usize::from(udp_len)).unwrap_or(u16::MAX);
+                    ip[2..4].copy_from_slice(&total.to_be_bytes());
+                    ip[8] = 64;
+                    ip[9] = 17;
+                    ip[12..16].copy_from_slice(&src.ip().octets());
+                    ip[16..20].copy_from_slice(&dst.ip().octets());
+                    let checksum = ipv4_checksum(&ip);
+                    ip[10..12].copy_from_slice(&checksum.to_be_bytes());
+                    ip
+                }
+                (SocketAddr::V6(src), SocketAddr::V6(dst)) => {
+                    let mut ip = vec![0u8; 40];
+                    ip[0] = 0x60;
+                    ip[4..6].copy_from_slice(&udp_len.to_be_bytes());
+                    ip[6] = 17;
+                    ip[7] = 64;
+                    ip[8..24].copy_from_slice(&src.ip().octets());
+                    ip[24..40].copy_from_slice(&dst.ip().octets());
+                    ip
+                }
+                _ => continue,
+            };
+            let kept = pkt.bytes.len().min(Self::SNAP_LEN - ip.len() - 8);
+            let incl_len = (ip.len() + 8 + kept) as u32;
+            let orig_len = (ip.len() + 8 + pkt.bytes.len()) as u32;
+
+            let ts = pkt
+                .at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default();
+            out.extend_from_slice(&(ts.as_secs() as u32).to_le_bytes());
+            out.extend_from_slice(&ts.subsec_micros().to_le_bytes());
+            out.extend_from_slice(&incl_len.to_le_bytes());
+            out.extend_from_slice(&orig_len.to_le_bytes());
+            out.extend_from_slice(&ip);
+            out.extend_from_slice(&src.port().to_be_bytes());
+            out.extend_from_slice(&dst.port().to_be_bytes());
+            out.extend_from_slice(&udp_len.to_be_bytes());
+            // A zero UDP checksum means "not computed". IPv6 forbids it on the
+            // wire, but Wireshark only flags it.
+            out.extend_from_slice(&[0, 0]);
+            out.extend_from_slice(&pkt.bytes[..kept]);
+        }
+        fs::write(path, out)
+    }
+}
+
+// A v4 peer on a dual-stack socket shows up v4-mapped. Both ends are written
+// as IPv4 when they can be and as IPv6 otherwise.
+fn same_family(src: SocketAddr, dst: SocketAddr) -> (SocketAddr, SocketAddr) {
+    let unmap = |addr: SocketAddr| match addr {
+        SocketAddr::V6(v6) => match v6.ip().to_ipv4_mapped() {
+            Some(v4) => SocketAddr::new(v4.into(), v6.port()),
+            None => addr,
+        },
+        SocketAddr::V4(_) => addr,
+    };
+    let map = |addr: SocketAddr| match addr {
+        SocketAddr::V4(v4) => SocketAddr::new(v4.ip().to_ipv6_mapped().into(), v4.port()),
+        SocketAddr::V6(_) => addr,
+    };
+    match (unmap(src), unmap(dst)) {
+        (src @ SocketAddr::V4(_), dst @ SocketAddr::V4(_)) => (src, dst),
+        (src, dst) => (map(src), map(dst)),
+    }
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|pair| u32::from(u16::from_be_bytes([pair[0], pair[1]])))
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// Every outbound message goes through here. Keep-alives always go out first
+// and are never throttled; everything else shares a token bucket so a burst
+// of forwarded or scripted traffic can't crowd out /subscribe and /thump.
+struct OutQueue {
+    keep_alive: VecDeque<OscMessage>,
+    // With where a forward goes; None for the host.
+    normal: VecDeque<(Option<SocketAddr>, OscMessage)>,
+    tokens: f32,
+    last_refill: Instant,
+}
+
+impl OutQueue {
+    pub const RATE_PER_SEC: f32 = 50.0;
+    pub const BURST: f32 = 20.0;
+    pub const MAX_PENDING: usize = 256;
+
+    pub fn new() -> Self {
+        Self {
+            keep_alive: VecDeque::new(),
+            normal: VecDeque::new(),
+            tokens: Self::BURST,
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn push(&mut self, priority: Priority, msg: OscMessage) {
+        match priority {
+            Priority::KeepAlive => self.keep_alive.push_back(msg),
+            Priority::Normal => self.push_normal(None, msg),
+        }
+    }
+
+    // A cue forwarded to another receiver, throttled with everything else.
+    pub fn forward(&mut self, to: SocketAddr, msg: OscMessage) {
+        self.push_normal(Some(to), msg);
+    }
+
+    fn push_normal(&mut self, to: Option<SocketAddr>, msg: OscMessage) {
+        if self.normal.len() >= Self::MAX_PENDING {
+            self.normal.pop_front();
+        }
+        self.normal.push_back((to, msg));
+    }
+
+    // Hands each message over with its priority and, for a forward, its
+    // destination, so the caller can pick where it goes.
+    pub fn flush(&mut self, mut send: impl FnMut(Priority, Option<SocketAddr>, &OscMessage)) {
+        let elapsed = self.last_refill.elapsed().as_secs_f32();
+        self.last_refill = Instant::now();
+        self.tokens = (self.tokens + elapsed * Self::RATE_PER_SEC).min(Self::BURST);
+
+        for msg in self.keep_alive.drain(..) {
+            send(Priority::KeepAlive, None, &msg);
+        }
+        while self.tokens >= 1.0 {
+            let Some((to, msg)) = self.normal.pop_front() else {
+                break;
+            };
+            send(Priority::Normal, to, &msg);
+            self.tokens -= 1.0;
+        }
+    }
+
+    // How soon the next flush could send something still waiting.
+    pub fn next_due(&self) -> Option<Duration> {
+        if !self.keep_alive.is_empty() {
+            return Some(Duration::ZERO);
+        }
+        let short = (1.0 - self.tokens).max(0.0);
+        (!self.normal.is_empty()).then(|| Duration::from_secs_f32(short / Self::RATE_PER_SEC))
+    }
+}
+
+pub struct FoundHost {
+    pub name: String,
+    pub addr: IpAddr,
+}
+
+impl FoundHost {
+    // Whether `host`, as typed in Settings, names this host.
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.trim().trim_end_matches('.');
+        host.parse::<IpAddr>().ok() == Some(self.addr)
+            || host.eq_ignore_ascii_case(&self.name)
+            || host
+                .strip_suffix(".local")
+                .is_some_and(|name| name.eq_ignore_ascii_case(&self.name))
+    }
+}
+
+// A local address the primary socket can be bound to.
+pub struct LocalInterface {
+    pub name: String,
+    pub addr: IpAddr,
+}
+
+impl LocalInterface {
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.name, self.addr)
+    }
+}
+
+pub fn local_interfaces() -> Vec<LocalInterface> {
+    match if_addrs::get_if_addrs() {
+        Ok(found) => found
+            .into_iter()
+            .filter(|iface| !iface.is_loopback())
+            .map(|iface| LocalInterface {
+                addr: iface.ip(),
+                name: iface.name,
+            })
+            .collect(),
+        Err(err) => {
+            tracing::warn!("Could not list network interfaces: {err}");
+            Vec::new()
+        }
+    }
+}
+
+pub struct TestStep {
+    pub name: &'static str,
+    pub outcome: Result<String, String>,
+    // What to try next, for failures a non-technical user can act on.
+    pub advice: Option<String>,
+}
+
+// Lets the network thread ask a frontend to redraw as soon as events are
+// sent, rather than the frontend polling. Set once the frontend exists.
+#[derive(Clone, Default)]
+pub struct Waker(Arc<OnceLock<Box<dyn Fn() + Send + Sync>>>);
+
+impl Waker {
+    pub fn set(&self, wake: impl Fn() + Send + Sync + 'static) {
+        let _ = self.0.set(Box::new(wake));
+    }
+
+    pub fn wake(&self) {
+        if let Some(wake) = self.0.get() {
+            wake();
+        }
+    }
+}
+
+// Everything the session task reacts to, from the UI and the other tasks.
+enum Input {
+    Cmd(NetCmd),
+    // With where it came from and arrived at, when known, for the capture.
+    Packet(Source, Option<(SocketAddr, SocketAddr)>, Vec<u8>),
+    // The TCP link's local and remote ends, or None while it's down.
+    TcpLinked(Option<(SocketAddr, SocketAddr)>),
+    Refused,
+    SubscribeDue,
+    ThumpDue,
+    // Nothing is due, but failover and sleep detection should run.
+    Tick,
+    // The UI dropped its command sender.
+    Closed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Source {
+    Primary,
+    Backup,
+    BackupHost,
+    Listen,
+    Console,
+}
+
+// The network runs on a single-threaded tokio runtime on its own thread, so
+// per-thread requests such as keep-awake are made from one place.
+pub fn spawn_osc_thread(
+    host: String,
+    local_port: u16,
+    osc_port: u16,
+    tx: Sender<NetEvent>,
+    cmd_rx: Receiver<NetCmd>,
+    waker: Waker,
+) {
+    thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(err) => {
+                tracing::error!("Network runtime not started: {err}");
+                return;
+            }
+        };
+        runtime.block_on(run_session(host, local_port, osc_port, tx, cmd_rx, waker));
+    });
+}
+
+// TheatreMix's /subscribe, or QLab's /connect and /updates.
+fn push_subscribe(outbound: &mut OutQueue, qlab: &Option<Qlab>) {
+    match qlab {
+        Some(qlab) => {
+            for msg in qlab.connect_messages() {
+                outbound.push(Priority::KeepAlive, msg);
+            }
+        }
+        None => outbound.push(Priority::KeepAlive, osc_message("/subscribe", &[])),
+    }
+}
+
+// Decode failures are counted rather than reported one by one; TheatreMix
+// doesn't send malformed packets, so they point at the network.
+fn counted(decoded: Result<Vec<NetEvent>, OscError>, stats: &mut NetStats) -> Vec<NetEvent> {
+    decoded.unwrap_or_else(|err| {
+        tracing::debug!("Packet not decoded: {err:?}");
+        stats.decode_failures += 1;
+        Vec::new()
+    })
+}
+
+fn spawn_tasks(
+    primary: Option<&UdpSocket>,
+    backup: Option<&UdpSocket>,
+    backup_host: Option<&UdpSocket>,
+    listen: Option<&UdpSocket>,
+    console: Option<&UdpSocket>,
+    input: &UnboundedSender<Input>,
+    every: &watch::Receiver<Duration>,
+) -> Vec<JoinHandle<()>> {
+    let mut tasks = vec![tokio::spawn(keep_alive(every.clone(), input.clone()))];
+    let sockets = [
+        primary.map(|socket| (socket, Source::Primary)),
+        backup.map(|socket| (socket, Source::Backup)),
+        backup_host.map(|socket| (socket, Source::BackupHost)),
+        listen.map(|socket| (socket, Source::Listen)),
+        console.map(|socket| (socket, Source::Console)),
+    ];
+    for (socket, source) in sockets.into_iter().flatten() {
+        match socket.try_clone().and_then(tokio::net::UdpSocket::from_std) {
+            Ok(socket) => tasks.push(tokio::spawn(receive(socket, source, input.clone()))),
+            Err(err) => tracing::error!("Socket not readable, nothing will be received: {err}"),
+        }
+    }
+    tasks
+}
+
+// The primary link to TheatreMix. The session deals in whole OSC packets;
+// framing them on the wire is up to the transport.
+enum Conn {
+    Udp(UdpSocket),
+    // SLIP frames for the task that owns the TCP stream.
+    Tcp(UnboundedSender<Vec<u8>>),
+}
+
+impl Conn {
+    fn udp(&self) -> Option<&UdpSocket> {
+        match self {
+            Conn::Udp(socket) => Some(socket),
+            Conn::Tcp(_) => None,
+        }
+    }
+
+    fn send(&self, msg: &OscMessage) -> Option<Vec<u8>> {
+        match self {
+            Conn::Udp(socket) => send_osc(socket, msg),
+            Conn::Tcp(frames) => {
+                let buf = rosc::encoder::encode(&OscPacket::Message(msg.clone())).ok()?;
+                frames.send(slip_encode(&buf)).ok()?;
+                Some(buf)
+            }
+        }
+    }
+}
+
+fn open_primary(
+    transport: Transport,
+    local_port: u16,
+    interface: Option<IpAddr>,
+    remote: Option<SocketAddr>,
+    tx: &Sender<NetEvent>,
+    input: &UnboundedSender<Input>,
+) -> (Conn, Option<JoinHandle<()>>) {
+    match transport {
+        Transport::Udp => (
+            Conn::Udp(bind_primary(local_port, interface, remote, tx)),
+            None,
+        ),
+        Transport::Tcp => {
+            let (frames_tx, frames) = unbounded_channel();
+            let task =
+                remote.map(|addr| tokio::spawn(tcp_link(addr, interface, frames, input.clone())));
+            (Conn::Tcp(frames_tx), task)
+        }
+    }
+}
+
+// Keeps a TCP connection to TheatreMix open, reconnecting after a pause if it
+// drops. Frames queued while it is down are dropped, as UDP would lose them.
+async fn tcp_link(
+    addr: SocketAddr,
+    interface: Option<IpAddr>,
+    mut frames: UnboundedReceiver<Vec<u8>>,
+    input: UnboundedSender<Input>,
+) {
+    loop {
+        match connect_tcp(addr, interface).await {
+            Ok(stream) => {
+                tracing::info!("TCP connected to {addr}");
+                stream.set_nodelay(true).ok();
+                let ends = stream.local_addr().ok().zip(stream.peer_addr().ok());
+                if input.send(Input::TcpLinked(ends)).is_err() {
+                    return;
+                }
+                let received = ends.map(|(local, peer)| (peer, local));
+                let (mut reader, mut writer) = stream.into_split();
+                let mut slip = SlipDecoder::default();
+                let mut buf = [0u8; 4096];
+                loop {
+                    tokio::select! {
+                        read = reader.read(&mut buf) => match read {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => {
+                                for packet in slip.push(&buf[..n]) {
+                                    if input.send(Input::Packet(Source::Primary, received, packet)).is_err() {
+                                        return;
+                                    }
+                                }
+                            }
+                        },
+                        frame = frames.recv() => match frame {
+                            Some(frame) => {
+                                if writer.write_all(&frame).await.is_err() {
+                                    break;
+                                }
+                            }
+                            None => return,
+                        },
+                    }
+                }
+                tracing::warn!("TCP connection to {addr} closed");
+                if input.send(Input::TcpLinked(None)).is_err() {
+                    return;
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => {
+                if input.send(Input::Refused).is_err() {
+                    return;
+                }
+            }
+            Err(err) => tracing::debug!("TCP connect to {addr} failed: {err}"),
+        }
+        while frames.try_recv().is_ok() {}
+        tokio::time::sleep(TCP_RECONNECT_AFTER).await;
+    }
+}
+
+async fn connect_tcp(
+    addr: SocketAddr,
+    interface: Option<IpAddr>,
+) -> std::io::Result<tokio::net::TcpStream> {
+    let Some(ip) = interface else {
+        return tokio::net::TcpStream::connect(addr).await;
+    };
+    let socket = if addr.is_ipv4() {
+        tokio::net::TcpSocket::new_v4()?
+    } else {
+        tokio::net::TcpSocket::new_v6()?
+    };
+    socket.bind(SocketAddr::new(ip, 0))?;
+    socket.connect(addr).await
+}
+
+// Says when a /subscribe or /thump is due; the first of each is immediate.
+// The subscribe interval follows `every` as subscriptions are granted or
+// refused.
+async fn keep_alive(mut every: watch::Receiver<Duration>, input: UnboundedSender<Input>) {
+    let mut subscribe = tokio::time::interval(*every.borrow_and_update());
+    let mut thump = tokio::time::interval(THUMP_EVERY);
+    let mut tick = tokio::time::interval(MAX_WAIT);
+    for timer in [&mut subscribe, &mut thump, &mut tick] {
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    }
+    loop {
+        let due = tokio::select! {
+            _ = subscribe.tick() => Input::SubscribeDue,
+            _ = thump.tick() => Input::ThumpDue,
+            _ = tick.tick() => Input::Tick,
+            changed = every.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let period = *every.borrow_and_update();
+                subscribe = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                subscribe.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                continue;
+            }
+        };
+        if input.send(due).is_err() {
+            break;
+        }
+    }
+}
+
+// Hands each datagram on `socket` to the session task until aborted.
+async fn receive(socket: tokio::net::UdpSocket, source: Source, input: UnboundedSender<Input>) {
+    let mut buf = [0u8; 1536];
+    let local = socket.local_addr().ok();
+    loop {
+        let sent = match socket.recv_from(&mut buf).await {
+            Ok((n, from)) => input.send(Input::Packet(
+                source,
+                local.map(|to| (from, to)),
+                buf[..n].to_vec(),
+            )),
+            Err(err)
+                if matches!(source, Source::Primary)
+                    && err.kind() == std::io::ErrorKind::ConnectionRefused =>
+            {
+                input.send(Input::Refused)
+            }
+            // Transient error; keep reading.
+            Err(_) => Ok(()),
+        };
+        if sent.is_err() {
+            break;
+        }
+    }
+}
+
+// Stop Windows from idling into sleep while we hold a subscription. The
+// request is per-thread, so it must be made from the network thread.
+#[cfg(windows)]
+fn set_keep_awake(awake: bool) {
+    use windows_sys::Win32::System::Power::{
+        ES_CONTINUOUS, ES_SYSTEM_REQUIRED, SetThreadExecutionState,
+    };
+
+    let flags = if awake {
+        ES_CONTINUOUS | ES_SYSTEM_REQUIRED
+    } else {
+        ES_CONTINUOUS
+    };
+    // SAFETY: takes flags by value and touches no memory we own.
+    unsafe {
+        SetThreadExecutionState(flags);
+    }
+}
+
+#[cfg(not(windows))]
+fn set_keep_awake(_awake: bool) {}
+
+// Time that keeps counting while the machine sleeps, for spotting a resume.
+// SystemTime would too, but NTP or the operator can step it at any moment.
+#[cfg(windows)]
+fn sleep_clock() -> Duration {
+    // Instant reads QueryPerformanceCounter, which runs on through sleep.
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed()
+}
+
+// Instant stops during sleep here, so read a clock that doesn't: Linux's
+// CLOCK_BOOTTIME, or CLOCK_MONOTONIC, which counts sleep on macOS.
+#[cfg(unix)]
+fn sleep_clock() -> Duration {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let clock = libc::CLOCK_BOOTTIME;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let clock = libc::CLOCK_MONOTONIC;
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `now` is a valid timespec for the call to fill in.
+    unsafe {
+        libc::clock_gettime(clock, &mut now);
+    }
+    Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+}
+
+// Browse the LAN for TheatreMix hosts advertising over mDNS/Bonjour.
+pub fn spawn_discovery(tx: Sender<FoundHost>) {
+    thread::spawn(move || {
+        let Ok(daemon) = mdns_sd::ServiceDaemon::new() else {
+            return;
+        };
+        let Ok(events) = daemon.browse(DISCOVERY_SERVICE) else {
+            let _ = daemon.shutdown();
+            return;
+        };
+        let deadline = Instant::now() + DISCOVERY_FOR;
+        while let Ok(event) = events.recv_deadline(deadline) {
+            if let mdns_sd::ServiceEvent::ServiceResolved(info) = event {
+                let name = info
+                    .get_fullname()
+                    .trim_end_matches(DISCOVERY_SERVICE)
+                    .trim_end_matches('.')
+                    .to_string();
+                for addr in info.get_addresses_v4() {
+                    let found = FoundHost {
+                        name: name.clone(),
+                        addr: IpAddr::V4(*addr),
+                    };
+                    if tx.send(found).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = daemon.shutdown();
+    });
+}
+
+// Walks through the same steps the network thread takes, on its own socket,
+// and reports each one so users can tell DNS, firewall and TheatreMix
+// configuration problems apart. Stops at the first failure.
+pub fn spawn_connection_test(
+    host: String,
+    osc_port: u16,
+    choice: AddrChoice,
+    interface: Option<IpAddr>,
+    tx: Sender<TestStep>,
+) {
+    thread::spawn(move || {
+        // Advice is only shown, and logged, when the step fails.
+        let report = |name, outcome: Result<String, String>, advice: String| {
+            let ok = outcome.is_ok();
+            let advice = match &outcome {
+                Ok(detail) => {
+                    tracing::info!("Connection test: {name}: {detail}");
+                    None
+                }
+                Err(detail) => {
+                    tracing::warn!("Connection test: {name} failed: {detail}. {advice}");
+                    Some(advice)
+                }
+            };
+            let _ = tx.send(TestStep {
+                name,
+                outcome,
+                advice,
+            });
+            ok
+        };
+        tracing::info!("Connection test: {host} port {osc_port}");
+
+        let addrs = match (bare_host(&host), osc_port).to_socket_addrs() {
+            Ok(found) => unique(found),
+            Err(err) => {
+                report(
+                    "Resolve host",
+                    Err(err.to_string()),
+                    "Check the host name is spelled right, or enter the IP address \
+                     shown in TheatreMix instead."
+                        .to_string(),
+                );
+                return;
+            }
+        };
+        let Some(&remote) = choice.candidates(&addrs).first() else {
+            report(
+                "Resolve host",
+                Err("no usable addresses found".to_string()),
+                format!(
+                    "The host has no {} address. Change Address in Settings.",
+                    choice.family.label()
+                ),
+            );
+            return;
+        };
+        let detail = if addrs.len() > 1 {
+            let all: Vec<String> = addrs.iter().map(|addr| addr.ip().to_string()).collect();
+            format!("{remote} (of {})", all.join(", "))
+        } else {
+            remote.to_string()
+        };
+        report("Resolve host", Ok(detail), String::new());
+
+        // Many machines drop ping, so carry on whatever the answer.
+        report(
+            "Ping host",
+            ping(remote.ip()),
+            "No answer to ping. That's normal if its firewall blocks ping; \
+             otherwise check both machines are on the same network and plugged in."
+                .to_string(),
+        );
+
+        let local = SocketAddr::new(interface.unwrap_or_else(|| unspecified(remote)), 0);
+        let socket = match UdpSocket::bind(local).and_then(|s| s.connect(remote).map(|_| s)) {
+            Ok(socket) => socket,
+            Err(err) => {
+                report(
+                    "Bind local socket",
+                    Err(err.to_string()),
+                    "The chosen network interface may be down, or can't reach this \
+                     host. Set Network interface to Any and try again."
+                        .to_string(),
+                );
+                return;
+            }
+        };
+        let local = socket
+            .local_addr()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        report("Bind local socket", Ok(local), String::new());
+
+        send_osc(&socket, &osc_message("/subscribe", &[]));
+        let (outcome, advice) = match wait_for_osc(&socket, &["/subscribeok", "/subscribefail"]) {
+            Ok(Some(addr)) if addr == "/subscribeok" => (Ok("accepted".to_string()), String::new()),
+            Ok(Some(_)) => (
+                Err("rejected by TheatreMix".to_string()),
+                "TheatreMix answered but turned the subscription down. Check its remote \
+                 settings allow another display."
+                    .to_string(),
+            ),
+            Ok(None) => (
+                Err("no reply".to_string()),
+                format!(
+                    "Requests went out but nothing came back. Check remote control is \
+                     enabled in TheatreMix on port {osc_port}. {}",
+                    firewall_guidance()
+                ),
+            ),
+            Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => (
+                Err(format!("port {osc_port} refused")),
+                format!(
+                    "Nothing is listening on port {osc_port} there. Check TheatreMix is \
+                     running on this host and uses that OSC port."
+                ),
+            ),
+            Err(err) => (Err(err.to_string()), String::new()),
+        };
+        if !report("Subscribe", outcome, advice) {
+            return;
+        }
+
+        send_osc(&socket, &osc_message("/thump", &[]));
+        let outcome = match wait_for_osc(&socket, &["/thump"]) {
+            Ok(Some(_)) => Ok("received".to_string()),
+            Ok(None) => Err("no /thump from TheatreMix".to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        report(
+            "First thump",
+            outcome,
+            format!(
+                "Subscribed, but TheatreMix's keep-alives aren't getting through, so the \
+                 display will keep dropping out. {}",
+                firewall_guidance()
+            ),
+        );
+    });
+}
+
+// Uses the system ping, as sending ICMP ourselves needs extra privileges.
+fn ping(ip: IpAddr) -> Result<String, String> {
+    let program = if cfg!(target_os = "macos") && ip.is_ipv6() {
+        "ping6"
+    } else {
+        "ping"
+    };
+    let mut cmd = std::process::Command::new(program);
+    if cfg!(windows) {
+        cmd.args(["-n", "1", "-w", "2000"]);
+    } else if cfg!(target_os = "macos") {
+        cmd.args(["-c", "1", "-t", "2"]);
+    } else {
+        cmd.args(["-c", "1", "-W", "2"]);
+    }
+    #[cfg(windows)]
+    {
+        // Don't flash a console window up from the GUI app.
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = cmd
+        .arg(ip.to_string())
+        .output()
+        .map_err(|err| format!("ping not run: {err}"))?;
+    let text = String::from_utf8_lossy(&output.stdout).to_ascii_lowercase();
+    // Windows ping exits 0 for "destination unreachable" replies from a
+    // router, so look for the host's own reply too.
+    if !output.status.success() || !text.contains("ttl=") {
+        return Err("no answer".to_string());
+    }
+    // "time=0.41 ms" on Unix, "time<1ms" on Windows.
+    let time = text.find("time").and_then(|at| {
+        let rest = &text[at..];
+        rest.find("ms").map(|end| rest[..end + 2].to_string())
+    });
+    Ok(time.unwrap_or_else(|| "answered".to_string()))
+}
+
+pub fn firewall_guidance() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "macOS firewall: System Settings → Network → Firewall → Options, and allow \
+         incoming connections for TheatreMix Remote Display."
+    } else if cfg!(windows) {
+        "Windows Defender Firewall: Allow an app through firewall, and tick TheatreMix \
+         Remote Display for both Private and Public networks (show networks are often Public)."
+    } else {
+        "Linux firewall: allow inbound UDP from the TheatreMix host, e.g. \
+         `sudo ufw allow from <host> proto udp`, or use a fixed local port below."
+    }
+}
+
+// Waits up to a few seconds for one of `addrs`, returning the one that arrived.
+fn wait_for_osc(socket: &UdpSocket, addrs: &[&str]) -> std::io::Result<Option<String>> {
+    let deadline = Instant::now() + Duration::from_secs(4);
+    let mut buf = [0u8; 1536];
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        socket.set_read_timeout(Some(left.max(Duration::from_millis(1))))?;
+        let n = match socket.recv(&mut buf) {
+            Ok(n) => n,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+        let Ok((_, packet)) = rosc::decoder::decode_udp(&buf[..n]) else {
+            continue;
+        };
+        let mut messages = Vec::new();
+        match packet {
+            OscPacket::Message(msg) => messages.push(msg),
+            OscPacket::Bundle(bundle) => {
+                for pkt in bundle.content {
+                    if let OscPacket::Message(msg) = pkt {
+                        messages.push(msg);
+                    }
+                }
+            }
+        }
+        if let Some(msg) = messages.iter().find(|m| addrs.contains(&m.addr.as_str())) {
+            return Ok(Some(msg.addr.clone()));
+        }
+    }
+    Ok(None)
+}
+
+// Checks the form of a host name or address without looking it up, so it is
+// cheap enough to run as the user types.
+pub fn host_is_valid(host: &str) -> bool {
+    let host = bare_host(host);
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    // IPv6 with a zone, as in fe80::1%en0.
+    if let Some((addr, zone)) = host.split_once('%') {
+        return addr.parse::<Ipv6Addr>().is_ok()
+            && !zone.is_empty()
+            && zone.chars().all(|c| c.is_ascii_alphanumeric());
+    }
+    let name = host.strip_suffix('.').unwrap_or(host);
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+// IPv6 literals may be written in brackets, as in URLs.
+fn bare_host(host: &str) -> &str {
+    let host = host.trim();
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+fn unique(found: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+    let mut addrs = Vec::new();
+    for addr in found {
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    addrs
+}
+
+// Runs on tokio's blocking pool, so a slow DNS server doesn't hold up the
+// session.
+async fn lookup(host: &str, port: u16) -> Vec<SocketAddr> {
+    match tokio::net::lookup_host((bare_host(host), port)).await {
+        Ok(found) => unique(found),
+        Err(err) => {
+            tracing::warn!("Could not resolve {host}: {err}");
+            Vec::new()
+        }
+    }
+}
+
+// Resolves the host afresh and takes the nth address the choice allows,
+// counting round. Also says how many there were to choose from.
+async fn pick_remote(
+    host: &str,
+    port: u16,
+    choice: &AddrChoice,
+    nth: usize,
+    tx: &Sender<NetEvent>,
+) -> (Option<SocketAddr>, usize) {
+    let addrs = lookup(host, port).await;
+    let candidates = choice.candidates(&addrs);
+    let remote = (!candidates.is_empty()).then(|| candidates[nth % candidates.len()]);
+    match remote {
+        Some(addr) if addrs.len() > 1 => {
+            tracing::info!("{host} has {} addresses; using {addr}", addrs.len())
+        }
+        None if !addrs.is_empty() => {
+            tracing::warn!("{host} has no address for {}", choice.family.label())
+        }
+        _ => {}
+    }
+    let _ = tx.send(NetEvent::Resolved {
+        addrs: addrs.iter().map(|addr| addr.ip()).collect(),
+        using: remote.map(|addr| addr.ip()),
+    });
+    (remote, candidates.len())
+}
+
+// The wildcard address of the same family, for binding a local socket that
+// can reach the remote one.
+fn unspecified(remote: SocketAddr) -> IpAddr {
+    match remote {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    }
+}
+
+// A fixed local port can already be taken; fall back to an ephemeral one
+// rather than taking the network thread down. While the host can't be
+// resolved or reached the socket is left unconnected and sends on it fail.
+fn bind_primary(
+    local_port: u16,
+    interface: Option<IpAddr>,
+    remote: Option<SocketAddr>,
+    tx: &Sender<NetEvent>,
+) -> UdpSocket {
+    let any_ip = remote.map_or(Ipv4Addr::UNSPECIFIED.into(), unspecified);
+    let target = remote.map_or("the host".to_string(), |addr| addr.to_string());
+    let local_ip = match interface {
+        Some(ip) if ip.is_ipv4() == any_ip.is_ipv4() => ip,
+        Some(ip) => {
+            tracing::warn!("Interface {ip} can't reach {target}; using any");
+            let _ = tx.send(NetEvent::BindFailed(format!(
+                "interface {ip}: other address family"
+            )));
+            any_ip
+        }
+        None => any_ip,
+    };
+    if local_port != 0 {
+        match try_bind_socket(SocketAddr::new(local_ip, local_port), remote) {
+            Ok(socket) => return socket,
+            Err(err) => {
+                tracing::warn!("Local port {local_port} not available, using any: {err}");
+                let _ = tx.send(NetEvent::BindFailed(format!("port {local_port}: {err}")));
+            }
+        }
+    }
+    match try_bind_socket(SocketAddr::new(local_ip, 0), remote) {
+        Ok(socket) => socket,
+        // No route to an IPv6 host, or the chosen interface has gone away.
+        Err(err) => {
+            tracing::warn!("Could not reach {target} from {local_ip}: {err}");
+            let _ = tx.send(NetEvent::BindFailed(format!("route to host: {err}")));
+            bind_socket(SocketAddr::new(any_ip, 0))
+        }
+    }
+}
+
+fn try_bind_socket(
+    local_addr: SocketAddr,
+    remote: Option<SocketAddr>,
+) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(local_addr)?;
+    socket.set_nonblocking(true).ok();
+    if let Some(remote) = remote {
+        socket.connect(remote)?;
+    }
+    Ok(socket)
+}
+
+fn bind_socket(local_addr: SocketAddr) -> UdpSocket {
+    let socket = UdpSocket::bind(local_addr).expect("bind UDP socket");
+    socket.set_nonblocking(true).ok();
+    socket
+}
+
+// The backup route is bound to a specific local address (e.g. the Wi-Fi
+// interface) and read by its own task like the primary.
+fn bind_backup_socket(local_ip: IpAddr, remote_addr: SocketAddr) -> Option<UdpSocket> {
+    let socket = UdpSocket::bind(SocketAddr::new(local_ip, 0)).ok()?;
+    socket.set_nonblocking(true).ok()?;
+    socket.connect(remote_addr).ok()?;
+    Some(socket)
+}
+
+// Left unconnected so packets from any sender are read.
+fn bind_listen(port: u16, tx: &Sender<NetEvent>) -> Option<UdpSocket> {
+    match UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], port))) {
+        Ok(socket) => {
+            socket.set_nonblocking(true).ok()?;
+            tracing::info!("Listening for pushed OSC on port {port}");
+            Some(socket)
+        }
+        Err(err) => {
+            tracing::warn!("Listen port {port} not available: {err}");
+            let _ = tx.send(NetEvent::BindFailed(format!("listen port {port}: {err}")));
+            None
+        }
+    }
+}
+
+pub fn capture_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    let name = format!("capture-{}.pcap", Local::now().format("%Y%m%d-%H%M%S"));
+    Some(
+        base.join("theatremix-remote-display")
+            .join("captures")
+            .join(name),
+    )
+}
+
+// Listens for display-control OSC from anyone on the network (a lighting
+// desk, a show controller), separate from the TheatreMix connection.
+pub struct ControlListener {
+    pub rx: Receiver<ControlMsg>,
+    pub stop: Arc<AtomicBool>,
+    socket: UdpSocket,
+}
+
+impl ControlListener {
+    pub fn start(port: u16) -> Result<ControlListener, String> {
+        let socket = UdpSocket::bind(("0.0.0.0", port)).map_err(|e| format!("Port {port}: {e}"))?;
+        socket
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .map_err(|e| e.to_string())?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let (tx, rx) = mpsc::channel();
+        let reader = socket.try_clone().map_err(|e| e.to_string())?;
+        thread::spawn(move || {
+            let mut buf = [0u8; 1536];
+            while !stopped.load(Ordering::Relaxed) {
+                let Ok((n, from)) = reader.recv_from(&mut buf) else {
+                    continue;
+                };
+                let Ok((_, packet)) = rosc::decoder::decode_udp(&buf[..n]) else {
+                    continue;
+                };
+                for msg in packet_messages(packet) {
+                    let Some(mut control) = parse_control(&msg) else {
+                        continue;
+                    };
+                    if let ControlMsg::Page(page) = &mut control {
+                        page.reply_to = Some(from);
+                    }
+                    if tx.send(control).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(ControlListener { rx, stop, socket })
+    }
+
+    // Sends /display/page/ack with the page id back to whoever sent it.
+    pub fn acknowledge(&self, page: &Page) {
+        let Some(to) = page.reply_to else {
+            return;
+        };
+        let msg = osc_message("/display/page/ack", &[OscType::Long(page.id as i64)]);
+        if let Ok(buf) = rosc::encoder::encode(&OscPacket::Message(msg))
+            && let Err(err) = self.socket.send_to(&buf, to)
+        {
+            tracing::warn!("Page acknowledgement to {to} not sent: {err}");
+        }
+    }
+}
+
+impl Drop for ControlListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Cues as they reach the display, whichever route or host they came in on,
+// re-sent as /cuefired and /nextcue to other receivers. The messages go
+// through the session's OutQueue; only the sending is done here.
+#[derive(Default)]
+struct FanOut {
+    targets: Vec<SocketAddr>,
+    v4: Option<UdpSocket>,
+    v6: Option<UdpSocket>,
+    current: Option<OscMessage>,
+    next: Option<OscMessage>,
+    held: bool,
+}
+
+impl FanOut {
+    fn set_targets(&mut self, targets: Vec<SocketAddr>) -> Vec<(SocketAddr, OscMessage)> {
+        if self.v4.is_none() && targets.iter().any(SocketAddr::is_ipv4) {
+            // Broadcast targets reach every display on the subnet at once.
+            self.v4 = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+                .and_then(|s| s.set_broadcast(true).map(|_| s))
+                .inspect_err(|err| tracing::warn!("Forwarding socket not bound: {err}"))
+                .ok();
+        }
+        if self.v6.is_none() && targets.iter().any(SocketAddr::is_ipv6) {
+            self.v6 = UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+                .inspect_err(|err| tracing::warn!("Forwarding socket not bound: {err}"))
+                .ok();
+        }
+        // Targets just added catch up with the cue showing now, and the hold.
+        let held = self.held.then(|| hold_message(true));
+        let catch_up = targets
+            .iter()
+            .filter(|t| !self.targets.contains(t))
+            .flat_map(|&to| {
+                [&self.current, &self.next, &held]
+                    .into_iter()
+                    .flatten()
+                    .map(move |msg| (to, msg.clone()))
+            })
+            .collect();
+        self.targets = targets;
+        catch_up
+    }
+
+    // The messages to queue for this event, one per target.
+    fn forward(&mut self, ev: &NetEvent) -> Vec<(SocketAddr, OscMessage)> {
+        let msg = match ev {
+            NetEvent::CueFired(cue) => cue_message("/cuefired", cue),
+            NetEvent::NextCue(cue) => cue_message("/nextcue", cue),
+            _ => return Vec::new(),
+        };
+        let out = self.targets.iter().map(|&to| (to, msg.clone())).collect();
+        match ev {
+            NetEvent::CueFired(_) => self.current = Some(msg),
+            _ => self.next = Some(msg),
+        }
+        out
+    }
+
+    fn hold(&mut self, on: bool) -> Vec<(SocketAddr, OscMessage)> {
+        self.held = on;
+        let msg = hold_message(on);
+        self.targets.iter().map(|&to| (to, msg.clone())).collect()
+    }
+
+    // Returns the local end and what was sent, for the capture.
+    fn send(&self, to: SocketAddr, msg: &OscMessage) -> Option<(SocketAddr, Vec<u8>)> {
+        let socket = if to.is_ipv4() { &self.v4 } else { &self.v6 };
+        let socket = socket.as_ref()?;
+        let buf = rosc::encoder::encode(&OscPacket::Message(msg.clone())).ok()?;
+        match socket.send_to(&buf, to) {
+            Ok(_) => Some((socket.local_addr().ok()?, buf)),
+            Err(err) => {
+                tracing::debug!("Forward to {to} failed: {err}");
+                None
+            }
+        }
+    }
+}
+
+// The same number, text, color layout TheatreMix sends.
+fn cue_message(addr: &str, cue: &CueInfo) -> OscMessage {
+    let mut args = vec![
+        OscType::String(cue.number.clone()),
+        OscType::String(cue.text.clone()),
+    ];
+    if let Some(color) = &cue.color {
+        args.push(OscType::String(color.clone()));
+    }
+    osc_message(addr, &args)
+}
+
+fn hold_message(on: bool) -> OscMessage {
+    osc_message("/display/hold", &[OscType::Int(i32::from(on))])
+}
+
+// Returns the bytes actually put on the wire, for the traffic capture.
+fn send_osc(socket: &UdpSocket, msg: &OscMessage) -> Option<Vec<u8>> {
+    let buf = rosc::encoder::encode(&OscPacket::Message(msg.clone())).ok()?;
+    socket.send(&buf).ok()?;
+    Some(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(name: &str, src: &str, dst: &str, payload: &[u8]) -> Vec<u8> {
+        let mut capture = Capture::default();
+        capture.push(src.parse().unwrap(), dst.parse().unwrap(), payload);
+        let path = std::env::temp_dir().join(format!(
+            "theatremix-capture-{}-{name}.pcap",
+            std::process::id()
+        ));
+        capture.save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).ok();
+        bytes
+    }
+
+    // The 24-byte file header and 16-byte record header come first.
+    const RECORD: usize = 24 + 16;
+
+    #[test]
+    fn ipv4_header_layout() {
+        let pcap = saved("v4", "10.0.0.2:32000", "10.0.0.1:32001", b"/thump\0\0");
+        assert_eq!(u32::from_le_bytes(pcap[20..24].try_into().unwrap()), 101);
+        assert_eq!(
+            u32::from_le_bytes(pcap[32..36].try_into().unwrap()),
+            20 + 8 + 8
+        );
+        let ip = &pcap[RECORD..RECORD + 20];
+        assert_eq!(ip[0], 0x45);
+        assert_eq!(u16::from_be_bytes([ip[2], ip[3]]), 36);
+        assert_eq!(ip[9], 17);
+        assert_eq!(&ip[12..16], &[10, 0, 0, 2]);
+        assert_eq!(&ip[16..20], &[10, 0, 0, 1]);
+        assert_eq!(ipv4_checksum(ip), 0);
+        let udp = &pcap[RECORD + 20..RECORD + 28];
+        assert_eq!(u16::from_be_bytes([udp[0], udp[1]]), 32000);
+        assert_eq!(u16::from_be_bytes([udp[2], udp[3]]), 32001);
+        assert_eq!(u16::from_be_bytes([udp[4], udp[5]]), 16);
+        assert_eq!(&pcap[RECORD + 28..], b"/thump\0\0");
+    }
+
+    #[test]
+    fn ipv6_header_layout() {
+        let pcap = saved("v6", "[fe80::2]:32000", "[fe80::1]:32001", b"/thump\0\0");
+        assert_eq!(
+            u32::from_le_bytes(pcap[32..36].try_into().unwrap()),
+            40 + 8 + 8
+        );
+        let ip = &pcap[RECORD..RECORD + 40];
+        assert_eq!(ip[0], 0x60);
+        assert_eq!(u16::from_be_bytes([ip[4], ip[5]]), 16);
+        assert_eq!(ip[6], 17);
+        let src: std::net::Ipv6Addr = "fe80::2".parse().unwrap();
+        assert_eq!(&ip[8..24], &src.octets());
+        let udp = &pcap[RECORD + 40..RECORD + 48];
+        assert_eq!(u16::from_be_bytes([udp[0], udp[1]]), 32000);
+        assert_eq!(&pcap[RECORD + 48..], b"/thump\0\0");
+    }
+
+    #[test]
+    fn mapped_and_mixed_families() {
+        let mapped = "[::ffff:10.0.0.1]:32001".parse().unwrap();
+        let v4: SocketAddr = "10.0.0.2:32000".parse().unwrap();
+        let (src, dst) = same_family(v4, mapped);
+        assert!(src.is_ipv4() && dst.is_ipv4());
+        assert_eq!(dst, "10.0.0.1:32001".parse().unwrap());
+
+        let v6 = "[fe80::1]:32001".parse().unwrap();
+        let (src, dst) = same_family(v4, v6);
+        assert!(src.is_ipv6() && dst.is_ipv6());
+        assert_eq!(src, "[::ffff:10.0.0.2]:32000".parse().unwrap());
+    }
+
+    #[test]
+    fn found_host_matches_address_or_name() {
+        let found = FoundHost {
+            name: "Theatre-Mac".to_string(),
+            addr: "10.0.0.5".parse().unwrap(),
+        };
+        assert!(found.matches("10.0.0.5"));
+        assert!(found.matches("theatre-mac.local"));
+        assert!(found.matches("Theatre-Mac"));
+        assert!(!found.matches("10.0.0.50"));
+        assert!(!found.matches("rehearsal-mac.local"));
+    }
+
+    #[test]
+    fn capture_keeps_the_latest() {
+        let mut capture = Capture::default();
+        let addr: SocketAddr = "10.0.0.1:32000".parse().unwrap();
+        for n in 0..=Capture::MAX_PACKETS {
+            capture.push(addr, addr, &(n as u32).to_be_bytes());
+        }
+        assert_eq!(capture.packets.len(), Capture::MAX_PACKETS);
+        assert_eq!(capture.packets[0].bytes, 1u32.to_be_bytes());
+    }
+
+    #[test]
+    fn valid_hosts() {
+        for host in [
+            "192.168.1.10",
+            "::1",
+            "[fe80::1]",
+            "fe80::1%en0",
+            "theatremix.local",
+            "theatremix.local.",
+            "foh-mac",
+            " 10.0.0.1 ",
+        ] {
+            assert!(host_is_valid(host), "{host}");
+        }
+    }
+
+    #[test]
+    fn invalid_hosts() {
+        let long_label = "a".repeat(64);
+        for host in [
+            "",
+            "   ",
+            "-foh",
+            "foh-",
+            "foh..local",
+            "foh mac",
+            "foh_mac",
+            "fe80::1%",
+            "fe80::1%en 0",
+            "http://10.0.0.1",
+            long_label.as_str(),
+        ] {
+            assert!(!host_is_valid(host), "{host}");
+        }
+    }
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn rejections_back_off_to_cap() {
+        let policy = RetryPolicy::default();
+        let intervals: Vec<_> = (1..=6).map(|n| policy.interval(n, 0)).collect();
+        assert_eq!(intervals, [10, 20, 40, 80, 120, 120].map(secs));
+        // A rejection outranks unanswered subscribes.
+        assert_eq!(policy.interval(1, 50), secs(10));
+        assert_eq!(policy.interval(u32::MAX, 0), secs(120));
+    }
+
+    #[test]
+    fn odd_retry_settings() {
+        let policy = RetryPolicy {
+            unreachable_secs: 0,
+            unreachable_max_secs: 0,
+            rejected_secs: 30,
+            rejected_max_secs: 5,
+        };
+        // Never faster than a second, and a cap below the base is ignored.
+        assert_eq!(policy.interval(0, 0), secs(1));
+        assert_eq!(policy.interval(3, 0), secs(30));
+    }
+
+    #[test]
+    fn unanswered_subscribes_back_off_to_cap() {
+        let policy = RetryPolicy::default();
+        // Quick retries until NO_REPLY_AFTER subscribes have gone unanswered.
+        for unanswered in 0..=NO_REPLY_AFTER {
+            assert_eq!(policy.interval(0, unanswered), secs(2));
+        }
+        let intervals: Vec<_> = (NO_REPLY_AFTER + 1..NO_REPLY_AFTER + 6)
+            .map(|n| policy.interval(0, n))
+            .collect();
+        assert_eq!(intervals, [4, 8, 16, 30, 30].map(secs));
+        assert_eq!(policy.interval(0, u32::MAX), secs(30));
+    }
+
+    #[test]
+    fn flush_hands_over_each_priority() {
+        let mut queue = OutQueue::new();
+        queue.push(Priority::Normal, osc_message("/go", &[]));
+        queue.push(Priority::KeepAlive, osc_message("/thump", &[]));
+        let mut sent = Vec::new();
+        queue.flush(|priority, _, msg| sent.push((priority, msg.addr.clone())));
+        assert_eq!(
+            sent,
+            [
+                (Priority::KeepAlive, "/thump".to_string()),
+                (Priority::Normal, "/go".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_frames_are_cut_at_the_snap_length() {
+        let payload = vec![0u8; 70_000];
+        let pcap = saved("big", "10.0.0.2:32000", "10.0.0.1:32001", &payload);
+        let incl = u32::from_le_bytes(pcap[32..36].try_into().unwrap());
+        let orig = u32::from_le_bytes(pcap[36..40].try_into().unwrap());
+        assert_eq!(incl, 65535);
+        assert_eq!(orig, 20 + 8 + 70_000);
+        assert_eq!(pcap.len(), RECORD + 65535);
+        let ip = &pcap[RECORD..RECORD + 20];
+        assert_eq!(u16::from_be_bytes([ip[2], ip[3]]), u16::MAX);
+        assert_eq!(ipv4_checksum(ip), 0);
+    }
+}
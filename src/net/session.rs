@@ -0,0 +1,1288 @@
+//! The session task: the sockets for one connection, the subscription kept
+//! up over them and which route drives the display.
+
+use super::{
+    AddrChoice, AddrFamily, Capture, Conn, DEFAULT_LOST_AFTER, FanOut, Input, NO_REPLY_AFTER,
+    NetCmd, OutQueue, Priority, RESUME_GAP, ROUTE_FAILOVER_AFTER, RetryPolicy, STATS_EVERY, Source,
+    SourceKind, THUMP_EVERY, Transport, Waker, bind_backup_socket, bind_listen, counted, lookup,
+    open_primary, pick_remote, push_subscribe, send_osc, set_keep_awake, sleep_clock, spawn_tasks,
+    unspecified,
+};
+use crate::event::{CueTiming, NetEvent, NetStats, Route, TrafficEntry};
+use crate::protocol::{
+    CueDebounce, OscMapping, decode_packet, decode_timed, incoming_traffic, osc_message,
+    packet_messages, schedule_packet,
+};
+use crate::qlab::Qlab;
+use crate::script::Script;
+use crate::x32::{X32, X32_PORT};
+use rosc::OscMessage;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+// Everything the session was told to connect to. Changing any of it rebinds.
+struct Target {
+    host: String,
+    local_port: u16,
+    osc_port: u16,
+    transport: Transport,
+    choice: AddrChoice,
+    interface: Option<IpAddr>,
+    // Which of the candidate addresses to use, counting round.
+    nth_addr: usize,
+    backup_ip: Option<IpAddr>,
+    backup_host: Option<String>,
+    console: Option<String>,
+    listen_port: Option<u16>,
+}
+
+impl Target {
+    fn new(host: String, local_port: u16, osc_port: u16) -> Target {
+        Target {
+            host,
+            local_port,
+            osc_port,
+            transport: Transport::default(),
+            choice: AddrChoice::default(),
+            interface: None,
+            nth_addr: 0,
+            backup_ip: None,
+            backup_host: None,
+            console: None,
+            listen_port: None,
+        }
+    }
+}
+
+// The sockets for one connection attempt and the tasks reading them, all
+// replaced together on a rebind.
+struct Sockets {
+    remote: Option<SocketAddr>,
+    // How many of the host's addresses the choice allows.
+    candidates: usize,
+    primary: Conn,
+    backup: Option<UdpSocket>,
+    backup_host: Option<UdpSocket>,
+    console: Option<UdpSocket>,
+    listen: Option<UdpSocket>,
+    // Local and remote ends of the TCP link while it is up, for the capture.
+    tcp_ends: Option<(SocketAddr, SocketAddr)>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Sockets {
+    async fn open(
+        target: &Target,
+        tx: &Sender<NetEvent>,
+        input: &UnboundedSender<Input>,
+        every: &watch::Receiver<Duration>,
+    ) -> Sockets {
+        let (remote, candidates) = pick_remote(
+            &target.host,
+            target.osc_port,
+            &target.choice,
+            target.nth_addr,
+            tx,
+        )
+        .await;
+        let (primary, tcp_task) = open_primary(
+            target.transport,
+            target.local_port,
+            target.interface,
+            remote,
+            tx,
+            input,
+        );
+        let backup = target
+            .backup_ip
+            .zip(remote)
+            .and_then(|(ip, remote)| bind_backup_socket(ip, remote));
+        let backup_host = match &target.backup_host {
+            Some(host) => bind_secondary(host, target.osc_port, target.choice.family).await,
+            None => None,
+        };
+        let console = match &target.console {
+            Some(host) => bind_secondary(host, X32_PORT, target.choice.family).await,
+            None => None,
+        };
+        let listen = target.listen_port.and_then(|port| bind_listen(port, tx));
+        let mut tasks = spawn_tasks(
+            primary.udp(),
+            backup.as_ref(),
+            backup_host.as_ref(),
+            listen.as_ref(),
+            console.as_ref(),
+            input,
+            every,
+        );
+        tasks.extend(tcp_task);
+        Sockets {
+            remote,
+            candidates,
+            primary,
+            backup,
+            backup_host,
+            console,
+            listen,
+            tcp_ends: None,
+            tasks,
+        }
+    }
+
+    // Subscribes and thumps go out on every route so each keeps its own
+    // subscription. Anything else goes only on the active one, since the
+    // backup route reaches the same host and a GO sent twice fires twice.
+    // Each send is recorded for the capture.
+    fn send(&self, priority: Priority, active: Source, msg: &OscMessage, capture: &mut Capture) {
+        let everywhere = priority == Priority::KeepAlive;
+        if everywhere || active == Source::Primary {
+            self.send_primary(msg, capture);
+        }
+        let backups = [
+            (&self.backup, Source::Backup),
+            (&self.backup_host, Source::BackupHost),
+        ];
+        for (socket, source) in backups {
+            if let Some(socket) = socket
+                && (everywhere || active == source)
+                && let Some(bytes) = send_osc(socket, msg)
+            {
+                capture.record_sent(socket, &bytes);
+            }
+        }
+    }
+
+    fn send_primary(&self, msg: &OscMessage, capture: &mut Capture) {
+        if let Some(bytes) = self.primary.send(msg) {
+            match (self.primary.udp(), self.tcp_ends) {
+                (Some(socket), _) => capture.record_sent(socket, &bytes),
+                (None, Some((local, peer))) => capture.push(local, peer, &bytes),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+// The backup host and the console are reached on whichever of their
+// addresses the family allows; a pin only applies to the primary host.
+async fn bind_secondary(host: &str, port: u16, family: AddrFamily) -> Option<UdpSocket> {
+    let any = AddrChoice {
+        family,
+        pinned: None,
+    };
+    any.candidates(&lookup(host, port).await)
+        .first()
+        .and_then(|&addr| bind_backup_socket(unspecified(addr), addr))
+}
+
+// Our subscription with the primary host: when it is next due and how the
+// host has been answering.
+struct Subscription {
+    last_sent: Instant,
+    // Seconds the host granted, or 0 while we aren't subscribed.
+    expiry: u32,
+    // Whether the host has accepted us since we last (re)connected; renewals
+    // don't ask for the cues again.
+    subscribed: bool,
+    // Subscribes sent without hearing anything back; a host firewall
+    // typically lets our sends out but drops every reply.
+    sent_since_rx: u32,
+    refused: bool,
+    retry: RetryPolicy,
+    // Consecutive /subscribefail replies, for backing off.
+    rejections: u32,
+    last_thump_rx: Option<Instant>,
+}
+
+impl Subscription {
+    fn new(now: Instant) -> Subscription {
+        Subscription {
+            last_sent: now - Duration::from_secs(10),
+            expiry: 0,
+            subscribed: false,
+            sent_since_rx: 0,
+            refused: false,
+            retry: RetryPolicy::default(),
+            rejections: 0,
+            last_thump_rx: None,
+        }
+    }
+
+    // Renewed halfway through a granted subscription, otherwise at the
+    // retry policy's rate.
+    fn interval(&self) -> Duration {
+        if self.expiry > 0 {
+            Duration::from_secs((self.expiry / 2).max(2) as u64)
+        } else {
+            self.retry.interval(self.rejections, self.sent_since_rx)
+        }
+    }
+
+    // When the next retry goes out, while there's no subscription to renew.
+    fn retry_at(&self) -> Option<Instant> {
+        (self.expiry == 0).then(|| self.last_sent + self.interval())
+    }
+
+    // True on the subscribe that makes NO_REPLY_AFTER in a row unanswered.
+    fn sent(&mut self, now: Instant) -> bool {
+        self.last_sent = now;
+        self.sent_since_rx += 1;
+        self.sent_since_rx == NO_REPLY_AFTER
+    }
+
+    fn heard(&mut self) {
+        self.sent_since_rx = 0;
+        self.refused = false;
+    }
+
+    // True the first time since (re)connecting, when the cues are asked for.
+    fn accepted(&mut self) -> bool {
+        self.rejections = 0;
+        !std::mem::replace(&mut self.subscribed, true)
+    }
+
+    fn rejected(&mut self) {
+        self.rejections = self.rejections.saturating_add(1);
+        self.subscribed = false;
+    }
+
+    // TheatreMix thumps as often as we do; a longer gap means some went
+    // missing on the way. Returns how many.
+    fn thumped(&mut self, now: Instant) -> u64 {
+        let missed = self.last_thump_rx.map_or(0, |prev| {
+            let gaps =
+                now.saturating_duration_since(prev).as_secs_f32() / THUMP_EVERY.as_secs_f32();
+            (gaps.round() as u64).saturating_sub(1)
+        });
+        self.last_thump_rx = Some(now);
+        missed
+    }
+
+    // New sockets start from scratch.
+    fn restart(&mut self) {
+        self.sent_since_rx = 0;
+        self.rejections = 0;
+        self.subscribed = false;
+        self.expiry = 0;
+        self.last_thump_rx = None;
+    }
+
+    // Drop back to the unsubscribed retry rate and start now rather than
+    // waiting out the old subscription's renewal.
+    fn lost(&mut self, now: Instant) {
+        self.expiry = 0;
+        self.rejections = 0;
+        self.last_thump_rx = None;
+        self.subscribed = false;
+        self.last_sent = now;
+    }
+}
+
+// When each route last heard anything, and which of them is driving the
+// display.
+struct Failover {
+    last_primary_rx: Instant,
+    last_backup_rx: Instant,
+    last_backup_host_rx: Instant,
+    last_console_rx: Instant,
+    last_listen_rx: Option<Instant>,
+    // Last packet that drove the display, on whichever route or host.
+    last_rx: Instant,
+    route: Route,
+    on_backup_host: bool,
+    on_console: bool,
+    pushed: bool,
+}
+
+impl Failover {
+    fn new(now: Instant) -> Failover {
+        let long_ago = now - Duration::from_secs(10);
+        Failover {
+            last_primary_rx: long_ago,
+            last_backup_rx: long_ago,
+            last_backup_host_rx: long_ago,
+            last_console_rx: long_ago,
+            last_listen_rx: None,
+            last_rx: now,
+            route: Route::Primary,
+            on_backup_host: false,
+            on_console: false,
+            pushed: false,
+        }
+    }
+
+    // Notes a packet arriving and says whether it should drive the display.
+    fn accepts(&mut self, source: Source, now: Instant) -> bool {
+        let quiet = |at: Instant| now.saturating_duration_since(at) >= ROUTE_FAILOVER_AFTER;
+        let drives = match source {
+            Source::Primary => {
+                self.last_primary_rx = now;
+                true
+            }
+            Source::Backup => {
+                // Both routes carry the same subscription, so backup traffic
+                // is only used once the primary has gone quiet.
+                self.last_backup_rx = now;
+                quiet(self.last_primary_rx)
+            }
+            Source::BackupHost => {
+                // The backup machine holds its own subscription; it only
+                // drives the display once the main machine stops thumping.
+                let drives = !self.main_alive(now);
+                self.last_backup_host_rx = now;
+                drives
+            }
+            Source::Listen => {
+                // Pushed OSC needs no subscription, so it always drives the
+                // display.
+                self.last_listen_rx = Some(now);
+                true
+            }
+            Source::Console => {
+                self.last_console_rx = now;
+                false
+            }
+        };
+        if drives {
+            self.last_rx = now;
+        }
+        drives
+    }
+
+    fn main_alive(&self, now: Instant) -> bool {
+        let recent = |at: Instant| now.saturating_duration_since(at) < ROUTE_FAILOVER_AFTER;
+        recent(self.last_primary_rx) || recent(self.last_backup_rx)
+    }
+
+    fn active_route(&self, now: Instant) -> Route {
+        let recent = |at: Instant| now.saturating_duration_since(at) < ROUTE_FAILOVER_AFTER;
+        if !recent(self.last_primary_rx) && recent(self.last_backup_rx) {
+            Route::Backup
+        } else {
+            Route::Primary
+        }
+    }
+
+    fn backup_host_active(&self, now: Instant) -> bool {
+        !self.main_alive(now)
+            && now.saturating_duration_since(self.last_backup_host_rx) < ROUTE_FAILOVER_AFTER
+    }
+
+    // Console scenes only show once nothing else is driving the display.
+    fn console_active(&self, now: Instant) -> bool {
+        let recent = |at: Instant| now.saturating_duration_since(at) < ROUTE_FAILOVER_AFTER;
+        !self.main_alive(now) && !recent(self.last_backup_host_rx) && recent(self.last_console_rx)
+    }
+
+    fn pushing(&self, now: Instant, lost_after: Duration) -> bool {
+        self.last_listen_rx
+            .is_some_and(|at| now.saturating_duration_since(at) < lost_after)
+    }
+}
+
+// What one pass over the waiting inputs turned up.
+#[derive(Default)]
+struct Pass {
+    rebind: bool,
+    resolve_again: bool,
+    packets: Vec<(Source, Vec<u8>)>,
+    wake: bool,
+}
+
+struct Session {
+    target: Target,
+    sockets: Sockets,
+    subscription: Subscription,
+    failover: Failover,
+    tx: Sender<NetEvent>,
+    input_tx: UnboundedSender<Input>,
+    // The keep-alive task's subscribe interval.
+    every_tx: watch::Sender<Duration>,
+    every_rx: watch::Receiver<Duration>,
+    announced_retry: Option<Instant>,
+    outbound: OutQueue,
+    capture: Capture,
+    // Bundles stamped for a moment still to come, with when it comes.
+    held: Vec<(Instant, Source, Vec<u8>)>,
+    qlab: Option<Qlab>,
+    x32: X32,
+    script: Option<Script>,
+    mappings: Vec<OscMapping>,
+    debounce: CueDebounce,
+    // Received and decoded times of the latest packet carrying a cue.
+    cue_stamps: Option<(Instant, Instant)>,
+    // The backup host's and the pushing sender's expiries, kept apart from
+    // our own subscription's.
+    backup_host_expiry: u32,
+    listen_expiry: u32,
+    fan_out: FanOut,
+    monitoring: bool,
+    lost_after: Duration,
+    keep_awake: bool,
+    last_tick: Duration,
+    stats: NetStats,
+    reported: NetStats,
+    last_report: Instant,
+}
+
+// Owns the sockets and subscription state. Datagrams arrive from one
+// `receive` task per socket and keep-alive timing from `keep_alive`; both
+// are restarted whenever the sockets are rebound.
+pub(super) async fn run_session(
+    host: String,
+    local_port: u16,
+    osc_port: u16,
+    tx: Sender<NetEvent>,
+    cmd_rx: Receiver<NetCmd>,
+    waker: Waker,
+) {
+    let (input_tx, mut input) = unbounded_channel::<Input>();
+    // The UI's commands come over a std channel; a plain thread forwards them.
+    let forward = input_tx.clone();
+    thread::spawn(move || {
+        for cmd in cmd_rx {
+            if forward.send(Input::Cmd(cmd)).is_err() {
+                return;
+            }
+        }
+        let _ = forward.send(Input::Closed);
+    });
+
+    let target = Target::new(host, local_port, osc_port);
+    let mut session = Session::start(target, tx, input_tx).await;
+
+    'run: loop {
+        let first = match session.next_due() {
+            Some(due) => tokio::time::timeout(due, input.recv())
+                .await
+                .unwrap_or(Some(Input::Tick)),
+            None => input.recv().await,
+        };
+        let mut pass = Pass::default();
+        let mut next = first;
+        while let Some(item) = next.take() {
+            if !session.handle(item, &mut pass) {
+                break 'run;
+            }
+            next = input.try_recv().ok();
+        }
+        if session.step(pass).await {
+            waker.wake();
+        }
+    }
+
+    session.close();
+}
+
+impl Session {
+    async fn start(
+        target: Target,
+        tx: Sender<NetEvent>,
+        input_tx: UnboundedSender<Input>,
+    ) -> Session {
+        tracing::info!("Connecting to {}:{}", target.host, target.osc_port);
+        let now = Instant::now();
+        let subscription = Subscription::new(now);
+        let (every_tx, every_rx) = watch::channel(subscription.interval());
+        let sockets = Sockets::open(&target, &tx, &input_tx, &every_rx).await;
+        Session {
+            target,
+            sockets,
+            subscription,
+            failover: Failover::new(now),
+            tx,
+            input_tx,
+            every_tx,
+            every_rx,
+            announced_retry: None,
+            outbound: OutQueue::new(),
+            capture: Capture::default(),
+            held: Vec::new(),
+            qlab: None,
+            x32: X32::default(),
+            script: None,
+            mappings: Vec::new(),
+            debounce: CueDebounce::default(),
+            cue_stamps: None,
+            backup_host_expiry: 0,
+            listen_expiry: 0,
+            fan_out: FanOut::default(),
+            monitoring: false,
+            lost_after: DEFAULT_LOST_AFTER,
+            keep_awake: false,
+            last_tick: sleep_clock(),
+            stats: NetStats::default(),
+            reported: NetStats::default(),
+            last_report: now,
+        }
+    }
+
+    // Throttled messages still queued and held bundles get their own
+    // deadline.
+    fn next_due(&self) -> Option<Duration> {
+        let held_due = self
+            .held
+            .iter()
+            .map(|(due, _, _)| due.saturating_duration_since(Instant::now()))
+            .min();
+        self.outbound.next_due().into_iter().chain(held_due).min()
+    }
+
+    // False once the UI has gone and the session should end.
+    fn handle(&mut self, input: Input, pass: &mut Pass) -> bool {
+        let target = &mut self.target;
+        match input {
+            Input::Cmd(NetCmd::SetHost(new_host)) => {
+                target.host = new_host;
+                target.nth_addr = 0;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::Reconnect) => pass.rebind = true,
+            Input::Cmd(NetCmd::SetLocalPort(port)) => {
+                target.local_port = port;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetOscPort(port)) => {
+                target.osc_port = port;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetTransport(to)) => {
+                target.transport = to;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetSource(kind, passcode)) => {
+                self.qlab = (kind == SourceKind::Qlab).then(|| Qlab::new(passcode));
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetAddrChoice(to)) => {
+                target.choice = to;
+                target.nth_addr = 0;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetInterface(ip)) => {
+                target.interface = ip;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetListenPort(port)) => {
+                target.listen_port = port;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetForwardTargets(targets)) => {
+                for (to, msg) in self.fan_out.set_targets(targets) {
+                    self.outbound.forward(to, msg);
+                }
+            }
+            Input::Cmd(NetCmd::ForwardHold(on)) => {
+                for (to, msg) in self.fan_out.hold(on) {
+                    self.outbound.forward(to, msg);
+                }
+            }
+            Input::Cmd(NetCmd::SetRetryPolicy(policy)) => self.subscription.retry = policy,
+            Input::Cmd(NetCmd::SetLostAfter(after)) => self.lost_after = after,
+            Input::Cmd(NetCmd::SetBackupRoute(ip)) => {
+                target.backup_ip = ip;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetBackupHost(host)) => {
+                target.backup_host = host;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::SetConsole(host)) => {
+                target.console = host;
+                pass.rebind = true;
+            }
+            Input::Cmd(NetCmd::Send(msg)) => self.outbound.push(Priority::Normal, msg),
+            Input::Cmd(NetCmd::SetMonitor(on)) => self.monitoring = on,
+            Input::Cmd(NetCmd::SetOscMap(map)) => self.mappings = map,
+            Input::Cmd(NetCmd::LoadScript(path)) => {
+                self.load_script(path);
+                pass.wake = true;
+            }
+            Input::Cmd(NetCmd::SaveCapture(path)) => {
+                let result = self
+                    .capture
+                    .save(&path)
+                    .map(|_| path)
+                    .map_err(|e| e.to_string());
+                let _ = self.tx.send(NetEvent::CaptureSaved(result));
+                pass.wake = true;
+            }
+            Input::Packet(source, ends, bytes) => {
+                if let Some((from, to)) = ends {
+                    self.capture.push(from, to, &bytes);
+                }
+                for (delay, part) in schedule_packet(bytes) {
+                    if delay.is_zero() {
+                        pass.packets.push((source, part));
+                    } else {
+                        self.held.push((Instant::now() + delay, source, part));
+                    }
+                }
+            }
+            Input::TcpLinked(ends) => self.sockets.tcp_ends = ends,
+            // ICMP port unreachable: nothing is listening on the host.
+            Input::Refused => self.subscription.refused = true,
+            Input::SubscribeDue => {
+                push_subscribe(&mut self.outbound, &self.qlab);
+                if self.subscription.sent(Instant::now()) {
+                    let refused = self.subscription.refused;
+                    tracing::warn!(
+                        "{NO_REPLY_AFTER} subscribes to {} unanswered (refused: {refused})",
+                        target.host
+                    );
+                    let _ = self.tx.send(NetEvent::NoReplies { refused });
+                    pass.wake = true;
+                    if self.sockets.candidates > 1 {
+                        // Another of the host's addresses may be the one
+                        // that answers.
+                        target.nth_addr += 1;
+                        pass.rebind = true;
+                    }
+                }
+                pass.resolve_again |= self.sockets.remote.is_none();
+            }
+            // Keep session alive
+            Input::ThumpDue => {
+                self.outbound
+                    .push(Priority::KeepAlive, osc_message("/thump", &[]));
+                if let Some(socket) = &self.sockets.console {
+                    for msg in self.x32.keep_alive() {
+                        if let Some(bytes) = send_osc(socket, &msg) {
+                            self.capture.record_sent(socket, &bytes);
+                        }
+                    }
+                }
+            }
+            Input::Tick => {}
+            Input::Closed => return false,
+        }
+        true
+    }
+
+    fn load_script(&mut self, path: Option<PathBuf>) {
+        // A script that fails to load leaves none running.
+        self.script = None;
+        let mut widgets = Vec::new();
+        let loaded = match path.map(|path| Script::load(&path)) {
+            Some(Ok(Some(mut loaded))) => {
+                tracing::info!("Script loaded");
+                // Widgets the script set up as it started.
+                widgets = loaded.widget_events();
+                self.script = Some(loaded);
+                Ok(true)
+            }
+            Some(Err(err)) => {
+                tracing::warn!("Script not loaded: {err}");
+                Err(err)
+            }
+            Some(Ok(None)) | None => Ok(false),
+        };
+        let _ = self.tx.send(NetEvent::ScriptLoaded(loaded));
+        for ev in widgets {
+            let _ = self.tx.send(ev);
+        }
+    }
+
+    // Everything after the inputs are in: rebinding, sending, decoding and
+    // telling the UI. True when the UI should be woken.
+    async fn step(&mut self, mut pass: Pass) -> bool {
+        self.release_held(&mut pass.packets);
+
+        if pass.resolve_again && !pass.rebind {
+            // A name that didn't resolve is looked up again at the retry rate.
+            let target = &self.target;
+            let (found, _) = pick_remote(
+                &target.host,
+                target.osc_port,
+                &target.choice,
+                target.nth_addr,
+                &self.tx,
+            )
+            .await;
+            pass.rebind = found.is_some();
+            pass.wake = true;
+        }
+
+        if self.resumed() {
+            // The interface may have come back with a new address, so
+            // rebind and subscribe straight away rather than waiting.
+            pass.rebind = true;
+            let _ = self.tx.send(NetEvent::Resumed);
+            pass.wake = true;
+        }
+
+        if pass.rebind {
+            // Packets already read belong to the sockets being replaced.
+            pass.packets.clear();
+            self.rebind().await;
+            // Binding may have failed over to another port.
+            pass.wake = true;
+        }
+
+        pass.wake |= self.schedule_subscribe();
+        self.flush();
+
+        let mut events = Vec::new();
+        let scene_changed = self.receive(pass.packets, &mut events);
+        self.follow_console(scene_changed, &mut events);
+        if let Some(qlab) = &mut self.qlab {
+            for query in qlab.queries.drain(..) {
+                self.outbound.push(Priority::Normal, query);
+            }
+        }
+        self.follow_routes(&mut events);
+        self.check_lost(&mut events);
+
+        pass.wake |= !events.is_empty();
+        self.deliver(events);
+        self.report_stats();
+        pass.wake
+    }
+
+    // Held bundles that have come due go ahead of anything newer.
+    fn release_held(&mut self, packets: &mut Vec<(Source, Vec<u8>)>) {
+        let mut due: Vec<_> = self
+            .held
+            .extract_if(.., |(at, _, _)| *at <= Instant::now())
+            .collect();
+        due.sort_by_key(|(at, _, _)| *at);
+        packets.splice(
+            0..0,
+            due.into_iter().map(|(_, source, bytes)| (source, bytes)),
+        );
+    }
+
+    // True when the sleep clock jumped since the last pass.
+    fn resumed(&mut self) -> bool {
+        let now = sleep_clock();
+        let resumed = now.saturating_sub(self.last_tick) >= RESUME_GAP;
+        self.last_tick = now;
+        resumed
+    }
+
+    async fn rebind(&mut self) {
+        tracing::info!(
+            "Connecting to {}:{} over {}",
+            self.target.host,
+            self.target.osc_port,
+            self.target.transport.label()
+        );
+        self.sockets.close();
+        // Keep-alives are sent afresh by the new keep-alive task.
+        self.held.clear();
+        self.outbound.keep_alive.clear();
+        self.sockets = Sockets::open(&self.target, &self.tx, &self.input_tx, &self.every_rx).await;
+        self.subscription.restart();
+        if let Some(qlab) = &mut self.qlab {
+            qlab.reset();
+        }
+        self.x32 = X32::default();
+        self.backup_host_expiry = 0;
+        self.listen_expiry = 0;
+        self.failover.last_listen_rx = None;
+    }
+
+    // Tells the keep-alive task how often to subscribe, and the UI when the
+    // next retry goes out. True if the UI was told.
+    fn schedule_subscribe(&mut self) -> bool {
+        let interval = self.subscription.interval();
+        self.every_tx.send_if_modified(|every| {
+            let changed = *every != interval;
+            *every = interval;
+            changed
+        });
+        let retry_at = self.subscription.retry_at();
+        if retry_at == self.announced_retry {
+            return false;
+        }
+        self.announced_retry = retry_at;
+        match retry_at {
+            Some(at) => {
+                let _ = self.tx.send(NetEvent::RetryAt(at));
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The route and host driving the display, which commands go to.
+    fn active_source(&self) -> Source {
+        if self.failover.on_backup_host && self.sockets.backup_host.is_some() {
+            Source::BackupHost
+        } else if self.failover.route == Route::Backup && self.sockets.backup.is_some() {
+            Source::Backup
+        } else {
+            Source::Primary
+        }
+    }
+
+    fn flush(&mut self) {
+        let active = self.active_source();
+        self.outbound.flush(|priority, to, msg| {
+            self.stats.sent += 1;
+            if self.monitoring {
+                let _ = self
+                    .tx
+                    .send(NetEvent::Traffic(TrafficEntry::new(msg, true)));
+            }
+            match to {
+                Some(to) => {
+                    if let Some((local, bytes)) = self.fan_out.send(to, msg) {
+                        self.capture.push(local, to, &bytes);
+                    }
+                }
+                None => self.sockets.send(priority, active, msg, &mut self.capture),
+            }
+        });
+    }
+
+    // Decodes packets from the routes allowed to drive the display. True if
+    // a console scene changed.
+    fn receive(&mut self, packets: Vec<(Source, Vec<u8>)>, events: &mut Vec<NetEvent>) -> bool {
+        let mut scene_changed = false;
+        for (source, bytes) in packets {
+            self.stats.received += 1;
+            if self.monitoring {
+                events.extend(incoming_traffic(&bytes));
+            }
+            let drives = self.failover.accepts(source, Instant::now());
+            if let Source::Primary = source {
+                self.subscription.heard();
+            }
+            let mut no_qlab = None;
+            let (expiry, qlab) = match source {
+                Source::Primary | Source::Backup => (&mut self.subscription.expiry, &mut self.qlab),
+                Source::BackupHost => (&mut self.backup_host_expiry, &mut self.qlab),
+                // Pushed OSC never touches our own expiry.
+                Source::Listen => (&mut self.listen_expiry, &mut no_qlab),
+                Source::Console => {
+                    match rosc::decoder::decode_udp(&bytes) {
+                        Ok((_, packet)) => {
+                            for msg in packet_messages(packet) {
+                                scene_changed |= self.x32.handle_message(&msg);
+                            }
+                        }
+                        Err(err) => {
+                            tracing::debug!("Console packet not decoded: {err:?}");
+                            self.stats.decode_failures += 1;
+                        }
+                    }
+                    continue;
+                }
+            };
+            if drives {
+                events.extend(counted(
+                    decode_timed(
+                        &bytes,
+                        expiry,
+                        &mut self.cue_stamps,
+                        &mut self.debounce,
+                        &self.mappings,
+                        &mut self.script,
+                        qlab,
+                    ),
+                    &mut self.stats,
+                ));
+            } else if let Source::BackupHost = source {
+                // The backup host's own subscription is still kept track of.
+                counted(
+                    decode_packet(&bytes, expiry, &[], &mut None, &mut None),
+                    &mut self.stats,
+                );
+            }
+        }
+        scene_changed
+    }
+
+    fn follow_console(&mut self, mut scene_changed: bool, events: &mut Vec<NetEvent>) {
+        let Some(socket) = &self.sockets.console else {
+            if self.failover.on_console {
+                self.failover.on_console = false;
+                events.push(NetEvent::ConsoleActive(false));
+            }
+            return;
+        };
+        for query in self.x32.queries.drain(..) {
+            if let Some(bytes) = send_osc(socket, &query) {
+                self.capture.record_sent(socket, &bytes);
+            }
+        }
+        let active = self.failover.console_active(Instant::now());
+        if active != self.failover.on_console {
+            self.failover.on_console = active;
+            tracing::info!(
+                "Console scenes {}",
+                if active { "showing" } else { "hidden" }
+            );
+            events.push(NetEvent::ConsoleActive(active));
+            scene_changed = active;
+        }
+        if self.failover.on_console
+            && scene_changed
+            && let Some(scene) = self.x32.current()
+        {
+            events.push(NetEvent::CueFired(scene));
+        }
+    }
+
+    fn follow_routes(&mut self, events: &mut Vec<NetEvent>) {
+        let now = Instant::now();
+        let failover = &mut self.failover;
+        if self.sockets.backup.is_some() {
+            let active = failover.active_route(now);
+            if active != failover.route {
+                failover.route = active;
+                tracing::info!("Route changed to {active:?}");
+                events.push(NetEvent::RouteChanged(active));
+            }
+        }
+
+        let on_backup_host = self.sockets.backup_host.is_some() && failover.backup_host_active(now);
+        if on_backup_host != failover.on_backup_host {
+            failover.on_backup_host = on_backup_host;
+            events.push(NetEvent::BackupHostActive(on_backup_host));
+        }
+
+        let pushing = self.sockets.listen.is_some() && failover.pushing(now, self.lost_after);
+        if pushing != failover.pushed {
+            failover.pushed = pushing;
+            events.push(NetEvent::Pushed(pushing));
+        }
+    }
+
+    fn check_lost(&mut self, events: &mut Vec<NetEvent>) {
+        if self.subscription.expiry == 0 || self.failover.last_rx.elapsed() < self.lost_after {
+            return;
+        }
+        tracing::warn!(
+            "Nothing from {} for {} s; resubscribing",
+            self.target.host,
+            self.lost_after.as_secs()
+        );
+        self.subscription.lost(Instant::now());
+        push_subscribe(&mut self.outbound, &self.qlab);
+        events.push(NetEvent::ConnectionLost);
+    }
+
+    fn deliver(&mut self, events: Vec<NetEvent>) {
+        for ev in events {
+            match ev {
+                NetEvent::SubscribeOk(secs) => tracing::debug!("Subscribed for {secs} s"),
+                NetEvent::SubscribeFail => {
+                    tracing::warn!("Subscription rejected by {}", self.target.host);
+                    self.subscription.rejected();
+                }
+                NetEvent::Thump => {
+                    self.stats.thumps += 1;
+                    self.stats.missed_thumps += self.subscription.thumped(Instant::now());
+                }
+                _ => {}
+            }
+            for (to, msg) in self.fan_out.forward(&ev) {
+                self.outbound.forward(to, msg);
+            }
+            if let NetEvent::CueFired(_) = ev
+                && let Some((received, decoded)) = self.cue_stamps
+            {
+                let _ = self.tx.send(NetEvent::CueTiming(CueTiming {
+                    received,
+                    decoded,
+                    queued: Instant::now(),
+                }));
+            }
+            if let NetEvent::SubscribeOk(_) = ev {
+                let _ = self
+                    .tx
+                    .send(NetEvent::RoundTrip(self.subscription.last_sent.elapsed()));
+                if self.subscription.accepted() && self.qlab.is_none() {
+                    // Ask for the live and standby cues so a display started
+                    // mid-show fills in before the next GO.
+                    self.outbound
+                        .push(Priority::Normal, osc_message("/currentcue", &[]));
+                    self.outbound
+                        .push(Priority::Normal, osc_message("/nextcue", &[]));
+                }
+            }
+            let awake = match ev {
+                NetEvent::SubscribeOk(_) => true,
+                NetEvent::SubscribeFail | NetEvent::Resumed => false,
+                _ => self.keep_awake,
+            };
+            if awake != self.keep_awake {
+                self.keep_awake = awake;
+                set_keep_awake(awake);
+            }
+            let _ = self.tx.send(ev);
+        }
+    }
+
+    // Not worth waking the UI for; it picks these up on its next frame.
+    fn report_stats(&mut self) {
+        if self.stats != self.reported && self.last_report.elapsed() >= STATS_EVERY {
+            let _ = self.tx.send(NetEvent::Stats(self.stats));
+            self.reported = self.stats;
+            self.last_report = Instant::now();
+        }
+    }
+
+    fn close(mut self) {
+        self.sockets.close();
+        if self.keep_awake {
+            set_keep_awake(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::CueInfo;
+    use rosc::OscPacket;
+    use std::net::Ipv4Addr;
+    use std::sync::mpsc;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn unanswered_subscribes_are_flagged_once() {
+        let start = Instant::now();
+        let mut sub = Subscription::new(start);
+        let flagged: Vec<bool> = (1..=NO_REPLY_AFTER + 2)
+            .map(|n| sub.sent(start + secs(n as u64)))
+            .collect();
+        assert_eq!(flagged.iter().filter(|&&f| f).count(), 1);
+        assert!(flagged[NO_REPLY_AFTER as usize - 1]);
+        sub.heard();
+        assert_eq!(sub.sent_since_rx, 0);
+        assert!(!sub.refused);
+    }
+
+    #[test]
+    fn renews_halfway_through_a_granted_subscription() {
+        let start = Instant::now();
+        let mut sub = Subscription::new(start);
+        sub.sent(start);
+        assert_eq!(sub.retry_at(), Some(start + sub.interval()));
+        sub.expiry = 30;
+        assert_eq!(sub.interval(), secs(15));
+        assert_eq!(sub.retry_at(), None);
+        sub.expiry = 1;
+        assert_eq!(sub.interval(), secs(2));
+    }
+
+    #[test]
+    fn cues_asked_for_once_per_connection() {
+        let mut sub = Subscription::new(Instant::now());
+        sub.rejected();
+        sub.rejected();
+        assert_eq!(sub.rejections, 2);
+        assert!(sub.accepted());
+        assert_eq!(sub.rejections, 0);
+        assert!(!sub.accepted());
+        sub.restart();
+        assert!(sub.accepted());
+        sub.lost(Instant::now());
+        assert!(sub.accepted());
+    }
+
+    #[test]
+    fn missed_thumps_counted_from_the_gap() {
+        let start = Instant::now();
+        let mut sub = Subscription::new(start);
+        assert_eq!(sub.thumped(start), 0);
+        assert_eq!(sub.thumped(start + THUMP_EVERY), 0);
+        assert_eq!(sub.thumped(start + THUMP_EVERY * 4), 2);
+        sub.restart();
+        assert_eq!(sub.thumped(start + THUMP_EVERY * 10), 0);
+    }
+
+    #[test]
+    fn backup_route_waits_for_the_primary_to_go_quiet() {
+        let start = Instant::now();
+        let mut failover = Failover::new(start);
+        assert!(failover.accepts(Source::Primary, start));
+        assert!(!failover.accepts(Source::Backup, start + secs(1)));
+        assert_eq!(failover.active_route(start + secs(1)), Route::Primary);
+        let later = start + ROUTE_FAILOVER_AFTER + secs(1);
+        assert!(failover.accepts(Source::Backup, later));
+        assert_eq!(failover.active_route(later), Route::Backup);
+        assert_eq!(failover.last_rx, later);
+    }
+
+    #[test]
+    fn backup_host_then_console_take_over_in_turn() {
+        let start = Instant::now();
+        let mut failover = Failover::new(start);
+        failover.accepts(Source::Primary, start);
+        assert!(!failover.accepts(Source::BackupHost, start + secs(1)));
+        assert!(!failover.backup_host_active(start + secs(1)));
+        assert!(!failover.accepts(Source::Console, start + secs(1)));
+        assert!(!failover.console_active(start + secs(1)));
+
+        let quiet = start + ROUTE_FAILOVER_AFTER + secs(1);
+        assert!(failover.accepts(Source::BackupHost, quiet));
+        assert!(failover.backup_host_active(quiet));
+        failover.accepts(Source::Console, quiet);
+        assert!(!failover.console_active(quiet));
+
+        let later = quiet + ROUTE_FAILOVER_AFTER;
+        failover.accepts(Source::Console, later);
+        assert!(failover.console_active(later));
+    }
+
+    #[test]
+    fn pushed_osc_always_drives_the_display() {
+        let start = Instant::now();
+        let mut failover = Failover::new(start);
+        failover.accepts(Source::Primary, start);
+        assert!(failover.accepts(Source::Listen, start));
+        assert!(failover.pushing(start + secs(5), secs(10)));
+        assert!(!failover.pushing(start + secs(10), secs(10)));
+    }
+
+    // A session reaching a host on localhost, and over a backup route from
+    // the same machine if asked, with its input and event channels.
+    async fn reaching(
+        host: &UdpSocket,
+        backup_route: bool,
+    ) -> (Session, UnboundedReceiver<Input>, Receiver<NetEvent>) {
+        let (tx, events) = mpsc::channel();
+        let (input_tx, input) = unbounded_channel();
+        let port = host.local_addr().unwrap().port();
+        let mut target = Target::new("127.0.0.1".to_string(), 0, port);
+        if backup_route {
+            target.backup_ip = Some(Ipv4Addr::LOCALHOST.into());
+        }
+        (Session::start(target, tx, input_tx).await, input, events)
+    }
+
+    // Addresses of the datagrams that reached the host, with where each
+    // came from.
+    fn on_the_wire(host: &UdpSocket) -> Vec<(String, SocketAddr)> {
+        host.set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        let mut buf = [0u8; 1536];
+        let mut seen = Vec::new();
+        while let Ok((len, from)) = host.recv_from(&mut buf) {
+            if let Ok((_, OscPacket::Message(msg))) = rosc::decoder::decode_udp(&buf[..len]) {
+                seen.push((msg.addr, from));
+            }
+        }
+        seen
+    }
+
+    #[tokio::test]
+    async fn commands_go_out_once_with_a_backup_route() {
+        let host = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (mut session, _input, _events) = reaching(&host, true).await;
+        let backup = session
+            .sockets
+            .backup
+            .as_ref()
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut pass = Pass::default();
+        session.handle(Input::Cmd(NetCmd::Send(osc_message("/go", &[]))), &mut pass);
+        session.flush();
+        let sent = on_the_wire(&host);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, "/go");
+        assert_ne!(sent[0].1, backup);
+
+        // Once the backup route drives the display, commands move to it.
+        session.failover.route = Route::Backup;
+        session.handle(
+            Input::Cmd(NetCmd::Send(osc_message("/back", &[]))),
+            &mut pass,
+        );
+        session.flush();
+        assert_eq!(on_the_wire(&host), [("/back".to_string(), backup)]);
+
+        // Keep-alives still go both ways.
+        session.handle(Input::ThumpDue, &mut pass);
+        session.flush();
+        let sent = on_the_wire(&host);
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|(addr, _)| addr == "/thump"));
+        session.close();
+    }
+
+    #[tokio::test]
+    async fn forwards_are_queued_captured_and_monitored() {
+        let host = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let to = receiver.local_addr().unwrap();
+        let (mut session, _input, events) = reaching(&host, false).await;
+
+        let mut pass = Pass::default();
+        session.handle(Input::Cmd(NetCmd::SetMonitor(true)), &mut pass);
+        session.handle(Input::Cmd(NetCmd::SetForwardTargets(vec![to])), &mut pass);
+        session.deliver(vec![NetEvent::CueFired(CueInfo {
+            number: "12".to_string(),
+            ..CueInfo::default()
+        })]);
+        // Nothing leaves until the queue is flushed.
+        assert_eq!(session.outbound.next_due(), Some(Duration::ZERO));
+        session.flush();
+
+        let forwarded = on_the_wire(&receiver);
+        assert_eq!(forwarded.len(), 1);
+        assert_eq!(forwarded[0].0, "/cuefired");
+        assert!(on_the_wire(&host).is_empty());
+        assert!(session.capture.packets.iter().any(|p| p.dst == to));
+        assert!(events.try_iter().any(|ev| matches!(
+            ev,
+            NetEvent::Traffic(entry) if entry.address == "/cuefired"
+        )));
+        session.close();
+    }
+
+    #[tokio::test]
+    async fn holds_reach_targets_added_later() {
+        let host = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let later = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let first_addr = first.local_addr().unwrap();
+        let (mut session, _input, _events) = reaching(&host, false).await;
+
+        let mut pass = Pass::default();
+        session.handle(
+            Input::Cmd(NetCmd::SetForwardTargets(vec![first_addr])),
+            &mut pass,
+        );
+        session.handle(Input::Cmd(NetCmd::ForwardHold(true)), &mut pass);
+        session.flush();
+        assert_eq!(on_the_wire(&first).len(), 1);
+
+        session.handle(
+            Input::Cmd(NetCmd::SetForwardTargets(vec![
+                first_addr,
+                later.local_addr().unwrap(),
+            ])),
+            &mut pass,
+        );
+        session.flush();
+        let caught_up = on_the_wire(&later);
+        assert_eq!(caught_up.len(), 1);
+        assert_eq!(caught_up[0].0, "/display/hold");
+        assert!(on_the_wire(&first).is_empty());
+        session.close();
+    }
+}
@@ -0,0 +1,101 @@
+//! Operator pages: short messages from FOH, over OSC or HTTP, that cover the
+//! display until they time out or someone at the display acknowledges them.
+
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Debug)]
+pub struct Page {
+    pub id: u64,
+    pub text: String,
+    // None stays up until acknowledged.
+    pub timeout: Option<Duration>,
+    // The OSC sender, told when the page is acknowledged. HTTP senders poll
+    // /page instead.
+    pub reply_to: Option<SocketAddr>,
+}
+
+impl Page {
+    pub fn new(text: &str, timeout: Option<Duration>) -> Page {
+        Page {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            text: text.trim().to_string(),
+            timeout,
+            reply_to: None,
+        }
+    }
+}
+
+// Zero, negative or nonsense times leave the page up until acknowledged.
+pub fn page_timeout(secs: f32) -> Option<Duration> {
+    (secs.is_finite() && secs > 0.0).then(|| Duration::from_secs_f32(secs.min(86_400.0)))
+}
+
+// How the latest page went, for senders polling the web server.
+#[derive(Clone, Debug, Serialize)]
+pub struct PageStatus {
+    pub id: u64,
+    pub text: String,
+    pub showing: bool,
+    pub acknowledged: bool,
+}
+
+// The page on screen, if any, and the outcome of the latest one.
+#[derive(Default)]
+pub struct Pager {
+    current: Option<(Page, Instant)>,
+    pub last: Option<PageStatus>,
+}
+
+impl Pager {
+    // A new page replaces whatever was showing.
+    pub fn show(&mut self, page: Page) {
+        tracing::info!("Page {}: {}", page.id, page.text);
+        self.last = Some(PageStatus {
+            id: page.id,
+            text: page.text.clone(),
+            showing: true,
+            acknowledged: false,
+        });
+        self.current = Some((page, Instant::now()));
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+        if let Some(last) = &mut self.last {
+            last.showing = false;
+        }
+    }
+
+    pub fn current(&self) -> Option<&Page> {
+        self.current.as_ref().map(|(page, _)| page)
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        let (page, since) = self.current.as_ref()?;
+        Some(page.timeout?.saturating_sub(since.elapsed()))
+    }
+
+    pub fn expire(&mut self) {
+        if self.remaining().is_some_and(|left| left.is_zero()) {
+            if let Some(page) = self.current() {
+                tracing::info!("Page {} timed out unacknowledged", page.id);
+            }
+            self.clear();
+        }
+    }
+
+    pub fn acknowledge(&mut self) -> Option<Page> {
+        let (page, _) = self.current.take()?;
+        tracing::info!("Page {} acknowledged", page.id);
+        if let Some(last) = &mut self.last {
+            last.showing = false;
+            last.acknowledged = true;
+        }
+        Some(page)
+    }
+}
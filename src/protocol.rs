@@ -0,0 +1,791 @@
+//! TheatreMix OSC messages: decoding incoming cues and building outgoing commands.
+
+use crate::config::LayoutPreset;
+use crate::event::{CueInfo, NetEvent, TrafficEntry};
+use crate::paging::{Page, page_timeout};
+use crate::qlab::Qlab;
+use crate::script::Script;
+use clap::ValueEnum;
+use rosc::{OscBundle, OscError, OscMessage, OscPacket, OscTime, OscType};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub fn decode_packet(
+    bytes: &[u8],
+    subscription_expiry: &mut u32,
+    mappings: &[OscMapping],
+    script: &mut Option<Script>,
+    qlab: &mut Option<Qlab>,
+) -> Result<Vec<NetEvent>, OscError> {
+    let mut events = Vec::new();
+    let (_, packet) = rosc::decoder::decode_udp(bytes)?;
+    for msg in packet_messages(packet) {
+        let extra = script
+            .as_mut()
+            .map(|script| script.on_message(&msg))
+            .unwrap_or_default();
+        events.extend(mappings.iter().filter_map(|mapping| mapping.apply(&msg)));
+        events.extend(match qlab {
+            Some(qlab) => qlab.handle_message(&msg, subscription_expiry),
+            None => handle_message(msg, subscription_expiry),
+        });
+        events.extend(extra);
+    }
+    // The script sees every cue before anything shows it.
+    if let Some(script) = script {
+        for ev in &mut events {
+            match ev {
+                NetEvent::CueFired(cue) | NetEvent::NextCue(cue) | NetEvent::CurrentCue(cue) => {
+                    *cue = script.on_cue(std::mem::take(cue));
+                }
+                _ => {}
+            }
+        }
+        events.extend(script.widget_events());
+    }
+    Ok(events)
+}
+
+// Seconds from the NTP epoch that OSC time tags count from to 1970.
+const NTP_TO_UNIX: u64 = 2_208_988_800;
+
+// Further ahead than this, a time tag says more about the sender's clock
+// than its intent, so the messages are applied at once.
+const MAX_BUNDLE_DELAY: Duration = Duration::from_secs(2);
+
+// The messages in a packet, through any depth of bundles, in time tag
+// order. Messages sharing a time tag keep the order they were sent in.
+pub fn packet_messages(packet: OscPacket) -> Vec<OscMessage> {
+    let mut timed = Vec::new();
+    collect_messages(packet, 0, &mut timed);
+    timed.sort_by_key(|(tag, _)| *tag);
+    timed.into_iter().map(|(_, msg)| msg).collect()
+}
+
+// A nested bundle can't be due before the one holding it.
+fn collect_messages(packet: OscPacket, tag: u64, out: &mut Vec<(u64, OscMessage)>) {
+    match packet {
+        OscPacket::Message(msg) => out.push((tag, msg)),
+        OscPacket::Bundle(bundle) => {
+            let tag = tag.max(timetag_key(bundle.timetag));
+            for packet in bundle.content {
+                collect_messages(packet, tag, out);
+            }
+        }
+    }
+}
+
+// Time tags compare as one 64-bit fixed point number; "immediately" is 1.
+fn timetag_key(time: OscTime) -> u64 {
+    (u64::from(time.seconds) << 32) | u64::from(time.fractional)
+}
+
+fn timetag_time(key: u64) -> Option<SystemTime> {
+    let secs = (key >> 32).checked_sub(NTP_TO_UNIX)?;
+    let nanos = ((key & 0xffff_ffff) * 1_000_000_000) >> 32;
+    Some(UNIX_EPOCH + Duration::new(secs, nanos as u32))
+}
+
+// How long to wait for a time tag; zero for now, the past and anything
+// beyond MAX_BUNDLE_DELAY.
+fn timetag_delay(key: u64, now: SystemTime) -> Duration {
+    let Some(delay) = timetag_time(key).and_then(|at| at.duration_since(now).ok()) else {
+        return Duration::ZERO;
+    };
+    if delay > MAX_BUNDLE_DELAY {
+        tracing::debug!("Bundle stamped {delay:?} ahead; applying now");
+        return Duration::ZERO;
+    }
+    delay
+}
+
+// Splits a packet by when its messages are due, each message taking the
+// time tag of its innermost bundle. Parts to wait for are re-encoded as
+// bundles of their own; a packet with nothing to wait for comes back whole.
+pub fn schedule_packet(bytes: Vec<u8>) -> Vec<(Duration, Vec<u8>)> {
+    let Ok((_, packet @ OscPacket::Bundle(_))) = rosc::decoder::decode_udp(&bytes) else {
+        return vec![(Duration::ZERO, bytes)];
+    };
+    let mut timed = Vec::new();
+    collect_messages(packet, 0, &mut timed);
+    timed.sort_by_key(|(tag, _)| *tag);
+    let now = SystemTime::now();
+    let mut parts: Vec<(Duration, Vec<OscPacket>)> = Vec::new();
+    for (tag, msg) in timed {
+        let delay = timetag_delay(tag, now);
+        match parts.last_mut() {
+            Some((last, content)) if *last == delay => content.push(OscPacket::Message(msg)),
+            _ => parts.push((delay, vec![OscPacket::Message(msg)])),
+        }
+    }
+    if parts.iter().all(|(delay, _)| delay.is_zero()) {
+        return vec![(Duration::ZERO, bytes)];
+    }
+    parts
+        .into_iter()
+        .filter_map(|(delay, content)| {
+            let bundle = OscPacket::Bundle(OscBundle {
+                timetag: OscTime {
+                    seconds: 0,
+                    fractional: 1,
+                },
+                content,
+            });
+            rosc::encoder::encode(&bundle)
+                .ok()
+                .map(|bytes| (delay, bytes))
+        })
+        .collect()
+}
+
+pub fn incoming_traffic(bytes: &[u8]) -> Vec<NetEvent> {
+    let Ok((_, packet)) = rosc::decoder::decode_udp(bytes) else {
+        return Vec::new();
+    };
+    packet_messages(packet)
+        .iter()
+        .map(|msg| NetEvent::Traffic(TrafficEntry::new(msg, false)))
+        .collect()
+}
+
+// OSC 1.1 streams frame each packet with double-ended SLIP (RFC 1055).
+const SLIP_END: u8 = 0xc0;
+const SLIP_ESC: u8 = 0xdb;
+const SLIP_ESC_END: u8 = 0xdc;
+const SLIP_ESC_ESC: u8 = 0xdd;
+
+pub fn slip_encode(packet: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(packet.len() + 2);
+    out.push(SLIP_END);
+    for &byte in packet {
+        match byte {
+            SLIP_END => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+            _ => out.push(byte),
+        }
+    }
+    out.push(SLIP_END);
+    out
+}
+
+// The largest OSC packet a UDP datagram can carry; TCP frames past this are
+// dropped, so a peer that never sends END can't grow the buffer without end.
+const MAX_SLIP_FRAME: usize = 65507;
+
+// Reassembles packets from a SLIP stream read in arbitrary chunks.
+#[derive(Default)]
+pub struct SlipDecoder {
+    frame: Vec<u8>,
+    escaped: bool,
+    // Set once the frame outgrows MAX_SLIP_FRAME; the rest of it is skipped.
+    oversized: bool,
+}
+
+impl SlipDecoder {
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+        for &byte in bytes {
+            if self.oversized && byte != SLIP_END {
+                continue;
+            }
+            match (self.escaped, byte) {
+                (false, SLIP_END) if self.oversized => {
+                    tracing::debug!("Dropped a SLIP frame over {MAX_SLIP_FRAME} bytes");
+                    self.oversized = false;
+                }
+                (false, SLIP_END) => {
+                    // Back-to-back ENDs delimit an empty frame; skip it.
+                    if !self.frame.is_empty() {
+                        packets.push(std::mem::take(&mut self.frame));
+                    }
+                }
+                (false, SLIP_ESC) => self.escaped = true,
+                (false, _) => self.frame.push(byte),
+                (true, _) => {
+                    self.escaped = false;
+                    self.frame.push(match byte {
+                        SLIP_ESC_END => SLIP_END,
+                        SLIP_ESC_ESC => SLIP_ESC,
+                        // Protocol violation; keep the byte as sent.
+                        other => other,
+                    });
+                }
+            }
+            if self.frame.len() > MAX_SLIP_FRAME {
+                self.frame = Vec::new();
+                self.escaped = false;
+                self.oversized = true;
+            }
+        }
+        packets
+    }
+}
+
+// Retransmits, or a fire arriving over both routes as they fail over, can
+// hand us the same cue twice in quick succession.
+const REPEAT_WINDOW: Duration = Duration::from_millis(500);
+
+// The latest cue fired, so a repeat of it can be dropped before the history,
+// chime and outputs see it twice.
+#[derive(Default)]
+pub struct CueDebounce {
+    last: Option<(CueInfo, Instant)>,
+}
+
+impl CueDebounce {
+    pub fn is_repeat(&mut self, cue: &CueInfo) -> bool {
+        self.is_repeat_at(cue, Instant::now())
+    }
+
+    fn is_repeat_at(&mut self, cue: &CueInfo, now: Instant) -> bool {
+        let repeat = self.last.as_ref().is_some_and(|(last, at)| {
+            last.number == cue.number
+                && last.text == cue.text
+                && now.duration_since(*at) < REPEAT_WINDOW
+        });
+        // Repeats don't extend the window, so a cue GO'd again on purpose
+        // still fires.
+        if !repeat {
+            self.last = Some((cue.clone(), now));
+        }
+        repeat
+    }
+}
+
+pub fn decode_timed(
+    bytes: &[u8],
+    subscription_expiry: &mut u32,
+    cue_stamps: &mut Option<(Instant, Instant)>,
+    debounce: &mut CueDebounce,
+    mappings: &[OscMapping],
+    script: &mut Option<Script>,
+    qlab: &mut Option<Qlab>,
+) -> Result<Vec<NetEvent>, OscError> {
+    let received = Instant::now();
+    let mut events = decode_packet(bytes, subscription_expiry, mappings, script, qlab)?;
+    events.retain(|ev| match ev {
+        NetEvent::CueFired(cue) if debounce.is_repeat(cue) => {
+            tracing::debug!("Dropped repeated fire of cue {}", cue.number);
+            false
+        }
+        _ => true,
+    });
+    if events.iter().any(|ev| matches!(ev, NetEvent::CueFired(_))) {
+        *cue_stamps = Some((received, Instant::now()));
+    }
+    Ok(events)
+}
+
+pub enum ControlMsg {
+    Brightness(f32),
+    Layout(LayoutPreset),
+    Page(Page),
+    ClearPage,
+    Hold(bool),
+}
+
+pub fn parse_control(msg: &OscMessage) -> Option<ControlMsg> {
+    match (msg.addr.as_str(), msg.args.first()) {
+        ("/display/brightness", Some(OscType::Float(level))) => {
+            Some(ControlMsg::Brightness(*level))
+        }
+        // Whole numbers are taken as a percentage.
+        ("/display/brightness", Some(OscType::Int(percent))) => {
+            Some(ControlMsg::Brightness(*percent as f32 / 100.0))
+        }
+        // The same names as --layout: "standard" or "cue-list".
+        ("/display/layout", Some(OscType::String(name))) => LayoutPreset::from_str(name, true)
+            .ok()
+            .map(ControlMsg::Layout),
+        // Text and, for a page, how many seconds to show it; an empty or
+        // missing text clears whatever is showing.
+        ("/display/message" | "/display/page", Some(OscType::String(text)))
+            if !text.trim().is_empty() =>
+        {
+            let timeout = match msg.args.get(1) {
+                Some(OscType::Int(secs)) => page_timeout(*secs as f32),
+                Some(OscType::Float(secs)) => page_timeout(*secs),
+                _ => None,
+            };
+            Some(ControlMsg::Page(Page::new(text, timeout)))
+        }
+        ("/display/message" | "/display/page", None | Some(OscType::String(_))) => {
+            Some(ControlMsg::ClearPage)
+        }
+        ("/display/hold", arg) => hold_arg(arg).map(ControlMsg::Hold),
+        _ => None,
+    }
+}
+
+// 1 or true holds, 0 or false releases.
+fn hold_arg(arg: Option<&OscType>) -> Option<bool> {
+    match arg? {
+        OscType::Int(n) => Some(*n != 0),
+        OscType::Float(n) => Some(*n != 0.0),
+        OscType::Bool(on) => Some(*on),
+        _ => None,
+    }
+}
+
+pub fn handle_message(msg: OscMessage, subscription_expiry: &mut u32) -> Option<NetEvent> {
+    match msg.addr.as_str() {
+        "/subscribeok" => {
+            if let Some(OscType::Int(exp)) = msg.args.first() {
+                *subscription_expiry = (*exp).max(2) as u32;
+                return Some(NetEvent::SubscribeOk(*subscription_expiry));
+            }
+            None
+        }
+        "/subscribefail" => {
+            *subscription_expiry = 0;
+            Some(NetEvent::SubscribeFail)
+        }
+        "/thump" => Some(NetEvent::Thump),
+        "/cuefired" => Some(NetEvent::CueFired(parse_cue(&msg.args))),
+        "/nextcue" => Some(NetEvent::NextCue(parse_cue(&msg.args))),
+        "/currentcue" => Some(NetEvent::CurrentCue(parse_cue(&msg.args))),
+        "/display/hold" => hold_arg(msg.args.first()).map(NetEvent::Hold),
+        _ => None,
+    }
+}
+
+// Where a mapped cue field comes from: an argument or a segment of the
+// address, both counted from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapField {
+    Arg(usize),
+    Segment(usize),
+    Unset,
+}
+
+impl MapField {
+    fn parse(token: &str) -> Option<MapField> {
+        let nth = |n: &str| n.parse::<usize>().ok().filter(|&n| n > 0);
+        if token == "-" {
+            Some(MapField::Unset)
+        } else if let Some(n) = token.strip_prefix("arg") {
+            nth(n).map(MapField::Arg)
+        } else if let Some(n) = token.strip_prefix("seg") {
+            nth(n).map(MapField::Segment)
+        } else {
+            None
+        }
+    }
+}
+
+// Another program's OSC read as cues, for following QLab, Eos and the like
+// over the listen port.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMapping {
+    pub next: bool,
+    // Address segments; `*` matches any one.
+    pub address: Vec<String>,
+    pub number: MapField,
+    pub text: MapField,
+    pub color: MapField,
+}
+
+impl OscMapping {
+    pub fn apply(&self, msg: &OscMessage) -> Option<NetEvent> {
+        let segments: Vec<&str> = msg.addr.split('/').filter(|s| !s.is_empty()).collect();
+        let matches = segments.len() == self.address.len()
+            && self
+                .address
+                .iter()
+                .zip(&segments)
+                .all(|(pattern, segment)| pattern == "*" || pattern == segment);
+        if !matches {
+            return None;
+        }
+        let field = |field: MapField| match field {
+            MapField::Arg(n) => msg.args.get(n - 1).and_then(arg_text),
+            MapField::Segment(n) => segments.get(n - 1).map(|s| s.to_string()),
+            MapField::Unset => None,
+        };
+        let cue = CueInfo {
+            number: field(self.number).unwrap_or_default(),
+            text: field(self.text).unwrap_or_default(),
+            color: field(self.color),
+        };
+        Some(if self.next {
+            NetEvent::NextCue(cue)
+        } else {
+            NetEvent::CueFired(cue)
+        })
+    }
+}
+
+fn arg_text(arg: &OscType) -> Option<String> {
+    match arg {
+        OscType::String(s) => Some(s.clone()),
+        OscType::Int(i) => Some(i.to_string()),
+        OscType::Long(i) => Some(i.to_string()),
+        OscType::Float(f) => Some(f.to_string()),
+        OscType::Double(f) => Some(f.to_string()),
+        _ => None,
+    }
+}
+
+pub fn osc_map_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(base.join("theatremix-remote-display").join("osc-map.txt"))
+}
+
+// One mapping per line: `<cue|next> <address> <number> [text] [color]`, each
+// field `argN`, `segN` or `-`. Blank lines and `#` comments are ignored, as
+// are lines that don't parse.
+pub fn load_osc_map(path: &Path) -> Vec<OscMapping> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mapping = parse_mapping(line);
+            if mapping.is_none() {
+                tracing::warn!("Ignoring OSC map line: {line}");
+            }
+            mapping
+        })
+        .collect()
+}
+
+fn parse_mapping(line: &str) -> Option<OscMapping> {
+    let mut parts = line.split_whitespace();
+    let next = match parts.next()? {
+        "cue" => false,
+        "next" => true,
+        _ => return None,
+    };
+    let address = parts.next()?.strip_prefix('/')?;
+    let mut field = || parts.next().map_or(Some(MapField::Unset), MapField::parse);
+    Some(OscMapping {
+        next,
+        address: address.split('/').map(str::to_string).collect(),
+        number: field()?,
+        text: field()?,
+        color: field()?,
+    })
+}
+
+// `/cuefired` and `/nextcue` share the same number, text, color layout.
+pub fn parse_cue(args: &[OscType]) -> CueInfo {
+    let mut info = CueInfo::default();
+    if let Some(OscType::String(num)) = args.first() {
+        info.number = num.clone();
+    }
+    if let Some(OscType::String(text)) = args.get(1) {
+        info.text = text.clone();
+    }
+    if let Some(OscType::String(color)) = args.get(2) {
+        info.color = Some(color.clone());
+    }
+    info
+}
+
+pub fn osc_arg(text: &str) -> OscType {
+    if let Ok(int) = text.parse::<i32>() {
+        OscType::Int(int)
+    } else if let Ok(float) = text.parse::<f32>() {
+        OscType::Float(float)
+    } else {
+        OscType::String(text.to_string())
+    }
+}
+
+pub fn osc_message(addr: &str, args: &[OscType]) -> OscMessage {
+    OscMessage {
+        addr: addr.to_string(),
+        args: args.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(timetag: OscTime, content: Vec<OscPacket>) -> OscPacket {
+        OscPacket::Bundle(OscBundle { timetag, content })
+    }
+
+    fn message(addr: &str) -> OscPacket {
+        OscPacket::Message(osc_message(addr, &[]))
+    }
+
+    fn addrs(packet: OscPacket) -> Vec<String> {
+        packet_messages(packet)
+            .into_iter()
+            .map(|msg| msg.addr)
+            .collect()
+    }
+
+    // The time tag for `from_now` after the present.
+    fn stamp(from_now: Duration) -> OscTime {
+        let since = (SystemTime::now() + from_now)
+            .duration_since(UNIX_EPOCH)
+            .unwrap();
+        OscTime {
+            seconds: (since.as_secs() + NTP_TO_UNIX) as u32,
+            fractional: ((u64::from(since.subsec_nanos()) << 32) / 1_000_000_000) as u32,
+        }
+    }
+
+    const IMMEDIATELY: OscTime = OscTime {
+        seconds: 0,
+        fractional: 1,
+    };
+
+    #[test]
+    fn nested_bundles_come_out_in_time_tag_order() {
+        let packet = bundle(
+            OscTime {
+                seconds: 10,
+                fractional: 0,
+            },
+            vec![
+                message("/a"),
+                bundle(
+                    OscTime {
+                        seconds: 30,
+                        fractional: 0,
+                    },
+                    vec![message("/late")],
+                ),
+                bundle(
+                    OscTime {
+                        seconds: 20,
+                        fractional: 0,
+                    },
+                    vec![message("/b"), message("/c")],
+                ),
+            ],
+        );
+        assert_eq!(addrs(packet), ["/a", "/b", "/c", "/late"]);
+    }
+
+    #[test]
+    fn an_inner_bundle_is_never_due_before_its_outer_one() {
+        let packet = bundle(
+            OscTime {
+                seconds: 20,
+                fractional: 0,
+            },
+            vec![
+                bundle(IMMEDIATELY, vec![message("/inner")]),
+                message("/outer"),
+            ],
+        );
+        // Both take the outer tag, so the sent order stands.
+        assert_eq!(addrs(packet), ["/inner", "/outer"]);
+    }
+
+    #[test]
+    fn messages_sharing_a_time_tag_keep_their_order() {
+        let packet = bundle(
+            IMMEDIATELY,
+            vec![message("/1"), message("/2"), message("/3")],
+        );
+        assert_eq!(addrs(packet), ["/1", "/2", "/3"]);
+    }
+
+    #[test]
+    fn a_packet_with_nothing_to_wait_for_is_passed_through_whole() {
+        let bytes = rosc::encoder::encode(&bundle(IMMEDIATELY, vec![message("/now")])).unwrap();
+        assert_eq!(
+            schedule_packet(bytes.clone()),
+            vec![(Duration::ZERO, bytes)]
+        );
+        let bytes = rosc::encoder::encode(&message("/plain")).unwrap();
+        assert_eq!(
+            schedule_packet(bytes.clone()),
+            vec![(Duration::ZERO, bytes)]
+        );
+    }
+
+    #[test]
+    fn malformed_packets_are_passed_through_to_fail_decoding() {
+        let bytes = vec![1, 2, 3];
+        assert_eq!(
+            schedule_packet(bytes.clone()),
+            vec![(Duration::ZERO, bytes)]
+        );
+    }
+
+    #[test]
+    fn a_future_dated_inner_bundle_waits_for_its_own_time_tag() {
+        let packet = bundle(
+            IMMEDIATELY,
+            vec![
+                message("/now"),
+                bundle(stamp(Duration::from_millis(500)), vec![message("/later")]),
+            ],
+        );
+        let parts = schedule_packet(rosc::encoder::encode(&packet).unwrap());
+        assert_eq!(parts.len(), 2);
+        assert!(parts[0].0.is_zero());
+        assert!(parts[1].0 > Duration::from_millis(400));
+        assert!(parts[1].0 <= Duration::from_millis(500));
+        let decoded = |bytes: &[u8]| addrs(rosc::decoder::decode_udp(bytes).unwrap().1);
+        assert_eq!(decoded(&parts[0].1), ["/now"]);
+        assert_eq!(decoded(&parts[1].1), ["/later"]);
+    }
+
+    #[test]
+    fn time_tags_past_the_cap_or_in_the_past_apply_at_once() {
+        let now = SystemTime::now();
+        let key = |time: OscTime| timetag_key(time);
+        assert!(timetag_delay(key(stamp(Duration::from_secs(60))), now).is_zero());
+        assert!(timetag_delay(key(IMMEDIATELY), now).is_zero());
+        let past = (now - Duration::from_secs(5))
+            .duration_since(UNIX_EPOCH)
+            .unwrap();
+        let past = OscTime {
+            seconds: (past.as_secs() + NTP_TO_UNIX) as u32,
+            fractional: 0,
+        };
+        assert!(timetag_delay(key(past), now).is_zero());
+        assert!(!timetag_delay(key(stamp(MAX_BUNDLE_DELAY / 2)), now).is_zero());
+    }
+    fn cue(number: &str, text: &str) -> CueInfo {
+        CueInfo {
+            number: number.to_string(),
+            text: text.to_string(),
+            color: None,
+        }
+    }
+
+    #[test]
+    fn repeats_dropped_inside_window() {
+        let start = Instant::now();
+        let mut debounce = CueDebounce::default();
+        assert!(!debounce.is_repeat_at(&cue("1", "Lights up"), start));
+        let just_inside = start + REPEAT_WINDOW - Duration::from_millis(1);
+        assert!(debounce.is_repeat_at(&cue("1", "Lights up"), just_inside));
+        // The window is half-open: a repeat exactly at its end fires.
+        assert!(!debounce.is_repeat_at(&cue("1", "Lights up"), start + REPEAT_WINDOW));
+    }
+
+    #[test]
+    fn repeats_do_not_extend_window() {
+        let start = Instant::now();
+        let mut debounce = CueDebounce::default();
+        debounce.is_repeat_at(&cue("1", ""), start);
+        assert!(debounce.is_repeat_at(&cue("1", ""), start + Duration::from_millis(400)));
+        assert!(!debounce.is_repeat_at(&cue("1", ""), start + Duration::from_millis(600)));
+    }
+
+    #[test]
+    fn different_cues_are_not_repeats() {
+        let start = Instant::now();
+        let mut debounce = CueDebounce::default();
+        debounce.is_repeat_at(&cue("1", "Lights up"), start);
+        assert!(!debounce.is_repeat_at(&cue("2", "Lights up"), start));
+        assert!(!debounce.is_repeat_at(&cue("2", "Blackout"), start));
+        // Cue 1 again after another cue is a GO, not a repeat.
+        assert!(!debounce.is_repeat_at(&cue("1", "Lights up"), start));
+    }
+
+    #[test]
+    fn slip_frames_split_across_reads() {
+        let mut slip = SlipDecoder::default();
+        assert!(slip.push(&[SLIP_END, b'/', b't']).is_empty());
+        assert_eq!(slip.push(&[b'h', SLIP_END, b'/']), vec![b"/th".to_vec()]);
+        assert_eq!(slip.push(&[b'x', SLIP_END]), vec![b"/x".to_vec()]);
+    }
+
+    #[test]
+    fn slip_escapes() {
+        let mut slip = SlipDecoder::default();
+        let wire = [
+            1,
+            SLIP_ESC,
+            SLIP_ESC_END,
+            2,
+            SLIP_ESC,
+            SLIP_ESC_ESC,
+            3,
+            SLIP_END,
+        ];
+        assert_eq!(slip.push(&wire), vec![vec![1, SLIP_END, 2, SLIP_ESC, 3]]);
+        // An escape split from its byte by a read boundary.
+        assert!(slip.push(&[4, SLIP_ESC]).is_empty());
+        assert_eq!(
+            slip.push(&[SLIP_ESC_END, SLIP_END]),
+            vec![vec![4, SLIP_END]]
+        );
+    }
+
+    #[test]
+    fn slip_drops_oversized_frames() {
+        let mut slip = SlipDecoder::default();
+        let big = vec![1u8; MAX_SLIP_FRAME + 1];
+        assert!(slip.push(&big).is_empty());
+        assert!(slip.push(&[2, 3, SLIP_END]).is_empty());
+        assert_eq!(slip.push(&[4, SLIP_END]), vec![vec![4]]);
+    }
+
+    #[test]
+    fn slip_malformed_input() {
+        let mut slip = SlipDecoder::default();
+        // Empty frames between back-to-back ENDs are skipped.
+        assert_eq!(
+            slip.push(&[SLIP_END, SLIP_END, 7, SLIP_END, SLIP_END]),
+            vec![vec![7]]
+        );
+        // A bad escape keeps the byte as sent.
+        assert_eq!(slip.push(&[SLIP_ESC, 9, SLIP_END]), vec![vec![9]]);
+    }
+
+    #[test]
+    fn mappings_parse() {
+        assert_eq!(
+            parse_mapping("cue /eos/out/event/cue/*/* seg6 arg1 -"),
+            Some(OscMapping {
+                next: false,
+                address: ["eos", "out", "event", "cue", "*", "*"]
+                    .map(String::from)
+                    .to_vec(),
+                number: MapField::Segment(6),
+                text: MapField::Arg(1),
+                color: MapField::Unset,
+            })
+        );
+        // Fields left off the end are unset.
+        let mapping = parse_mapping("next /qlab/next arg2").unwrap();
+        assert!(mapping.next);
+        assert_eq!(mapping.number, MapField::Arg(2));
+        assert_eq!(mapping.text, MapField::Unset);
+    }
+
+    #[test]
+    fn malformed_mappings_rejected() {
+        for line in [
+            "fire /go arg1",
+            "cue go arg1",
+            "cue /go arg0",
+            "cue /go argx",
+            "cue /go field1",
+            "cue",
+        ] {
+            assert_eq!(parse_mapping(line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn osc_map_file_skips_comments_and_bad_lines() {
+        let path = std::env::temp_dir().join(format!("osc-map-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "# QLab\n\ncue /qlab/go arg1 arg2\nnonsense\n  next /qlab/next arg1  \n",
+        )
+        .unwrap();
+        let map = load_osc_map(&path);
+        fs::remove_file(&path).ok();
+        assert_eq!(map.len(), 2);
+        assert!(map[1].next);
+        assert!(load_osc_map(Path::new("/no/such/osc-map.txt")).is_empty());
+    }
+}
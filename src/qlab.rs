@@ -0,0 +1,167 @@
+//! QLab's OSC: /connect with the workspace passcode, /updates for pushed
+//! changes, and queries for the playhead and running cues, answered as the
+//! same cue events TheatreMix sends.
+
+use crate::event::{CueInfo, NetEvent};
+use crate::protocol::osc_message;
+use rosc::{OscMessage, OscType};
+use serde_json::Value;
+use std::collections::HashSet;
+
+pub const DEFAULT_QLAB_PORT: u16 = 53000;
+
+// QLab drops an /updates client it hasn't heard from in a minute; renewing
+// at half that, as with a TheatreMix subscription, keeps us on.
+const UPDATES_FOR: u32 = 60;
+
+const CUE_KEYS: &str = r#"["number","displayName","colorName"]"#;
+
+#[derive(Default)]
+pub struct Qlab {
+    passcode: String,
+    // Unique IDs running at the last answer; None until the first, so cues
+    // already running when we connect aren't fired.
+    running: Option<HashSet<String>>,
+    // Queries prompted by replies and updates, for the network thread to send.
+    pub queries: Vec<OscMessage>,
+}
+
+impl Qlab {
+    pub fn new(passcode: String) -> Qlab {
+        Qlab {
+            passcode,
+            ..Qlab::default()
+        }
+    }
+
+    // Sent in place of /subscribe, and renewed the same way.
+    pub fn connect_messages(&self) -> Vec<OscMessage> {
+        let connect = if self.passcode.is_empty() {
+            osc_message("/connect", &[])
+        } else {
+            osc_message("/connect", &[OscType::String(self.passcode.clone())])
+        };
+        vec![connect, osc_message("/updates", &[OscType::Int(1)])]
+    }
+
+    pub fn reset(&mut self) {
+        self.running = None;
+        self.queries.clear();
+    }
+
+    pub fn handle_message(
+        &mut self,
+        msg: &OscMessage,
+        subscription_expiry: &mut u32,
+    ) -> Option<NetEvent> {
+        if let Some(update) = msg.addr.strip_prefix("/update") {
+            return self.handle_update(without_workspace(update), &msg.args);
+        }
+        let address = without_workspace(msg.addr.strip_prefix("/reply")?);
+        let Some(OscType::String(json)) = msg.args.first() else {
+            return None;
+        };
+        let reply: Value = serde_json::from_str(json).ok()?;
+        let data = reply.get("data").unwrap_or(&Value::Null);
+        match address {
+            // "ok", or "ok:view|edit|control" from QLab 5; "badpass" otherwise.
+            "/connect" => {
+                if data.as_str().is_some_and(|d| d.starts_with("ok")) {
+                    *subscription_expiry = UPDATES_FOR;
+                    self.queries.push(osc_message("/playbackPosition", &[]));
+                    self.queries.push(running_query());
+                    Some(NetEvent::SubscribeOk(UPDATES_FOR))
+                } else {
+                    *subscription_expiry = 0;
+                    Some(NetEvent::SubscribeFail)
+                }
+            }
+            "/thump" => Some(NetEvent::Thump),
+            "/playbackPosition" => self.playhead(data.as_str().unwrap_or_default()),
+            "/runningCues/shallow" => self.running_cues(data),
+            _ if address.starts_with("/cue_id/") && address.ends_with("/valuesForKeys") => {
+                Some(NetEvent::NextCue(cue_info(data)))
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_update(&mut self, address: &str, args: &[OscType]) -> Option<NetEvent> {
+        let segments: Vec<&str> = address.split('/').skip(1).collect();
+        match segments.as_slice() {
+            // QLab 5 calls it the playhead, QLab 4 the playback position.
+            ["cueList", _, "playbackPosition" | "playhead"] => match args.first() {
+                Some(OscType::String(id)) => self.playhead(id),
+                _ => self.playhead(""),
+            },
+            // Any cue changing, starting and stopping included.
+            ["cue_id", _] => {
+                if !self.queries.contains(&running_query()) {
+                    self.queries.push(running_query());
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn playhead(&mut self, id: &str) -> Option<NetEvent> {
+        if id.is_empty() || id == "none" {
+            return Some(NetEvent::NextCue(CueInfo::default()));
+        }
+        self.queries.push(osc_message(
+            &format!("/cue_id/{id}/valuesForKeys"),
+            &[OscType::String(CUE_KEYS.to_string())],
+        ));
+        None
+    }
+
+    // The latest cue to start since the last answer fires; unnumbered cues,
+    // cue lists and carts don't.
+    fn running_cues(&mut self, data: &Value) -> Option<NetEvent> {
+        let cues: Vec<&Value> = data
+            .as_array()?
+            .iter()
+            .filter(|cue| {
+                let kind = cue.get("type").and_then(Value::as_str).unwrap_or_default();
+                kind != "Cue List" && kind != "Cart"
+            })
+            .collect();
+        let ids: HashSet<String> = cues
+            .iter()
+            .filter_map(|cue| text(cue, "uniqueID"))
+            .collect();
+        let before = self.running.replace(ids)?;
+        cues.into_iter()
+            .filter(|cue| text(cue, "uniqueID").is_some_and(|id| !before.contains(&id)))
+            .map(cue_info)
+            .rfind(|cue| !cue.number.is_empty())
+            .map(NetEvent::CueFired)
+    }
+}
+
+// Updates and replies for a particular workspace carry its ID up front.
+fn without_workspace(address: &str) -> &str {
+    match address.strip_prefix("/workspace/") {
+        Some(rest) => rest.find('/').map_or("", |slash| &rest[slash..]),
+        None => address,
+    }
+}
+
+fn running_query() -> OscMessage {
+    osc_message("/runningCues/shallow", &[])
+}
+
+fn text(cue: &Value, key: &str) -> Option<String> {
+    cue.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn cue_info(cue: &Value) -> CueInfo {
+    CueInfo {
+        number: text(cue, "number").unwrap_or_default(),
+        text: text(cue, "displayName")
+            .or_else(|| text(cue, "name"))
+            .unwrap_or_default(),
+        color: text(cue, "colorName").filter(|color| color != "none"),
+    }
+}
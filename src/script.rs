@@ -0,0 +1,187 @@
+//! A user script (Rhai) for OSC the display doesn't know, rewriting cues and
+//! custom widgets, loaded from script.rhai in the config directory.
+//!
+//! The script may define `on_message(addr, args)`, returning a map such as
+//! `#{event: "cue_fired", number: "12", text: "Storm"}` (or an array of them)
+//! to act on, and `on_cue(cue)`, returning a changed cue map. Anywhere it can
+//! call `set_widget(name, value)` to show a value under the cues.
+
+use crate::event::{CueInfo, NetEvent};
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, FuncArgs, Map, Scope};
+use rosc::{OscMessage, OscType};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Plenty for real work, while a runaway loop can't stall the network thread.
+const MAX_OPERATIONS: u64 = 200_000;
+
+pub fn script_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(base.join("theatremix-remote-display").join("script.rhai"))
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    // The script's top-level variables, kept between calls.
+    scope: Scope<'static>,
+    on_message: bool,
+    on_cue: bool,
+    widgets: Rc<RefCell<Vec<(String, String)>>>,
+    // Repeats of the same error aren't logged again.
+    last_error: Option<String>,
+}
+
+impl Script {
+    // None when there's no script file.
+    pub fn load(path: &Path) -> Result<Option<Script>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| tracing::info!("Script: {text}"));
+        let widgets = Rc::new(RefCell::new(Vec::new()));
+        let set = widgets.clone();
+        engine.register_fn("set_widget", move |name: &str, value: Dynamic| {
+            set.borrow_mut().push((name.to_string(), value.to_string()));
+        });
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|err| err.to_string())?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|err| err.to_string())?;
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name);
+        Ok(Some(Script {
+            on_message: defines("on_message"),
+            on_cue: defines("on_cue"),
+            engine,
+            ast,
+            scope,
+            widgets,
+            last_error: None,
+        }))
+    }
+
+    // Events the script makes of a message, on top of the built-in handling.
+    pub fn on_message(&mut self, msg: &OscMessage) -> Vec<NetEvent> {
+        let mut events = Vec::new();
+        if self.on_message {
+            let args: Array = msg.args.iter().map(osc_value).collect();
+            if let Some(result) = self.call("on_message", (msg.addr.clone(), args)) {
+                events.extend(script_events(result));
+            }
+        }
+        events.extend(self.widget_events());
+        events
+    }
+
+    pub fn on_cue(&mut self, cue: CueInfo) -> CueInfo {
+        if !self.on_cue {
+            return cue;
+        }
+        // Anything but a map, such as no return value, leaves the cue alone.
+        match self
+            .call("on_cue", (cue_map(&cue),))
+            .and_then(|result| result.try_cast::<Map>())
+        {
+            Some(map) => map_cue(&map),
+            None => cue,
+        }
+    }
+
+    // Widgets set since the last call; an empty value removes one.
+    pub fn widget_events(&mut self) -> Vec<NetEvent> {
+        self.widgets
+            .borrow_mut()
+            .drain(..)
+            .map(|(name, value)| NetEvent::ScriptWidget { name, value })
+            .collect()
+    }
+
+    fn call(&mut self, name: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        // The top level already ran at load; only the function runs now.
+        let options = CallFnOptions::new().eval_ast(false).rewind_scope(false);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            name,
+            args,
+        );
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                let err = err.to_string();
+                if self.last_error.as_ref() != Some(&err) {
+                    tracing::warn!("Script {name}: {err}");
+                    self.last_error = Some(err);
+                }
+                None
+            }
+        }
+    }
+}
+
+fn osc_value(arg: &OscType) -> Dynamic {
+    match arg {
+        OscType::Int(v) => Dynamic::from_int((*v).into()),
+        OscType::Long(v) => Dynamic::from_int(*v),
+        OscType::Float(v) => Dynamic::from_float((*v).into()),
+        OscType::Double(v) => Dynamic::from_float(*v),
+        OscType::String(v) => v.clone().into(),
+        OscType::Bool(v) => (*v).into(),
+        other => format!("{other:?}").into(),
+    }
+}
+
+fn cue_map(cue: &CueInfo) -> Map {
+    let mut map = Map::new();
+    map.insert("number".into(), cue.number.clone().into());
+    map.insert("text".into(), cue.text.clone().into());
+    map.insert(
+        "color".into(),
+        cue.color.clone().map_or(Dynamic::UNIT, Into::into),
+    );
+    map
+}
+
+// Numbers are taken as text, so `number: 12` works as well as `"12"`.
+fn map_cue(map: &Map) -> CueInfo {
+    let text = |key: &str| {
+        map.get(key)
+            .filter(|value| !value.is_unit())
+            .map(|value| value.to_string())
+    };
+    CueInfo {
+        number: text("number").unwrap_or_default(),
+        text: text("text").unwrap_or_default(),
+        color: text("color"),
+    }
+}
+
+fn script_events(result: Dynamic) -> Vec<NetEvent> {
+    let maps: Vec<Map> = match result.clone().into_array() {
+        Ok(items) => items
+            .into_iter()
+            .filter_map(|item| item.try_cast::<Map>())
+            .collect(),
+        Err(_) => result.try_cast::<Map>().into_iter().collect(),
+    };
+    maps.iter()
+        .filter_map(|map| {
+            let event = map.get("event")?.to_string();
+            match event.as_str() {
+                "cue_fired" => Some(NetEvent::CueFired(map_cue(map))),
+                "next_cue" => Some(NetEvent::NextCue(map_cue(map))),
+                other => {
+                    tracing::warn!("Script returned unknown event {other:?}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
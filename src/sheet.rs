@@ -0,0 +1,313 @@
+//! The cue sheet, local edits to it and translated cue text.
+
+use crate::event::CueInfo;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default)]
+pub struct SheetRow {
+    pub description: String,
+    pub notes: String,
+    // Set for musical numbers; drives the beat indicator.
+    pub bpm: Option<f32>,
+    // Still shown full-screen while this cue is current.
+    pub image: Option<String>,
+    // Text changed in the app since the sheet was published.
+    pub edited: bool,
+}
+
+// A late change to one cue's sheet text, kept in sheet-edits.toml so the
+// published sheet itself is never written. None keeps the sheet's text.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetEdit {
+    pub description: Option<String>,
+    pub notes: Option<String>,
+    // When it was made, so the newest edit wins between synced displays.
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+impl SheetEdit {
+    // Kept rather than removed, so the revert reaches synced displays too.
+    pub fn is_revert(&self) -> bool {
+        self.description.is_none() && self.notes.is_none()
+    }
+}
+
+// Production cue synopsis, joined to TheatreMix cues by cue number.
+#[derive(Clone, Debug, Default)]
+pub struct CueSheet {
+    pub rows: HashMap<String, SheetRow>,
+    // Cue numbers in the order the sheet lists them, for the cue list.
+    pub order: Vec<String>,
+}
+
+impl CueSheet {
+    // Columns are found by header name when there is a recognisable header
+    // row, otherwise taken as number, description, notes in that order.
+    pub fn parse(text: &str) -> Result<CueSheet, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let records: Vec<csv::StringRecord> = reader
+            .records()
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let find = |header: &csv::StringRecord, names: &[&str]| {
+            header
+                .iter()
+                .position(|h| names.contains(&h.trim().to_ascii_lowercase().as_str()))
+        };
+        let (mut number, mut description, mut notes, mut bpm, mut image, mut skip) =
+            (0, Some(1), Some(2), Some(3), Some(4), 0);
+        if let Some(header) = records.first()
+            && let Some(col) = find(header, &["cue", "cue number", "number", "q", "#"])
+        {
+            number = col;
+            description = find(header, &["description", "desc", "synopsis", "text"]);
+            notes = find(header, &["notes", "note", "dept notes", "department notes"]);
+            bpm = find(header, &["bpm", "tempo"]);
+            image = find(header, &["image", "picture", "still"]);
+            skip = 1;
+        }
+
+        let cell = |record: &csv::StringRecord, col: Option<usize>| {
+            col.and_then(|c| record.get(c))
+                .unwrap_or("")
+                .trim()
+                .to_string()
+        };
+        let rows: Vec<(String, SheetRow)> = records
+            .iter()
+            .skip(skip)
+            .filter_map(|record| {
+                let key = record.get(number)?.trim();
+                if key.is_empty() {
+                    return None;
+                }
+                let row = SheetRow {
+                    description: cell(record, description),
+                    notes: cell(record, notes),
+                    bpm: cell(record, bpm).parse().ok().filter(|b: &f32| *b > 0.0),
+                    image: Some(cell(record, image)).filter(|i| !i.is_empty()),
+                    edited: false,
+                };
+                Some((key.to_string(), row))
+            })
+            .collect();
+        let mut order: Vec<String> = Vec::new();
+        for (number, _) in &rows {
+            if !order.contains(number) {
+                order.push(number.clone());
+            }
+        }
+        Ok(CueSheet {
+            rows: rows.into_iter().collect(),
+            order,
+        })
+    }
+
+    pub fn row(&self, number: &str) -> Option<&SheetRow> {
+        self.rows.get(number)
+    }
+
+    pub fn position(&self, number: &str) -> Option<usize> {
+        if number.is_empty() {
+            return None;
+        }
+        self.order.iter().position(|n| n == number)
+    }
+
+    // Cue numbers from first to last in the sheet's order, where a blank end
+    // is the sheet's first or last cue. None if either isn't on the sheet.
+    pub fn range(&self, first: &str, last: &str) -> Option<Vec<String>> {
+        let start = if first.is_empty() {
+            0
+        } else {
+            self.position(first)?
+        };
+        let end = if last.is_empty() {
+            self.order.len()
+        } else {
+            self.position(last)? + 1
+        };
+        Some(self.order.get(start..end).unwrap_or_default().to_vec())
+    }
+
+    pub fn with_edits(&self, edits: &HashMap<String, SheetEdit>) -> CueSheet {
+        let mut sheet = self.clone();
+        for (number, edit) in edits.iter().filter(|(_, edit)| !edit.is_revert()) {
+            let row = sheet.rows.entry(number.clone()).or_default();
+            if let Some(description) = &edit.description {
+                row.description = description.clone();
+            }
+            if let Some(notes) = &edit.notes {
+                row.notes = notes.clone();
+            }
+            row.edited = true;
+        }
+        sheet
+    }
+}
+
+pub fn translations_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(
+        base.join("theatremix-remote-display")
+            .join("translations.txt"),
+    )
+}
+
+// One `key = translation` per line, where the key is either a cue number or
+// the exact cue text. Blank lines and `#` comments are ignored.
+pub fn load_translations(path: &PathBuf) -> HashMap<String, String> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .collect()
+}
+
+pub fn sheet_edits_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(
+        base.join("theatremix-remote-display")
+            .join("sheet-edits.toml"),
+    )
+}
+
+// Keyed by cue number.
+pub fn load_sheet_edits(path: &Path) -> HashMap<String, SheetEdit> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    toml::from_str(&text).unwrap_or_else(|err| {
+        tracing::warn!("Ignoring {}: {err}", path.display());
+        HashMap::new()
+    })
+}
+
+pub fn save_sheet_edits(path: &Path, edits: &HashMap<String, SheetEdit>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(edits).map_err(std::io::Error::other)?;
+    fs::write(path, text)
+}
+
+// Last write wins: takes each of another display's edits that is newer than
+// the one here. Returns whether anything changed.
+pub fn merge_sheet_edits(
+    edits: &mut HashMap<String, SheetEdit>,
+    theirs: HashMap<String, SheetEdit>,
+) -> bool {
+    let mut changed = false;
+    for (number, edit) in theirs {
+        let Some(at) = edit.edited_at else {
+            continue;
+        };
+        let ours = edits.get(&number).and_then(|edit| edit.edited_at);
+        if ours.is_none_or(|ours| at > ours) {
+            edits.insert(number, edit);
+            changed = true;
+        }
+    }
+    changed
+}
+
+// Anything that isn't an http(s) URL is taken as a CSV file on this machine,
+// re-read at each refresh so changes saved to it show up.
+pub fn spawn_sheet_fetcher(url: String, every: Duration, tx: Sender<Result<CueSheet, String>>) {
+    thread::spawn(move || {
+        let remote = url.starts_with("http://") || url.starts_with("https://");
+        loop {
+            let text = if remote {
+                ureq::get(&url)
+                    .config()
+                    .timeout_global(Some(Duration::from_secs(15)))
+                    .build()
+                    .call()
+                    .and_then(|mut resp| resp.body_mut().read_to_string())
+                    .map_err(|e| format!("Fetch failed: {e}"))
+            } else {
+                fs::read_to_string(&url).map_err(|e| format!("Read failed: {e}"))
+            };
+            let result = text.and_then(|text| CueSheet::parse(&text));
+            // The app drops the receiver when the URL changes.
+            if tx.send(result).is_err() {
+                break;
+            }
+            thread::sleep(every);
+        }
+    });
+}
+
+pub fn translate<'a>(translations: &'a HashMap<String, String>, cue: &CueInfo) -> Option<&'a str> {
+    translations
+        .get(&cue.number)
+        .or_else(|| translations.get(&cue.text))
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(notes: Option<&str>, secs: i64) -> SheetEdit {
+        SheetEdit {
+            description: None,
+            notes: notes.map(str::to_string),
+            edited_at: DateTime::from_timestamp(secs, 0),
+        }
+    }
+
+    #[test]
+    fn newest_edit_wins() {
+        let mut ours = HashMap::from([
+            ("1".to_string(), edit(Some("ours"), 20)),
+            ("2".to_string(), edit(Some("ours"), 20)),
+        ]);
+        let theirs = HashMap::from([
+            ("1".to_string(), edit(Some("older"), 10)),
+            ("2".to_string(), edit(None, 30)),
+            ("3".to_string(), edit(Some("new"), 5)),
+        ]);
+        assert!(merge_sheet_edits(&mut ours, theirs.clone()));
+        assert_eq!(ours["1"].notes.as_deref(), Some("ours"));
+        assert!(ours["2"].is_revert());
+        assert_eq!(ours["3"].notes.as_deref(), Some("new"));
+        // Nothing new the second time round.
+        assert!(!merge_sheet_edits(&mut ours, theirs));
+
+        let sheet = CueSheet::default().with_edits(&ours);
+        assert!(!sheet.rows.contains_key("2"));
+        assert!(sheet.rows["3"].edited);
+    }
+
+    #[test]
+    fn ranges_follow_the_sheet_order() {
+        let sheet = CueSheet {
+            order: ["1", "2", "2.5", "3"].map(str::to_string).to_vec(),
+            ..CueSheet::default()
+        };
+        assert_eq!(sheet.range("2", "3").unwrap(), ["2", "2.5", "3"]);
+        assert_eq!(sheet.range("", "2").unwrap(), ["1", "2"]);
+        assert_eq!(sheet.range("2.5", "").unwrap(), ["2.5", "3"]);
+        assert!(sheet.range("3", "1").unwrap().is_empty());
+        assert!(sheet.range("9", "").is_none());
+    }
+}
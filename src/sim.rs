@@ -0,0 +1,95 @@
+//! A fake TheatreMix for `--simulate`.
+
+use crate::protocol::{osc_message, packet_messages};
+use rosc::{OscMessage, OscPacket, OscType};
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SIM_CUES: &[(&str, &str, &str)] = &[
+    ("1", "Preset: house to half", "blue"),
+    ("2", "Preshow music out", "green"),
+    ("3", "Opening number", "yellow"),
+    ("3.5", "Radio mics up", "orange"),
+    ("4", "Scene change", "purple"),
+    ("5", "Thunder", "red"),
+    ("6", "Finale", "yellow"),
+    ("7", "Curtain call", "green"),
+];
+
+const SIM_CUE_EVERY: Duration = Duration::from_secs(8);
+
+const SIM_SUBSCRIPTION_SECS: i32 = 30;
+
+// Stands in for a TheatreMix console on localhost: answers subscribes and
+// thumps, serves SIM_CUES as the cue list, fires them in a loop and follows
+// /go, /back and /jump. Returns the port it listens on.
+pub fn spawn_simulator() -> std::io::Result<u16> {
+    let socket = UdpSocket::bind(("127.0.0.1", 0))?;
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+    let port = socket.local_addr()?.port();
+    thread::spawn(move || {
+        let mut client: Option<SocketAddr> = None;
+        let mut current: Option<usize> = None;
+        let mut last_fire = Instant::now();
+        let mut buf = [0u8; 1536];
+        loop {
+            let mut fire = None;
+            if let Ok((n, from)) = socket.recv_from(&mut buf)
+                && let Ok((_, packet)) = rosc::decoder::decode_udp(&buf[..n])
+            {
+                for msg in packet_messages(packet) {
+                    let reply = |msg: OscMessage| sim_send(&socket, from, msg);
+                    match msg.addr.as_str() {
+                        "/subscribe" => {
+                            client = Some(from);
+                            reply(osc_message(
+                                "/subscribeok",
+                                &[OscType::Int(SIM_SUBSCRIPTION_SECS)],
+                            ));
+                        }
+                        "/thump" => reply(osc_message("/thump", &[])),
+                        "/currentcue" => {
+                            if let Some(idx) = current {
+                                reply(osc_message("/currentcue", &sim_cue_args(idx)));
+                            }
+                        }
+                        "/nextcue" => {
+                            let next = current.map_or(0, |idx| (idx + 1) % SIM_CUES.len());
+                            reply(osc_message("/nextcue", &sim_cue_args(next)));
+                        }
+                        "/go" => fire = Some(current.map_or(0, |idx| (idx + 1) % SIM_CUES.len())),
+                        "/back" => fire = Some(current.map_or(0, |idx| idx.saturating_sub(1))),
+                        _ => {}
+                    }
+                }
+            }
+            if fire.is_none() && client.is_some() && last_fire.elapsed() >= SIM_CUE_EVERY {
+                fire = Some(current.map_or(0, |idx| (idx + 1) % SIM_CUES.len()));
+            }
+            if let (Some(idx), Some(to)) = (fire, client) {
+                sim_send(&socket, to, osc_message("/cuefired", &sim_cue_args(idx)));
+                let next = (idx + 1) % SIM_CUES.len();
+                sim_send(&socket, to, osc_message("/nextcue", &sim_cue_args(next)));
+                current = Some(idx);
+                last_fire = Instant::now();
+            }
+        }
+    });
+    Ok(port)
+}
+
+fn sim_cue_args(idx: usize) -> Vec<OscType> {
+    let (number, text, color) = SIM_CUES[idx];
+    vec![
+        OscType::String(number.to_string()),
+        OscType::String(text.to_string()),
+        OscType::String(color.to_string()),
+    ]
+}
+
+fn sim_send(socket: &UdpSocket, to: SocketAddr, msg: OscMessage) {
+    if let Ok(bytes) = rosc::encoder::encode(&OscPacket::Message(msg)) {
+        let _ = socket.send_to(&bytes, to);
+    }
+}
@@ -0,0 +1,59 @@
+//! Live cue state as the display sees it.
+
+use crate::event::CueInfo;
+use crate::net::DEFAULT_LOST_AFTER;
+use chrono::{DateTime, Local};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct CueState {
+    pub current: CueInfo,
+    pub next: CueInfo,
+    pub connected: bool,
+    pub last_rx: Option<Instant>,
+    pub last_rx_at: Option<DateTime<Local>>,
+    pub current_since: Option<Instant>,
+    // When the cues restored from the journal were last shown; cleared by the
+    // first cue the host sends.
+    pub last_known: Option<DateTime<Local>>,
+    // Data older than this is frozen; see the network thread's matching
+    // timeout.
+    pub lost_after: Duration,
+}
+
+impl Default for CueState {
+    fn default() -> Self {
+        Self {
+            current: CueInfo::default(),
+            next: CueInfo::default(),
+            connected: false,
+            last_rx: None,
+            last_rx_at: None,
+            current_since: None,
+            last_known: None,
+            lost_after: DEFAULT_LOST_AFTER,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Link {
+    Disconnected,
+    Connected,
+    Stale,
+}
+
+impl CueState {
+    pub fn touch(&mut self) {
+        self.last_rx = Some(Instant::now());
+        self.last_rx_at = Some(Local::now());
+    }
+
+    pub fn link(&self) -> Link {
+        match self.last_rx {
+            Some(t) if t.elapsed() >= self.lost_after => Link::Stale,
+            _ if self.connected => Link::Connected,
+            _ => Link::Disconnected,
+        }
+    }
+}
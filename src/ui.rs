@@ -0,0 +1,2209 @@
+//! The egui frontend.
+
+use crate::audio::{spawn_chime_player, speak};
+use crate::config::{
+    ArtNet, ButtonAction, ButtonPage, CONFIG_VERSION, Chime, CommandHooks, Config, CueFlash, Gpio,
+    GpioEvent, LayoutPreset, LibrettoPanel, LossAlert, MidiMessage, MidiOut, Mqtt, OscPreset,
+    Profile, Reminder, ShowCall, ShowReport, SplitSource, StageDisplay, Theme, WindowGeometry,
+    clear_startups, save_config,
+};
+use crate::dmx::{Levels, cue_levels, spawn_artnet};
+use crate::event::{CueInfo, CueTiming, NetEvent, NetStats, Route, TrafficEntry};
+use crate::gpio::{GPIO_SUPPORTED, spawn_gpio};
+use crate::hooks::run_command_hook;
+use crate::journal::{
+    Journal, JournalEntry, Marker, SessionLog, format_elapsed, journal_dir, journal_path,
+    read_journal, report_path, restore_state, show_report, write_show_report,
+};
+use crate::libretto::Libretto;
+use crate::midi::{cue_messages, midi_ports, spawn_midi_out};
+use crate::mqtt::MqttPublisher;
+use crate::net::{
+    AddrChoice, AddrFamily, ControlListener, DEFAULT_LOST_AFTER, FoundHost, LocalInterface, NetCmd,
+    RetryPolicy, SourceKind, TestStep, Transport, Waker, capture_path, firewall_guidance,
+    host_is_valid, local_interfaces, spawn_connection_test, spawn_discovery, spawn_osc_thread,
+};
+use crate::paging::Pager;
+use crate::protocol::{ControlMsg, load_osc_map, osc_arg, osc_map_path, osc_message};
+use crate::script::script_path;
+use crate::sheet::{
+    CueSheet, SheetEdit, SheetRow, load_sheet_edits, load_translations, merge_sheet_edits,
+    save_sheet_edits, sheet_edits_path, spawn_sheet_fetcher, translate, translations_path,
+};
+use crate::state::{CueState, Link};
+use crate::web::{
+    HookEvent, PastCue, SharedNotes, Snapshot, WebServer, Webhook, apply_mirror, fill_placeholders,
+    json_escape, load_webhooks, pairing_code, post_webhook, spawn_mirror, spawn_notes_sync,
+    webhooks_path,
+};
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use eframe::egui::ViewportBuilder;
+use eframe::{App, Frame, egui};
+use rosc::OscType;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+mod cue_list;
+mod diagnostics;
+mod operator;
+mod overlays;
+mod settings;
+
+use diagnostics::LatencySample;
+use overlays::{
+    beat_indicator, blackout_overlay, contrast_text, cue_flash, dimmer_overlay, hold_overlay,
+    image_overlay, load_cue_image, loss_alert, stale_overlay,
+};
+use settings::join_targets;
+
+const CALL_BANNER_FOR: Duration = Duration::from_secs(60);
+
+// Cue fires kept for the history panel; a long show runs to a few hundred.
+const HISTORY_LEN: usize = 1000;
+
+// Messages kept by the OSC monitor.
+const TRAFFIC_LEN: usize = 500;
+
+// Messages remembered by the OSC console.
+const OSC_SENT_LEN: usize = 50;
+
+// Latency samples kept for the percentiles in Settings.
+const LATENCY_LEN: usize = 200;
+
+// Window the diagnostics panel's message rates are averaged over.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+
+// GO/BACK stay live this long after arming or the last press.
+const ARM_FOR: Duration = Duration::from_secs(30);
+
+const REMINDER_SNOOZE: Duration = Duration::from_secs(5 * 60);
+
+// How long a host switched to, or a search for profile hosts, is given before
+// automatic profile selection looks again.
+const AUTO_PROFILE_AFTER: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WizardStep {
+    Host,
+    Test,
+}
+
+// The split display's second source, on a network thread of its own.
+struct SplitPane {
+    state: CueState,
+    rx: Receiver<NetEvent>,
+    cmd_tx: Sender<NetCmd>,
+    waker: Waker,
+}
+
+impl SplitPane {
+    fn start(split: &SplitSource, lost_after: Duration) -> SplitPane {
+        let (tx, rx) = mpsc::channel();
+        let (cmd_tx, cmd_rx) = mpsc::channel();
+        let waker = Waker::default();
+        spawn_osc_thread(
+            split.host.clone(),
+            0,
+            split.osc_port,
+            tx,
+            cmd_rx,
+            waker.clone(),
+        );
+        if split.source != SourceKind::default() {
+            let _ = cmd_tx.send(NetCmd::SetSource(split.source, split.qlab_passcode.clone()));
+        }
+        if split.listen_port.is_some() {
+            let _ = cmd_tx.send(NetCmd::SetListenPort(split.listen_port));
+        }
+        if lost_after != DEFAULT_LOST_AFTER {
+            let _ = cmd_tx.send(NetCmd::SetLostAfter(lost_after));
+        }
+        SplitPane {
+            state: CueState {
+                lost_after,
+                ..CueState::default()
+            },
+            rx,
+            cmd_tx,
+            waker,
+        }
+    }
+
+    // Just the cues and link; outputs and logging follow the main source.
+    fn apply_event(&mut self, ev: NetEvent) {
+        match ev {
+            NetEvent::CueFired(info) => {
+                self.state.current = info;
+                self.state.current_since = Some(Instant::now());
+                self.state.touch();
+            }
+            NetEvent::NextCue(info) => {
+                self.state.next = info;
+                self.state.touch();
+            }
+            NetEvent::CurrentCue(info) => {
+                if info.number != self.state.current.number {
+                    self.state.current_since = Some(Instant::now());
+                }
+                self.state.current = info;
+                self.state.touch();
+            }
+            NetEvent::SubscribeOk(_) | NetEvent::Pushed(true) => self.state.connected = true,
+            NetEvent::Thump => self.state.touch(),
+            NetEvent::SubscribeFail | NetEvent::ConnectionLost | NetEvent::Resumed => {
+                self.state.connected = false;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct TheatreMixApp {
+    state: CueState,
+    rx: Receiver<NetEvent>,
+    cmd_tx: Sender<NetCmd>,
+    host: String,
+    status: String,
+    host_edit: String,
+    backup_route: Option<IpAddr>,
+    backup_route_edit: String,
+    route: Route,
+    backup_host: Option<String>,
+    backup_host_edit: String,
+    forward_to: Vec<SocketAddr>,
+    forward_edit: String,
+    forward_error: Option<String>,
+    on_backup_host: bool,
+    console: Option<String>,
+    console_edit: String,
+    on_console: bool,
+    // Set once subscribes go unanswered; true if the host actively refused.
+    no_replies: Option<bool>,
+    // TheatreMix answered /subscribefail; cleared by the next /subscribeok.
+    rejected: bool,
+    // When the last /subscribeok arrived and the expiry it granted.
+    subscription: Option<(Instant, u32)>,
+    retry_at: Option<Instant>,
+    retry: RetryPolicy,
+    retry_port: u16,
+    // 0 lets the OS choose; a fixed port makes firewall rules stick.
+    local_port: u16,
+    local_port_edit: u16,
+    osc_port: u16,
+    osc_port_edit: u16,
+    address: AddrChoice,
+    // What the network thread last resolved the host to.
+    resolved: Vec<IpAddr>,
+    using_addr: Option<IpAddr>,
+    interface: Option<IpAddr>,
+    interfaces: Vec<LocalInterface>,
+    transport: Transport,
+    source: SourceKind,
+    qlab_passcode: String,
+    qlab_passcode_edit: String,
+    listen: bool,
+    listen_port: u16,
+    capture_status: Option<String>,
+    always_on_top: bool,
+    theme: Theme,
+    text_scale: f32,
+    // Multiplies the cue font sizes, on top of auto-fit when that's on.
+    cue_text_scale: f32,
+    auto_fit: bool,
+    window: Option<WindowGeometry>,
+    loss_alert: LossAlert,
+    lost_after_secs: u32,
+    translations: HashMap<String, String>,
+    show_translation: bool,
+    sheet: CueSheet,
+    // As published, before local edits.
+    sheet_base: CueSheet,
+    sheet_edits: HashMap<String, SheetEdit>,
+    // Cue number, description and notes being edited.
+    sheet_editing: Option<(String, String, String)>,
+    sheet_url: String,
+    sheet_refresh_mins: u32,
+    sheet_rx: Option<Receiver<Result<CueSheet, String>>>,
+    sheet_status: Option<String>,
+    notes_peer: String,
+    notes_rx: Option<Receiver<Result<SharedNotes, String>>>,
+    notes_status: Option<String>,
+    // This session's markers, here and from synced displays.
+    markers: Vec<Marker>,
+    show_beat: bool,
+    show_cue_timer: bool,
+    operator: bool,
+    armed_until: Option<Instant>,
+    show_osc_console: bool,
+    show_osc_monitor: bool,
+    traffic: VecDeque<TrafficEntry>,
+    traffic_paused: bool,
+    // Whether a script is running, or why it didn't load.
+    script_status: Option<Result<bool, String>>,
+    script_widgets: BTreeMap<String, String>,
+    osc_map_len: usize,
+    traffic_filter: String,
+    osc_address: String,
+    osc_args: String,
+    // Newest last; (address, args) as typed.
+    osc_sent: VecDeque<(DateTime<Local>, String, String)>,
+    osc_presets: Vec<OscPreset>,
+    osc_preset_name: String,
+    chime: Chime,
+    notify_cues: bool,
+    // Minimised, or behind another window or on another desktop.
+    window_hidden: bool,
+    flash: CueFlash,
+    stage_display: StageDisplay,
+    // Started the first time a chime plays.
+    chime_tx: Option<Sender<Option<PathBuf>>>,
+    cue_image: Option<egui::TextureHandle>,
+    image_for: Option<Instant>,
+    snapshot: Arc<Mutex<Snapshot>>,
+    web_server: Option<WebServer>,
+    brightness: f32,
+    auto_dim_mins: u32,
+    auto_dim_level: f32,
+    last_activity: Instant,
+    control: Option<ControlListener>,
+    control_port: u16,
+    control_error: Option<String>,
+    web_port: u16,
+    web_error: Option<String>,
+    mqtt: Mqtt,
+    mqtt_publisher: Option<MqttPublisher>,
+    mqtt_error: Option<String>,
+    midi: MidiOut,
+    midi_tx: Option<Sender<Vec<u8>>>,
+    midi_error: Option<String>,
+    // Listed when the MIDI section is first opened or refreshed.
+    midi_ports: Option<Vec<String>>,
+    art_net: ArtNet,
+    art_net_tx: Option<Sender<Levels>>,
+    art_net_target_edit: String,
+    art_net_error: Option<String>,
+    gpio: Gpio,
+    gpio_tx: Option<Sender<GpioEvent>>,
+    gpio_error: Option<String>,
+    split: SplitSource,
+    split_pane: Option<SplitPane>,
+    split_error: Option<String>,
+    libretto_panel: LibrettoPanel,
+    libretto: Libretto,
+    libretto_status: Option<String>,
+    // The cue the script was last scrolled to.
+    libretto_for: String,
+    spare_edit: String,
+    pairing_edit: String,
+    mirror: Option<Receiver<Result<usize, String>>>,
+    // Files a main display has sent, waiting for the operator.
+    pending_mirror: Option<HashMap<String, String>>,
+    mirror_status: Option<String>,
+    pub show_cue_list: bool,
+    history: VecDeque<(DateTime<Local>, CueInfo)>,
+    show_history: bool,
+    session_start: DateTime<Local>,
+    // Performance clock: the first cue of the session, or "Start show".
+    show_started: Option<Instant>,
+    show_stats: bool,
+    show_report: ShowReport,
+    report_status: Option<String>,
+    scroll_to_current: bool,
+    webhooks: Vec<Webhook>,
+    pub command_hooks: CommandHooks,
+    last_link: Link,
+    // Shifts displayed clocks only, for relay venues in another timezone.
+    clock_offset_minutes: i32,
+    hold_since: Option<Instant>,
+    show_name: String,
+    house_open_edit: String,
+    curtain_edit: String,
+    calls: Vec<ShowCall>,
+    display_group: String,
+    active_call: Option<(String, Instant)>,
+    pager: Pager,
+    // Pages and clears posted to the web server.
+    web_control_tx: Sender<ControlMsg>,
+    web_control: Receiver<ControlMsg>,
+    reminders: Vec<Reminder>,
+    profiles: Vec<Profile>,
+    button_pages: Vec<ButtonPage>,
+    show_buttons: bool,
+    button_page: usize,
+    // Running stopwatches, keyed by (page, button).
+    button_timers: HashMap<(usize, usize), Instant>,
+    blackout: bool,
+    profile_name_edit: String,
+    active_profile: Option<String>,
+    // Profile waiting on the "switch while connected?" confirmation.
+    pending_profile: Option<usize>,
+    session_log: SessionLog,
+    journal: Journal,
+    // Time the marker key was pressed, while its note is being typed.
+    pending_marker: Option<DateTime<Local>>,
+    marker_text: String,
+    config_path: Option<PathBuf>,
+    show_settings: bool,
+    show_palette: bool,
+    // Fullscreen cues with the status row hidden and the top bar tucked away.
+    pub kiosk: bool,
+    // Default settings, nothing saved and no webhooks or web display.
+    safe_mode: bool,
+    started: Instant,
+    stable: bool,
+    wizard: Option<WizardStep>,
+    wizard_host: String,
+    conn_test: Option<Receiver<TestStep>>,
+    conn_test_results: Vec<TestStep>,
+    pending_timing: Option<CueTiming>,
+    // A cue applied this frame, waiting for the frame to finish drawing.
+    unrendered: Option<(CueTiming, Instant)>,
+    latency: VecDeque<LatencySample>,
+    round_trips: VecDeque<Duration>,
+    // Network counters as reported, oldest first, trimmed to RATE_WINDOW.
+    stats: VecDeque<(Instant, NetStats)>,
+    // Counters at the last reset; the panel shows totals since then.
+    stats_base: NetStats,
+    discovery: Option<Receiver<FoundHost>>,
+    found_hosts: Vec<FoundHost>,
+    auto_profile_at: Option<Instant>,
+    palette_query: String,
+    palette_selected: usize,
+}
+
+impl TheatreMixApp {
+    pub fn new(
+        config: Config,
+        rx: Receiver<NetEvent>,
+        cmd_tx: Sender<NetCmd>,
+        config_path: Option<PathBuf>,
+        first_run: bool,
+    ) -> Self {
+        let (journal, history) = Journal::open(journal_dir());
+        let mut past_cues: VecDeque<_> = history
+            .iter()
+            .filter(|entry| entry.kind == "cue")
+            .map(|entry| (entry.time, entry.cue.clone()))
+            .collect();
+        while past_cues.len() > HISTORY_LEN {
+            past_cues.pop_front();
+        }
+        let local_port = config.local_port;
+        let (web_control_tx, web_control) = mpsc::channel();
+        let osc_port = config.osc_port;
+        let mut app = Self {
+            state: restore_state(&history),
+            rx,
+            cmd_tx,
+            host: config.host,
+            status: "Connecting...".to_string(),
+            host_edit: String::new(),
+            backup_route: config.backup_route,
+            backup_route_edit: config
+                .backup_route
+                .map(|ip| ip.to_string())
+                .unwrap_or_default(),
+            route: Route::Primary,
+            backup_host_edit: config.backup_host.clone().unwrap_or_default(),
+            forward_to: config.forward_to.clone(),
+            forward_edit: join_targets(&config.forward_to),
+            forward_error: None,
+            backup_host: config.backup_host,
+            on_backup_host: false,
+            console_edit: config.console.clone().unwrap_or_default(),
+            console: config.console,
+            on_console: false,
+            no_replies: None,
+            rejected: false,
+            subscription: None,
+            retry_at: None,
+            retry: config.retry,
+            retry_port: if local_port == 0 { 32001 } else { local_port },
+            local_port,
+            local_port_edit: local_port,
+            osc_port,
+            osc_port_edit: osc_port,
+            address: config.address,
+            resolved: Vec::new(),
+            using_addr: None,
+            interface: config.interface,
+            interfaces: local_interfaces(),
+            transport: config.transport,
+            source: config.source,
+            qlab_passcode_edit: config.qlab_passcode.clone(),
+            qlab_passcode: config.qlab_passcode,
+            listen: config.listen,
+            listen_port: config.listen_port,
+            capture_status: None,
+            always_on_top: config.always_on_top,
+            theme: config.theme,
+            text_scale: config.text_scale,
+            cue_text_scale: config.cue_text_scale,
+            auto_fit: config.auto_fit,
+            window: config.window,
+            loss_alert: config.loss_alert,
+            lost_after_secs: config.lost_after_secs,
+            translations: translations_path()
+                .map(|p| load_translations(&p))
+                .unwrap_or_default(),
+            show_translation: config.show_translation,
+            sheet: CueSheet::default(),
+            sheet_base: CueSheet::default(),
+            sheet_edits: sheet_edits_path()
+                .map(|p| load_sheet_edits(&p))
+                .unwrap_or_default(),
+            sheet_editing: None,
+            sheet_url: config.sheet_url,
+            sheet_refresh_mins: config.sheet_refresh_mins,
+            sheet_rx: None,
+            sheet_status: None,
+            notes_peer: config.notes_peer,
+            notes_rx: None,
+            notes_status: None,
+            markers: Vec::new(),
+            show_beat: true,
+            show_cue_timer: config.cue_timer,
+            operator: config.operator,
+            armed_until: None,
+            show_osc_console: false,
+            show_osc_monitor: false,
+            traffic: VecDeque::new(),
+            traffic_paused: false,
+            script_status: None,
+            script_widgets: BTreeMap::new(),
+            osc_map_len: 0,
+            traffic_filter: String::new(),
+            osc_address: String::new(),
+            osc_args: String::new(),
+            osc_sent: VecDeque::new(),
+            osc_presets: config.osc_presets.clone(),
+            osc_preset_name: String::new(),
+            chime: config.chime.clone(),
+            notify_cues: config.notify_cues,
+            window_hidden: false,
+            flash: config.flash,
+            stage_display: config.stage_display,
+            chime_tx: None,
+            cue_image: None,
+            image_for: None,
+            snapshot: Arc::default(),
+            web_server: None,
+            web_port: config.web_port,
+            web_error: None,
+            mqtt: config.mqtt.clone(),
+            mqtt_publisher: None,
+            mqtt_error: None,
+            midi: config.midi.clone(),
+            midi_tx: None,
+            midi_error: None,
+            midi_ports: None,
+            art_net: config.art_net.clone(),
+            art_net_tx: None,
+            art_net_target_edit: config.art_net.target.to_string(),
+            art_net_error: None,
+            gpio: config.gpio.clone(),
+            gpio_tx: None,
+            gpio_error: None,
+            split: config.split.clone(),
+            split_pane: None,
+            split_error: None,
+            libretto_panel: config.libretto.clone(),
+            libretto: Libretto::default(),
+            libretto_status: None,
+            libretto_for: String::new(),
+            brightness: config.brightness,
+            auto_dim_mins: config.auto_dim_mins,
+            auto_dim_level: config.auto_dim_level,
+            last_activity: Instant::now(),
+            control: None,
+            control_port: config.osc_control_port,
+            control_error: None,
+            spare_edit: String::new(),
+            pairing_edit: String::new(),
+            mirror: None,
+            pending_mirror: None,
+            mirror_status: None,
+            show_cue_list: false,
+            history: past_cues,
+            show_history: false,
+            session_start: Local::now(),
+            show_started: None,
+            show_stats: false,
+            show_report: config.show_report.clone(),
+            report_status: None,
+            scroll_to_current: false,
+            webhooks: webhooks_path()
+                .map(|p| load_webhooks(&p))
+                .unwrap_or_default(),
+            command_hooks: config.hooks.clone(),
+            last_link: Link::Disconnected,
+            clock_offset_minutes: config.clock_offset_minutes,
+            hold_since: None,
+            show_name: config.show_name,
+            house_open_edit: config.house_open,
+            curtain_edit: config.curtain,
+            calls: config.calls,
+            display_group: config.display_group,
+            active_call: None,
+            pager: Pager::default(),
+            web_control_tx,
+            web_control,
+            reminders: config.reminders,
+            profiles: config.profiles,
+            button_pages: config.button_pages,
+            show_buttons: false,
+            button_page: 0,
+            button_timers: HashMap::new(),
+            blackout: false,
+            profile_name_edit: String::new(),
+            active_profile: config.profile,
+            pending_profile: None,
+            session_log: SessionLog::default(),
+            journal,
+            pending_marker: None,
+            marker_text: String::new(),
+            config_path,
+            show_settings: false,
+            show_palette: false,
+            kiosk: false,
+            safe_mode: false,
+            started: Instant::now(),
+            stable: false,
+            wizard: first_run.then_some(WizardStep::Host),
+            wizard_host: String::new(),
+            conn_test: None,
+            conn_test_results: Vec::new(),
+            pending_timing: None,
+            unrendered: None,
+            latency: VecDeque::new(),
+            round_trips: VecDeque::new(),
+            stats: VecDeque::new(),
+            stats_base: NetStats::default(),
+            discovery: None,
+            found_hosts: Vec::new(),
+            auto_profile_at: None,
+            palette_query: String::new(),
+            palette_selected: 0,
+        };
+        if app.interface.is_some() {
+            let _ = app.cmd_tx.send(NetCmd::SetInterface(app.interface));
+        }
+        if app.address != AddrChoice::default() {
+            let _ = app.cmd_tx.send(NetCmd::SetAddrChoice(app.address));
+        }
+        if app.transport != Transport::default() {
+            let _ = app.cmd_tx.send(NetCmd::SetTransport(app.transport));
+        }
+        if app.source != SourceKind::default() {
+            let _ = app
+                .cmd_tx
+                .send(NetCmd::SetSource(app.source, app.qlab_passcode.clone()));
+        }
+        if app.listen {
+            let _ = app
+                .cmd_tx
+                .send(NetCmd::SetListenPort(Some(app.listen_port)));
+        }
+        app.load_osc_map();
+        if !app.forward_to.is_empty() {
+            let _ = app
+                .cmd_tx
+                .send(NetCmd::SetForwardTargets(app.forward_to.clone()));
+        }
+        if app.retry != RetryPolicy::default() {
+            let _ = app.cmd_tx.send(NetCmd::SetRetryPolicy(app.retry));
+        }
+        app.state.lost_after = Duration::from_secs(app.lost_after_secs.max(1).into());
+        if app.state.lost_after != DEFAULT_LOST_AFTER {
+            let _ = app.cmd_tx.send(NetCmd::SetLostAfter(app.state.lost_after));
+        }
+        if app.backup_route.is_some() {
+            let _ = app.cmd_tx.send(NetCmd::SetBackupRoute(app.backup_route));
+        }
+        if app.backup_host.is_some() {
+            let _ = app
+                .cmd_tx
+                .send(NetCmd::SetBackupHost(app.backup_host.clone()));
+        }
+        if app.console.is_some() {
+            let _ = app.cmd_tx.send(NetCmd::SetConsole(app.console.clone()));
+        }
+        if !app.sheet_url.trim().is_empty() {
+            app.load_sheet();
+        }
+        app.publish_notes();
+        if !app.notes_peer.trim().is_empty() {
+            app.sync_notes();
+        }
+        if config.web_display {
+            app.set_web_server(true);
+        }
+        if config.osc_control {
+            app.set_control(true);
+        }
+        if config.mqtt.enabled {
+            app.set_mqtt(true);
+        }
+        if config.midi.enabled {
+            app.set_midi(true);
+        }
+        if config.art_net.enabled {
+            app.set_art_net(true);
+        }
+        if config.gpio.enabled {
+            app.set_gpio(true);
+        }
+        if config.split.enabled {
+            app.set_split(true);
+        }
+        if !app.libretto_panel.path.trim().is_empty() {
+            app.load_libretto();
+        }
+        app
+    }
+
+    fn to_config(&self) -> Config {
+        Config {
+            version: CONFIG_VERSION,
+            host: self.host.clone(),
+            osc_port: self.osc_port,
+            local_port: self.local_port,
+            address: self.address,
+            interface: self.interface,
+            transport: self.transport,
+            source: self.source,
+            qlab_passcode: self.qlab_passcode.clone(),
+            listen: self.listen,
+            listen_port: self.listen_port,
+            backup_host: self.backup_host.clone(),
+            console: self.console.clone(),
+            backup_route: self.backup_route,
+            forward_to: self.forward_to.clone(),
+            always_on_top: self.always_on_top,
+            theme: self.theme,
+            text_scale: self.text_scale,
+            cue_text_scale: self.cue_text_scale,
+            auto_fit: self.auto_fit,
+            cue_timer: self.show_cue_timer,
+            operator: self.operator,
+            osc_presets: self.osc_presets.clone(),
+            chime: self.chime.clone(),
+            notify_cues: self.notify_cues,
+            flash: self.flash,
+            stage_display: self.stage_display,
+            show_translation: self.show_translation,
+            loss_alert: self.loss_alert,
+            lost_after_secs: self.lost_after_secs,
+            clock_offset_minutes: self.clock_offset_minutes,
+            web_display: self.web_server.is_some(),
+            web_port: self.web_port,
+            mqtt: self.mqtt.clone(),
+            midi: self.midi.clone(),
+            art_net: self.art_net.clone(),
+            gpio: self.gpio.clone(),
+            hooks: self.command_hooks.clone(),
+            split: self.split.clone(),
+            libretto: self.libretto_panel.clone(),
+            show_report: self.show_report.clone(),
+            brightness: self.brightness,
+            auto_dim_mins: self.auto_dim_mins,
+            auto_dim_level: self.auto_dim_level,
+            osc_control: self.control.is_some(),
+            osc_control_port: self.control_port,
+            retry: self.retry,
+            sheet_url: self.sheet_url.clone(),
+            sheet_refresh_mins: self.sheet_refresh_mins,
+            notes_peer: self.notes_peer.clone(),
+            show_name: self.show_name.clone(),
+            house_open: self.house_open_edit.clone(),
+            curtain: self.curtain_edit.clone(),
+            calls: self.calls.clone(),
+            display_group: self.display_group.clone(),
+            reminders: self.reminders.clone(),
+            profiles: self.profiles.clone(),
+            profile: self.active_profile.clone(),
+            button_pages: self.button_pages.clone(),
+            window: self.window,
+        }
+    }
+
+    fn save_config(&self) {
+        if let Some(path) = &self.config_path
+            && let Err(err) = save_config(path, &self.to_config())
+        {
+            tracing::warn!("Settings not saved to {}: {err}", path.display());
+        }
+    }
+
+    pub fn apply_appearance(&self, ctx: &egui::Context) {
+        ctx.set_theme(match self.theme {
+            Theme::Dark | Theme::Backstage => egui::ThemePreference::Dark,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::System => egui::ThemePreference::System,
+        });
+        ctx.set_visuals_of(
+            egui::Theme::Dark,
+            if self.theme == Theme::Backstage {
+                backstage_visuals()
+            } else {
+                egui::Visuals::dark()
+            },
+        );
+        ctx.set_zoom_factor(self.text_scale);
+    }
+
+    fn display_time(&self, t: DateTime<Local>) -> DateTime<Local> {
+        t + chrono::Duration::minutes(self.clock_offset_minutes.into())
+    }
+
+    fn fire_hooks(&self, event: HookEvent) {
+        let cue = &self.state.current;
+        let now = Local::now().to_rfc3339();
+        let values = [
+            ("event", event.name()),
+            ("cue_number", cue.number.as_str()),
+            ("cue_text", cue.text.as_str()),
+            ("cue_color", cue.color.as_deref().unwrap_or("")),
+            ("host", self.host.as_str()),
+            ("time", now.as_str()),
+        ];
+        for hook in &self.webhooks {
+            if hook.event.is_some_and(|e| e != event) {
+                continue;
+            }
+            let body = fill_placeholders(&hook.template, &values, json_escape);
+            post_webhook(hook.url.clone(), body);
+        }
+        run_command_hook(&self.command_hooks, event, &values);
+    }
+
+    // Returns true when the sheet text edit button was pressed.
+    fn cue_section(&self, ui: &mut egui::Ui, heading: &str, cue: &CueInfo, scale: f32) -> bool {
+        let editable = !self.kiosk && !self.sheet_url.is_empty() && !cue.number.is_empty();
+        let edit = ui
+            .horizontal(|ui| {
+                ui.label(egui::RichText::new(heading).size(14.0 * scale));
+                editable
+                    && ui
+                        .small_button("Edit")
+                        .on_hover_text("Change this cue's sheet text on this display")
+                        .clicked()
+            })
+            .inner;
+        let translation = if self.show_translation {
+            translate(&self.translations, cue)
+        } else {
+            None
+        };
+        cue_block(ui, cue, translation, self.sheet.row(&cue.number), scale);
+        edit
+    }
+
+    fn play_chime(&mut self, sound: Option<PathBuf>) {
+        let tx = self.chime_tx.get_or_insert_with(spawn_chime_player);
+        let _ = tx.send(sound);
+    }
+
+    fn set_always_on_top(&mut self, ctx: &egui::Context, on_top: bool) {
+        self.always_on_top = on_top;
+        let level = if on_top {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+    }
+
+    fn set_kiosk(&mut self, ctx: &egui::Context, on: bool) {
+        self.kiosk = on;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(on));
+    }
+
+    // Before the first cue and before house open, the cue view is replaced
+    // by a holding screen.
+    fn pre_show(&self) -> Option<chrono::NaiveDateTime> {
+        if self.show_started.is_some() {
+            return None;
+        }
+        let house_open = NaiveTime::parse_from_str(self.house_open_edit.trim(), "%H:%M").ok()?;
+        let now = Local::now().naive_local();
+        let open_at = now.date().and_time(house_open);
+        (now < open_at).then_some(open_at)
+    }
+
+    fn check_calls(&mut self) {
+        let Ok(curtain) = NaiveTime::parse_from_str(self.curtain_edit.trim(), "%H:%M") else {
+            return;
+        };
+        let now = Local::now();
+        let today = now.date_naive();
+        let mut fired = Vec::new();
+        for call in &mut self.calls {
+            let due =
+                today.and_time(curtain) - chrono::Duration::minutes(call.minutes_before.into());
+            // Only fire inside the minute the call is due, so launching the
+            // app late doesn't replay every call that has already passed.
+            let late = now.naive_local() - due;
+            if call.fired_on != Some(today)
+                && late >= chrono::Duration::zero()
+                && late < chrono::Duration::minutes(1)
+            {
+                call.fired_on = Some(today);
+                if call.reaches(&self.display_group) {
+                    fired.push(call.clone());
+                }
+            }
+        }
+        for call in fired {
+            self.active_call = Some((call.name.clone(), Instant::now()));
+            let sound = call.sound.trim();
+            if !sound.is_empty() {
+                self.play_chime(Some(PathBuf::from(sound)));
+            }
+            if call.speak {
+                speak(format!("{} please", call.name));
+            }
+        }
+        if self
+            .active_call
+            .as_ref()
+            .is_some_and(|(_, at)| at.elapsed() >= CALL_BANNER_FOR)
+        {
+            self.active_call = None;
+        }
+    }
+
+    fn check_reminders(&mut self) {
+        let now = Local::now();
+        let today = now.date_naive();
+        for reminder in &mut self.reminders {
+            if reminder
+                .snoozed_until
+                .is_some_and(|until| Instant::now() >= until)
+            {
+                reminder.snoozed_until = None;
+                reminder.active = true;
+            }
+            let Ok(time) = NaiveTime::parse_from_str(reminder.time.trim(), "%H:%M") else {
+                continue;
+            };
+            let late = now.naive_local() - today.and_time(time);
+            if reminder.fired_on != Some(today)
+                && late >= chrono::Duration::zero()
+                && late < chrono::Duration::minutes(1)
+            {
+                reminder.fired_on = Some(today);
+                reminder.active = reminder.reaches(&self.display_group);
+            }
+        }
+    }
+
+    fn toggle_hold(&mut self) {
+        self.set_hold(self.hold_since.is_none());
+    }
+
+    // Logs the hold and passes it on to the displays fed from this one. Only
+    // a change goes out, so displays forwarding to each other don't echo it.
+    fn set_hold(&mut self, on: bool) {
+        match (self.hold_since, on) {
+            (None, true) => {
+                self.hold_since = Some(Instant::now());
+                self.session_log.record("hold", "Held", Local::now());
+            }
+            (Some(since), false) => {
+                self.hold_since = None;
+                let held = format!("Released after {}", format_elapsed(since.elapsed()));
+                self.session_log.record("hold", &held, Local::now());
+            }
+            _ => return,
+        }
+        let _ = self.cmd_tx.send(NetCmd::ForwardHold(on));
+    }
+
+    fn set_host(&mut self, host: String) {
+        self.host = host.clone();
+        let _ = self.cmd_tx.send(NetCmd::SetHost(host));
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+    }
+
+    fn set_local_port(&mut self, port: u16) {
+        self.local_port = port;
+        self.local_port_edit = port;
+        let _ = self.cmd_tx.send(NetCmd::SetLocalPort(port));
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+        self.save_config();
+    }
+
+    fn set_address(&mut self, address: AddrChoice) {
+        self.address = address;
+        let _ = self.cmd_tx.send(NetCmd::SetAddrChoice(address));
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+        self.save_config();
+    }
+
+    fn set_interface(&mut self, interface: Option<IpAddr>) {
+        self.interface = interface;
+        let _ = self.cmd_tx.send(NetCmd::SetInterface(interface));
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+        self.save_config();
+    }
+
+    fn set_transport(&mut self, transport: Transport) {
+        self.transport = transport;
+        let _ = self.cmd_tx.send(NetCmd::SetTransport(transport));
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+        self.save_config();
+    }
+
+    fn set_source(&mut self, source: SourceKind, passcode: String) {
+        // A port left at the old source's default follows to the new one's.
+        if source != self.source && self.osc_port == self.source.default_port() {
+            self.osc_port = source.default_port();
+            self.osc_port_edit = self.osc_port;
+            let _ = self.cmd_tx.send(NetCmd::SetOscPort(self.osc_port));
+        }
+        self.source = source;
+        self.qlab_passcode = passcode.clone();
+        let _ = self.cmd_tx.send(NetCmd::SetSource(source, passcode));
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+        self.save_config();
+    }
+
+    fn set_listen(&mut self, enabled: bool) {
+        self.listen = enabled;
+        let port = enabled.then_some(self.listen_port);
+        let _ = self.cmd_tx.send(NetCmd::SetListenPort(port));
+        self.save_config();
+    }
+
+    fn set_osc_port(&mut self, port: u16) {
+        self.osc_port = port;
+        self.osc_port_edit = port;
+        let _ = self.cmd_tx.send(NetCmd::SetOscPort(port));
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+        self.save_config();
+    }
+
+    fn reconnect(&mut self) {
+        let _ = self.cmd_tx.send(NetCmd::Reconnect);
+        self.status = "Reconnecting...".to_string();
+        self.state.connected = false;
+        self.subscription = None;
+    }
+
+    fn track_link(&mut self) {
+        let link = self.state.link();
+        if link != self.last_link {
+            if link == Link::Connected {
+                self.fire_hooks(HookEvent::Connect);
+                self.journal.record("connect", &CueInfo::default());
+            } else if self.last_link == Link::Connected {
+                self.fire_hooks(HookEvent::Disconnect);
+                self.pulse_gpio(GpioEvent::Lost);
+                self.journal.record("disconnect", &CueInfo::default());
+            }
+            self.last_link = link;
+        }
+    }
+
+    // Once we've stayed up a while this start no longer counts as a crash.
+    fn check_stable(&mut self) {
+        if !self.stable && self.started.elapsed() >= STABLE_AFTER {
+            self.stable = true;
+            clear_startups();
+        }
+    }
+
+    fn safe_mode_ui(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("safe_mode")
+            .frame(
+                egui::Frame::new()
+                    .fill(egui::Color32::from_rgb(150, 30, 30))
+                    .inner_margin(6.0),
+            )
+            .show(ctx, |ui| {
+                ui.colored_label(
+                    egui::Color32::WHITE,
+                    "Safe mode: started with default settings after repeated crashes. \
+                     Webhooks, the OSC map, the user script and the web display are off \
+                     and nothing is saved.",
+                );
+                ui.colored_label(
+                    egui::Color32::WHITE,
+                    "Your config.toml is untouched; fix or remove it, then restart.",
+                );
+            });
+    }
+
+    // Drives the app without a window: events still update state, webhooks
+    // and the web display, which is always on in this mode, safe mode
+    // included, as it's the only output.
+    pub fn run_headless(mut self) {
+        if self.safe_mode {
+            tracing::warn!(
+                "Safe mode: webhooks, the OSC map and the user script are off; \
+                 the web display stays on"
+            );
+        }
+        self.set_web_server(true);
+        match &self.web_error {
+            Some(err) => tracing::error!("Web display not started: {err}"),
+            None => tracing::info!("Web display on port {}", self.web_port),
+        }
+        loop {
+            match self.rx.recv_timeout(Duration::from_millis(250)) {
+                Ok(ev) => self.apply_event(ev),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            self.poll_split();
+            self.track_link();
+            self.publish_snapshot();
+            self.check_stable();
+        }
+    }
+
+    fn publish_snapshot(&self) {
+        let Ok(mut snapshot) = self.snapshot.lock() else {
+            return;
+        };
+        snapshot.current = self.state.current.clone();
+        snapshot.next = self.state.next.clone();
+        snapshot.connected = self.state.link() == Link::Connected;
+        snapshot.status = self.status.clone();
+        snapshot.last_rx_at = self.state.last_rx_at.map(|t| t.to_rfc3339());
+        snapshot.last_known = self.state.last_known.map(|t| t.to_rfc3339());
+        snapshot.page = self.pager.last.clone();
+        snapshot.held_secs = self.hold_since.map(|since| since.elapsed().as_secs());
+        // Published every frame, so only copy the history when it has moved on.
+        let latest = self.history.back().map(|(time, _)| *time);
+        if snapshot.history.len() != self.history.len()
+            || snapshot.history.last().map(|past| past.time) != latest
+        {
+            snapshot.history = self
+                .history
+                .iter()
+                .map(|(time, cue)| PastCue {
+                    time: *time,
+                    cue: cue.clone(),
+                })
+                .collect();
+        }
+    }
+
+    fn set_web_server(&mut self, enabled: bool) {
+        self.web_server = None;
+        self.web_error = None;
+        if enabled {
+            match WebServer::start(
+                self.web_port,
+                self.snapshot.clone(),
+                self.web_control_tx.clone(),
+            ) {
+                Ok(server) => self.web_server = Some(server),
+                Err(err) => self.web_error = Some(err),
+            }
+        }
+    }
+
+    // Drops everything a broken setup could have put in the way: webhooks and
+    // the OSC map. main also leaves out the user script.
+    pub fn enter_safe_mode(&mut self) {
+        self.safe_mode = true;
+        self.webhooks.clear();
+        self.load_osc_map();
+    }
+
+    fn load_osc_map(&mut self) {
+        let map = if self.safe_mode {
+            Vec::new()
+        } else {
+            osc_map_path().map(|p| load_osc_map(&p)).unwrap_or_default()
+        };
+        self.osc_map_len = map.len();
+        if let Some(pane) = &self.split_pane {
+            let _ = pane.cmd_tx.send(NetCmd::SetOscMap(map.clone()));
+        }
+        let _ = self.cmd_tx.send(NetCmd::SetOscMap(map));
+    }
+
+    fn set_split(&mut self, enabled: bool) {
+        // Dropping the pane's command sender ends its network thread.
+        self.split_pane = None;
+        self.split_error = None;
+        if enabled {
+            self.split.host = self.split.host.trim().to_string();
+            if host_is_valid(&self.split.host) {
+                self.split_pane = Some(SplitPane::start(&self.split, self.state.lost_after));
+            } else {
+                self.split_error = Some("Enter the second source's host".to_string());
+            }
+        }
+        self.split.enabled = self.split_pane.is_some();
+        if self.split_pane.is_some() {
+            self.load_osc_map();
+        }
+    }
+
+    fn poll_split(&mut self) {
+        if let Some(pane) = &mut self.split_pane {
+            while let Ok(ev) = pane.rx.try_recv() {
+                pane.apply_event(ev);
+            }
+        }
+    }
+
+    // The second source's cues, filling the right half of a split display.
+    fn split_panel(&self, ctx: &egui::Context) {
+        let Some(pane) = &self.split_pane else {
+            return;
+        };
+        let link = pane.state.link();
+        egui::SidePanel::right("split")
+            .exact_width(ctx.content_rect().width() / 2.0)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if !self.kiosk {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Host: {}", self.split.host));
+                        ui.separator();
+                        ui.label(match link {
+                            Link::Connected => "Status: connected",
+                            Link::Disconnected => "Status: waiting",
+                            Link::Stale => "Status: stale",
+                        });
+                    });
+                    ui.add_space(6.0);
+                }
+                let fit = if self.auto_fit || self.kiosk {
+                    (ui.available_width() / 700.0)
+                        .min(ui.available_height() / 220.0)
+                        .clamp(0.5, 6.0)
+                } else {
+                    1.0
+                };
+                let scale = fit * self.cue_text_scale;
+                let label = &self.split.label;
+                ui.label(egui::RichText::new(format!("{label} Current")).size(14.0 * scale));
+                cue_block(ui, &pane.state.current, None, None, scale);
+                ui.add_space(6.0 * scale);
+                ui.label(egui::RichText::new(format!("{label} Next")).size(14.0 * scale));
+                cue_block(ui, &pane.state.next, None, None, scale);
+                if link == Link::Stale {
+                    stale_overlay(ui, pane.state.last_rx_at.map(|t| self.display_time(t)));
+                }
+            });
+    }
+
+    fn set_mqtt(&mut self, enabled: bool) {
+        self.mqtt_publisher = None;
+        self.mqtt_error = None;
+        if enabled {
+            match MqttPublisher::start(&self.mqtt) {
+                Ok(publisher) => self.mqtt_publisher = Some(publisher),
+                Err(err) => self.mqtt_error = Some(err),
+            }
+        }
+        self.mqtt.enabled = self.mqtt_publisher.is_some();
+    }
+
+    fn set_midi(&mut self, enabled: bool) {
+        self.midi_tx = None;
+        self.midi_error = None;
+        if enabled {
+            match spawn_midi_out(&self.midi.port) {
+                Ok(tx) => self.midi_tx = Some(tx),
+                Err(err) => {
+                    tracing::warn!("MIDI output off: {err}");
+                    self.midi_error = Some(err);
+                }
+            }
+        }
+        self.midi.enabled = self.midi_tx.is_some();
+    }
+
+    fn send_midi(&self, cue: &CueInfo) {
+        if let Some(tx) = &self.midi_tx {
+            for bytes in cue_messages(&self.midi, cue) {
+                let _ = tx.send(bytes);
+            }
+        }
+    }
+
+    fn set_art_net(&mut self, enabled: bool) {
+        self.art_net_tx = None;
+        self.art_net_error = None;
+        if enabled {
+            match spawn_artnet(self.art_net.target, self.art_net.universe) {
+                Ok(tx) => {
+                    let _ = tx.send(cue_levels(&self.art_net, &self.state.current));
+                    self.art_net_tx = Some(tx);
+                }
+                Err(err) => {
+                    tracing::warn!("Art-Net output off: {err}");
+                    self.art_net_error = Some(err);
+                }
+            }
+        }
+        self.art_net.enabled = self.art_net_tx.is_some();
+    }
+
+    fn set_gpio(&mut self, enabled: bool) {
+        self.gpio_tx = None;
+        self.gpio_error = None;
+        if enabled {
+            match spawn_gpio(&self.gpio) {
+                Ok(tx) => self.gpio_tx = Some(tx),
+                Err(err) => {
+                    tracing::warn!("GPIO output off: {err}");
+                    self.gpio_error = Some(err);
+                }
+            }
+        }
+        self.gpio.enabled = self.gpio_tx.is_some();
+    }
+
+    fn pulse_gpio(&self, event: GpioEvent) {
+        if let Some(tx) = &self.gpio_tx {
+            let _ = tx.send(event);
+        }
+    }
+
+    fn set_control(&mut self, enabled: bool) {
+        self.control = None;
+        self.control_error = None;
+        if enabled {
+            match ControlListener::start(self.control_port) {
+                Ok(listener) => self.control = Some(listener),
+                Err(err) => self.control_error = Some(err),
+            }
+        }
+    }
+
+    fn check_control(&mut self) {
+        let mut msgs: Vec<ControlMsg> = self.web_control.try_iter().collect();
+        if let Some(control) = &self.control {
+            msgs.extend(control.rx.try_iter());
+        }
+        for msg in msgs {
+            match msg {
+                ControlMsg::Brightness(level) => {
+                    self.brightness = level.clamp(0.05, 1.0);
+                    self.last_activity = Instant::now();
+                }
+                ControlMsg::Layout(layout) => {
+                    self.show_cue_list = layout == LayoutPreset::CueList;
+                }
+                ControlMsg::Page(page) => {
+                    self.pager.show(page);
+                    self.last_activity = Instant::now();
+                }
+                ControlMsg::ClearPage => self.pager.clear(),
+                ControlMsg::Hold(on) => self.set_hold(on),
+            }
+        }
+        self.pager.expire();
+    }
+
+    // Brightness after auto-dim; never brighter than the slider.
+    fn effective_brightness(&self) -> f32 {
+        let idle = Duration::from_secs(u64::from(self.auto_dim_mins) * 60);
+        if self.auto_dim_mins > 0 && self.last_activity.elapsed() >= idle {
+            self.brightness.min(self.auto_dim_level)
+        } else {
+            self.brightness
+        }
+    }
+
+    fn apply_event(&mut self, ev: NetEvent) {
+        if let Some(server) = &self.web_server {
+            server.publish(&ev);
+        }
+        if matches!(
+            ev,
+            NetEvent::CueFired(_)
+                | NetEvent::NextCue(_)
+                | NetEvent::SubscribeOk(_)
+                | NetEvent::SubscribeFail
+                | NetEvent::Thump
+        ) {
+            self.no_replies = None;
+        }
+        match ev {
+            NetEvent::CueFired(info) => {
+                let text = format!("{} {}", info.number, info.text);
+                self.session_log.record("cue", text.trim(), Local::now());
+                self.journal.record("cue", &info);
+                if self.history.len() == HISTORY_LEN {
+                    self.history.pop_front();
+                }
+                self.history.push_back((Local::now(), info.clone()));
+                self.state.current = info;
+                self.state.current_since = Some(Instant::now());
+                self.state.last_known = None;
+                self.show_started.get_or_insert_with(Instant::now);
+                self.last_activity = Instant::now();
+                if let Some(timing) = self.pending_timing.take() {
+                    self.unrendered = Some((timing, Instant::now()));
+                }
+                if self.chime.enabled {
+                    self.play_chime(self.chime.sound_for(&self.state.current));
+                }
+                if self.notify_cues && self.window_hidden {
+                    notify_cue(&self.state.current);
+                }
+                if let Some(mqtt) = &self.mqtt_publisher {
+                    mqtt.publish(&self.state.current);
+                }
+                self.send_midi(&self.state.current);
+                if let Some(tx) = &self.art_net_tx {
+                    let _ = tx.send(cue_levels(&self.art_net, &self.state.current));
+                }
+                self.pulse_gpio(GpioEvent::Cue);
+                self.state.touch();
+                self.fire_hooks(HookEvent::Cue);
+                self.scroll_to_current = true;
+            }
+            NetEvent::NextCue(info) => {
+                self.journal.record("next", &info);
+                self.state.next = info;
+                self.state.touch();
+            }
+            NetEvent::CurrentCue(info) => {
+                // Asked for again after each reconnect; only a different cue
+                // restarts the in-cue timer.
+                if info.number != self.state.current.number {
+                    self.state.current_since = Some(Instant::now());
+                }
+                if self.state.last_known.take().is_some()
+                    || info.number != self.state.current.number
+                {
+                    self.journal.record("current", &info);
+                }
+                self.state.current = info;
+                self.state.touch();
+            }
+            NetEvent::SubscribeOk(expiry) => {
+                self.subscription = Some((Instant::now(), expiry));
+                self.retry_at = None;
+                self.rejected = false;
+                self.state.connected = true;
+                self.status = "Subscribed".to_string();
+            }
+            NetEvent::SubscribeFail => {
+                self.subscription = None;
+                self.rejected = true;
+                self.state.connected = false;
+                self.status = "Subscription rejected".to_string();
+            }
+            NetEvent::Thump => {
+                self.state.touch();
+            }
+            NetEvent::RetryAt(at) => self.retry_at = Some(at),
+            NetEvent::ConnectionLost => {
+                self.subscription = None;
+                self.state.connected = false;
+                self.status = "Connection lost; resubscribing...".to_string();
+            }
+            NetEvent::Resumed => {
+                self.state.connected = false;
+                self.status = "Resubscribing after sleep...".to_string();
+            }
+            NetEvent::RouteChanged(route) => {
+                self.route = route;
+            }
+            NetEvent::BackupHostActive(active) => {
+                self.on_backup_host = active;
+            }
+            NetEvent::ConsoleActive(active) => {
+                self.on_console = active;
+            }
+            NetEvent::Hold(on) => self.set_hold(on),
+            NetEvent::NoReplies { refused } => {
+                self.no_replies = Some(refused);
+            }
+            NetEvent::Resolved { addrs, using } => {
+                self.resolved = addrs;
+                self.using_addr = using;
+            }
+            NetEvent::Pushed(true) => {
+                self.state.connected = true;
+                self.status = "Receiving pushed OSC".to_string();
+            }
+            NetEvent::Pushed(false) => {
+                if self.subscription.is_none() {
+                    self.state.connected = false;
+                    self.status = "Pushed OSC stopped".to_string();
+                }
+            }
+            NetEvent::BindFailed(err) => {
+                self.status = format!("Could not bind {err}");
+            }
+            NetEvent::CaptureSaved(result) => {
+                self.capture_status = Some(match result {
+                    Ok(path) => format!("Saved {}", path.display()),
+                    Err(err) => format!("Capture not saved: {err}"),
+                });
+            }
+            NetEvent::CueTiming(timing) => self.pending_timing = Some(timing),
+            NetEvent::Traffic(entry) => {
+                if !self.traffic_paused {
+                    if self.traffic.len() == TRAFFIC_LEN {
+                        self.traffic.pop_front();
+                    }
+                    self.traffic.push_back(entry);
+                }
+            }
+            NetEvent::RoundTrip(rtt) => {
+                if self.round_trips.len() == LATENCY_LEN {
+                    self.round_trips.pop_front();
+                }
+                self.round_trips.push_back(rtt);
+            }
+            NetEvent::Stats(stats) => {
+                // Keep one sample older than the window so rates cover all of it.
+                while self.stats.len() > 1 && self.stats[1].0.elapsed() >= RATE_WINDOW {
+                    self.stats.pop_front();
+                }
+                self.stats.push_back((Instant::now(), stats));
+            }
+            NetEvent::ScriptLoaded(status) => {
+                self.script_widgets.clear();
+                self.script_status = Some(status);
+            }
+            NetEvent::ScriptWidget { name, value } => {
+                if value.is_empty() {
+                    self.script_widgets.remove(&name);
+                } else {
+                    self.script_widgets.insert(name, value);
+                }
+            }
+        }
+    }
+
+    fn top_bar_ui(&mut self, ctx: &egui::Context) {
+        let show_top_bar = !self.kiosk
+            || ctx
+                .input(|i| i.pointer.hover_pos())
+                .is_some_and(|pos| pos.y < 48.0);
+        egui::TopBottomPanel::top("top_bar").show_animated(ctx, show_top_bar, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("TheatreMix");
+                ui.add_space(8.0);
+                if ui.button("Settings").clicked() {
+                    self.show_settings = true;
+                }
+                if !self.profiles.is_empty() {
+                    self.profile_switcher(ui, "profile");
+                }
+                let hold_label = if self.hold_since.is_some() {
+                    "Release hold"
+                } else {
+                    "HOLD"
+                };
+                if ui.button(hold_label).clicked() {
+                    self.toggle_hold();
+                }
+                ui.toggle_value(&mut self.show_cue_list, "Cue list");
+                ui.toggle_value(&mut self.show_history, "History");
+                ui.toggle_value(&mut self.show_stats, "Stats");
+                ui.toggle_value(&mut self.libretto_panel.open, "Script");
+                if !self.button_pages.is_empty() {
+                    ui.toggle_value(&mut self.show_buttons, "Buttons");
+                }
+                if self.operator {
+                    ui.toggle_value(&mut self.show_osc_console, "OSC");
+                }
+                if ui.button("Mark (F2)").clicked() {
+                    self.start_marker();
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let now = self.display_time(Local::now());
+                    ui.label(egui::RichText::new(now.format("%H:%M:%S").to_string()).monospace());
+                    ui.separator();
+                    if ui
+                        .button("Start show")
+                        .on_hover_text("Restart the show clock from now")
+                        .clicked()
+                    {
+                        self.show_started = Some(Instant::now());
+                    }
+                    let elapsed = self
+                        .show_started
+                        .map(|t| format_elapsed(t.elapsed()))
+                        .unwrap_or_else(|| "--:--".to_string());
+                    ui.label(egui::RichText::new(format!("Show {elapsed}")).monospace());
+                });
+            });
+        });
+    }
+}
+
+impl App for TheatreMixApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_config();
+        clear_startups();
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        while let Ok(ev) = self.rx.try_recv() {
+            self.apply_event(ev);
+        }
+        if let Some(pane) = &self.split_pane {
+            // Only the first call takes.
+            let repaint = ctx.clone();
+            pane.waker.set(move || repaint.request_repaint());
+        }
+        self.poll_split();
+        self.publish_snapshot();
+        self.check_mirrored();
+        self.check_stable();
+
+        // Each new fire (not just a new cue number) swaps the still, and a
+        // cue without one clears it.
+        if self.image_for != self.state.current_since {
+            self.image_for = self.state.current_since;
+            self.cue_image = self
+                .sheet
+                .row(&self.state.current.number)
+                .and_then(|row| row.image.as_deref())
+                .and_then(|path| load_cue_image(ctx, path));
+        }
+
+        // A CSV dropped on the window becomes the cue sheet, and a text or
+        // Markdown file the script.
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+        for path in dropped {
+            let ext = path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+                .unwrap_or_default();
+            match ext.as_str() {
+                "csv" => {
+                    self.sheet_url = path.display().to_string();
+                    self.load_sheet();
+                }
+                "txt" | "md" => {
+                    self.libretto_panel.path = path.display().to_string();
+                    self.libretto_panel.open = true;
+                    self.load_libretto();
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(rx) = &self.sheet_rx {
+            while let Ok(result) = rx.try_recv() {
+                match result {
+                    Ok(sheet) => {
+                        self.sheet_status = Some(format!(
+                            "{} cues, updated {}",
+                            sheet.rows.len(),
+                            Local::now().format("%H:%M")
+                        ));
+                        self.sheet = sheet.with_edits(&self.sheet_edits);
+                        self.sheet_base = sheet;
+                    }
+                    // Keep showing the last good copy if a refresh fails.
+                    Err(err) => self.sheet_status = Some(err),
+                }
+            }
+        }
+
+        let synced: Vec<_> = self
+            .notes_rx
+            .as_ref()
+            .map(|rx| rx.try_iter().collect())
+            .unwrap_or_default();
+        for result in synced {
+            match result {
+                Ok(theirs) => {
+                    self.merge_notes(theirs);
+                    self.notes_status = Some(format!("Synced {}", Local::now().format("%H:%M:%S")));
+                }
+                Err(err) => self.notes_status = Some(err),
+            }
+        }
+
+        if let Some(rx) = &self.conn_test {
+            loop {
+                match rx.try_recv() {
+                    Ok(step) => self.conn_test_results.push(step),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.conn_test = None;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(rx) = &self.mirror
+            && let Ok(result) = rx.try_recv()
+        {
+            self.mirror_status = Some(match result {
+                Ok(count) => format!("Mirrored {count} files"),
+                Err(err) => err,
+            });
+            self.mirror = None;
+        }
+
+        if let Some(rx) = &self.discovery {
+            loop {
+                match rx.try_recv() {
+                    Ok(found) => {
+                        if !self.found_hosts.iter().any(|h| h.addr == found.addr) {
+                            self.found_hosts.push(found);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        self.discovery = None;
+                        break;
+                    }
+                }
+            }
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::K)) {
+            self.show_palette = !self.show_palette;
+            self.palette_query.clear();
+            self.palette_selected = 0;
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F2)) {
+            self.start_marker();
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F11)) {
+            self.set_kiosk(ctx, !self.kiosk);
+        }
+
+        self.track_link();
+        let link = self.state.link();
+        let last_rx_at = self.state.last_rx_at.map(|t| self.display_time(t));
+        let last_rx = self.state.last_rx;
+        let subscription = self.subscription;
+        let retry_at = self.retry_at;
+        let backup_route = self.backup_route.is_some();
+        let route = self.route;
+        let backup_host = self.backup_host.clone();
+        let on_backup_host = self.on_backup_host;
+        let console = self.console.clone().filter(|_| self.on_console);
+        let host = self.host.clone();
+        let status = self.status.clone();
+
+        self.check_calls();
+        self.check_reminders();
+        self.check_control();
+        if ctx.input(|i| !i.events.is_empty()) {
+            self.last_activity = Instant::now();
+        }
+
+        // In kiosk mode the top bar only appears while the pointer is near it.
+        let kiosk = self.kiosk;
+        let auto_fit = self.auto_fit;
+        let cue_text_scale = self.cue_text_scale;
+        if self.safe_mode {
+            self.safe_mode_ui(ctx);
+        }
+
+        self.top_bar_ui(ctx);
+
+        self.banners_ui(ctx);
+
+        if self.show_cue_list {
+            self.cue_list_ui(ctx);
+        }
+
+        if self.show_history {
+            self.history_ui(ctx);
+        }
+
+        if self.show_stats {
+            self.stats_ui(ctx);
+        }
+
+        if self.libretto_panel.open {
+            self.libretto_ui(ctx);
+        }
+
+        if self.show_buttons && !self.button_pages.is_empty() {
+            self.buttons_ui(ctx);
+        }
+
+        if self.operator {
+            self.operator_ui(ctx);
+        }
+
+        if self.operator && self.show_osc_console {
+            self.osc_console_ui(ctx);
+        }
+
+        if self.show_osc_monitor {
+            self.osc_monitor_ui(ctx);
+        }
+
+        let mut edit_cue = None;
+        let pre_show = self.pre_show();
+        if pre_show.is_none() {
+            self.split_panel(ctx);
+        }
+        let central = egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(open_at) = pre_show {
+                self.attract_ui(ui, open_at, link);
+                return;
+            }
+            if !kiosk {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Host: {host}"));
+                    ui.separator();
+                    ui.label(format!(
+                        "Status: {status}{}",
+                        match link {
+                            Link::Connected => "",
+                            Link::Disconnected => " (waiting)",
+                            Link::Stale => " (stale)",
+                        }
+                    ));
+                    ui.separator();
+                    if let Some(t) = last_rx {
+                        let age = t.elapsed().as_secs_f32();
+                        ui.label(format!("Last OSC: {:.1}s ago", age));
+                    } else {
+                        ui.label("Last OSC: n/a");
+                    }
+                    if let Some((since, expiry)) = subscription {
+                        // Mirrors the network thread, which renews at half the expiry.
+                        let age = since.elapsed().as_secs();
+                        let renew = u64::from((expiry / 2).max(2)).saturating_sub(age);
+                        let expires = u64::from(expiry).saturating_sub(age);
+                        ui.separator();
+                        if expires == 0 {
+                            ui.colored_label(egui::Color32::RED, "Subscription expired");
+                        } else {
+                            ui.label(format!(
+                                "Subscription renews in {renew} s / expires in {expires} s"
+                            ));
+                        }
+                    } else if let Some(at) = retry_at {
+                        let secs = at.saturating_duration_since(Instant::now()).as_secs_f32();
+                        ui.separator();
+                        ui.label(format!("Retrying in {:.0} s…", secs.ceil()));
+                    }
+                    if backup_route {
+                        ui.separator();
+                        ui.label(match route {
+                            Route::Primary => "Route: primary",
+                            Route::Backup => "Route: backup",
+                        });
+                    }
+                    if let Some(backup_host) = &backup_host {
+                        ui.separator();
+                        if on_backup_host {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                format!("Host: backup ({backup_host})"),
+                            );
+                        } else {
+                            ui.label(format!("Host: main ({host})"));
+                        }
+                    }
+                    if let Some(console) = &console {
+                        ui.separator();
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("Console scenes ({console})"),
+                        );
+                    }
+                });
+                ui.add_space(6.0);
+            }
+            // Sizes in cue_block suit the default 720x300 window; auto-fit
+            // (always on in kiosk) grows or shrinks them to the space left.
+            let fit = if auto_fit || kiosk {
+                (ui.available_width() / 700.0)
+                    .min(ui.available_height() / 220.0)
+                    .clamp(0.5, 6.0)
+            } else {
+                1.0
+            };
+            let scale = fit * cue_text_scale;
+
+            if let Some(shown) = self.state.last_known {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    egui::RichText::new(format!(
+                        "Last known cues from {}, waiting for the host",
+                        shown.format("%H:%M:%S")
+                    ))
+                    .strong()
+                    .size(18.0 * scale),
+                );
+                ui.add_space(4.0 * scale);
+            }
+            if self.cue_section(ui, "Current Cue", &self.state.current, scale) {
+                edit_cue = Some(self.state.current.number.clone());
+            }
+            if self.show_cue_timer
+                && let Some(since) = self.state.current_since
+            {
+                ui.label(
+                    egui::RichText::new(format!("In cue {}", format_elapsed(since.elapsed())))
+                        .monospace()
+                        .strong()
+                        .size(32.0 * scale),
+                );
+            }
+            ui.add_space(6.0 * scale);
+            if self.cue_section(ui, "Next Cue", &self.state.next, scale) {
+                edit_cue = Some(self.state.next.number.clone());
+            }
+
+            let bpm = self
+                .sheet
+                .row(&self.state.current.number)
+                .and_then(|row| row.bpm);
+            if self.show_beat
+                && let (Some(bpm), Some(since)) = (bpm, self.state.current_since)
+            {
+                beat_indicator(ui, bpm, since.elapsed());
+            }
+
+            if !self.script_widgets.is_empty() {
+                ui.add_space(6.0 * scale);
+                ui.horizontal_wrapped(|ui| {
+                    for (name, value) in &self.script_widgets {
+                        ui.label(egui::RichText::new(name).weak().size(16.0 * scale));
+                        ui.label(egui::RichText::new(value).strong().size(16.0 * scale));
+                        ui.add_space(12.0 * scale);
+                    }
+                });
+            }
+
+            // The console's scene is live even with the host gone.
+            if link == Link::Stale && !self.on_console {
+                stale_overlay(ui, last_rx_at);
+            }
+        });
+        self.record_render();
+        if let Some(number) = edit_cue {
+            self.edit_sheet_row(number);
+        }
+        self.sheet_edit_ui(ctx);
+
+        if self.flash.enabled
+            && let Some(since) = self.state.current_since
+        {
+            let length = Duration::from_millis(self.flash.duration_ms.into());
+            let elapsed = since.elapsed();
+            if elapsed < length {
+                let fade = 1.0 - elapsed.as_secs_f32() / length.as_secs_f32();
+                let color = self
+                    .state
+                    .current
+                    .color
+                    .as_deref()
+                    .and_then(parse_cue_color)
+                    .unwrap_or(if self.theme == Theme::Backstage {
+                        egui::Color32::RED
+                    } else {
+                        egui::Color32::WHITE
+                    });
+                cue_flash(
+                    ctx,
+                    central.response.rect,
+                    color,
+                    self.flash.intensity * fade,
+                );
+            }
+        }
+
+        if link != Link::Connected {
+            loss_alert(ctx, central.response.rect, self.loss_alert, link);
+        }
+
+        if let Some(texture) = &self.cue_image {
+            image_overlay(ctx, central.response.rect, texture);
+        }
+
+        if let Some(since) = self.hold_since {
+            hold_overlay(ctx, central.response.rect, since.elapsed());
+        }
+
+        self.settings_ui(ctx);
+
+        if self.show_palette {
+            self.palette_ui(ctx);
+        }
+
+        if let Some(step) = self.wizard {
+            self.wizard_ui(ctx, step);
+        }
+
+        if let Some(at) = self.pending_marker {
+            self.marker_ui(ctx, at);
+        }
+
+        self.auto_select_profile(ctx);
+        if let Some(idx) = self.pending_profile {
+            self.profile_confirm_ui(ctx, idx);
+        }
+
+        self.mirror_confirm_ui(ctx);
+
+        if self.blackout && blackout_overlay(ctx) {
+            self.blackout = false;
+        }
+
+        let brightness = self.effective_brightness();
+        if brightness < 1.0 {
+            dimmer_overlay(ctx, brightness);
+        }
+
+        if self.stage_display.enabled {
+            self.stage_display_viewport(ctx, brightness);
+        }
+
+        let (outer, inner, fullscreen, maximized) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (
+                viewport.outer_rect,
+                viewport.inner_rect,
+                viewport.fullscreen.unwrap_or(false),
+                viewport.maximized.unwrap_or(false),
+            )
+        });
+        self.window_hidden = ctx.input(|i| {
+            let viewport = i.viewport();
+            viewport.minimized.unwrap_or(false) || !viewport.focused.unwrap_or(true)
+        });
+        if let (Some(outer), Some(inner)) = (outer, inner) {
+            let windowed = !fullscreen && !maximized;
+            let (width, height) = match self.window {
+                Some(window) if !windowed => (window.width, window.height),
+                _ => (inner.width(), inner.height()),
+            };
+            self.window = Some(WindowGeometry {
+                x: outer.min.x,
+                y: outer.min.y,
+                width,
+                height,
+                // Kiosk fullscreen comes from the command line, not here.
+                fullscreen: fullscreen && !self.kiosk,
+                maximized,
+            });
+        }
+
+        // No auto-resize: keep the window size stable to avoid event-loop hangs.
+
+        // The network thread wakes us for events; this only keeps clocks
+        // and timeouts moving.
+        ctx.request_repaint_after(Duration::from_secs(1));
+    }
+}
+
+// On its own thread, as some notification servers are slow to answer.
+fn notify_cue(cue: &CueInfo) {
+    let summary = format!("Cue {}", cue.number);
+    let body = cue.text.clone();
+    std::thread::spawn(move || {
+        let shown = notify_rust::Notification::new()
+            .appname("TheatreMix Remote Display")
+            .summary(&summary)
+            .body(&body)
+            .show();
+        if let Err(err) = shown {
+            tracing::warn!("Cue notification not shown: {err}");
+        }
+    });
+}
+
+fn backstage_visuals() -> egui::Visuals {
+    let red = egui::Color32::from_rgb(170, 20, 20);
+    let dim = egui::Color32::from_rgb(90, 10, 10);
+    let mut visuals = egui::Visuals::dark();
+    visuals.override_text_color = Some(red);
+    visuals.panel_fill = egui::Color32::BLACK;
+    visuals.window_fill = egui::Color32::BLACK;
+    visuals.extreme_bg_color = egui::Color32::BLACK;
+    visuals.faint_bg_color = egui::Color32::from_rgb(12, 0, 0);
+    visuals.window_stroke.color = dim;
+    visuals.hyperlink_color = red;
+    visuals.selection.bg_fill = egui::Color32::from_rgb(60, 0, 0);
+    visuals.selection.stroke.color = red;
+    for widget in [
+        &mut visuals.widgets.noninteractive,
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_fill = egui::Color32::from_rgb(20, 0, 0);
+        widget.weak_bg_fill = egui::Color32::from_rgb(20, 0, 0);
+        widget.bg_stroke.color = dim;
+        widget.fg_stroke.color = red;
+    }
+    visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(40, 0, 0);
+    visuals.widgets.active.bg_fill = egui::Color32::from_rgb(55, 0, 0);
+    visuals
+}
+
+fn cue_block(
+    ui: &mut egui::Ui,
+    cue: &CueInfo,
+    translation: Option<&str>,
+    sheet: Option<&SheetRow>,
+    scale: f32,
+) {
+    let title = if cue.number.is_empty() {
+        "—".to_string()
+    } else {
+        cue.number.clone()
+    };
+
+    let text = if cue.text.is_empty() {
+        "—".to_string()
+    } else {
+        cue.text.clone()
+    };
+
+    let fill = cue.color.as_deref().and_then(parse_cue_color);
+    egui::Frame::new()
+        .fill(fill.unwrap_or(egui::Color32::TRANSPARENT))
+        .inner_margin(6.0)
+        .corner_radius(4.0)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            if let Some(fill) = fill {
+                ui.visuals_mut().override_text_color = Some(contrast_text(fill));
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::Label::new(
+                        egui::RichText::new(format!("Cue {title}"))
+                            .size(26.0 * scale)
+                            .strong(),
+                    )
+                    .wrap(),
+                );
+                ui.add(egui::Label::new(egui::RichText::new(text).size(20.0 * scale)).wrap());
+            });
+
+            if let Some(translation) = translation {
+                ui.add(
+                    egui::Label::new(
+                        egui::RichText::new(translation)
+                            .size(18.0 * scale)
+                            .italics(),
+                    )
+                    .wrap(),
+                );
+            }
+
+            if let Some(row) = sheet {
+                for line in [&row.description, &row.notes] {
+                    if !line.is_empty() {
+                        ui.add(
+                            egui::Label::new(egui::RichText::new(line).size(16.0 * scale).weak())
+                                .wrap(),
+                        );
+                    }
+                }
+                if row.edited {
+                    ui.label(
+                        egui::RichText::new("Edited on this display")
+                            .size(12.0 * scale)
+                            .italics()
+                            .color(egui::Color32::from_rgb(220, 160, 40)),
+                    );
+                }
+            }
+
+            ui.label(
+                egui::RichText::new(format!("Color: {}", cue.color.as_deref().unwrap_or("—")))
+                    .size(14.0 * scale),
+            );
+        });
+}
+
+// TheatreMix sends either a colour name or a hex string.
+pub fn parse_cue_color(color: &str) -> Option<egui::Color32> {
+    let color = color.trim();
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if hex.len() == 6 || hex.len() == 3 {
+        let expanded: String = if hex.len() == 3 {
+            hex.chars().flat_map(|c| [c, c]).collect()
+        } else {
+            hex.to_string()
+        };
+        if let Ok(rgb) = u32::from_str_radix(&expanded, 16) {
+            let [_, r, g, b] = rgb.to_be_bytes();
+            return Some(egui::Color32::from_rgb(r, g, b));
+        }
+    }
+    let rgb = match color
+        .to_ascii_lowercase()
+        .replace([' ', '_', '-'], "")
+        .as_str()
+    {
+        "red" => (220, 40, 40),
+        "darkred" => (140, 0, 0),
+        "orange" => (255, 140, 0),
+        "yellow" => (255, 220, 0),
+        "green" => (40, 170, 60),
+        "darkgreen" => (0, 100, 0),
+        "lime" => (150, 230, 50),
+        "cyan" | "aqua" => (0, 200, 220),
+        "teal" => (0, 128, 128),
+        "blue" => (40, 90, 220),
+        "lightblue" => (130, 180, 240),
+        "navy" => (0, 0, 128),
+        "purple" | "violet" => (140, 60, 200),
+        "magenta" | "pink" => (230, 80, 170),
+        "brown" => (140, 80, 30),
+        "white" => (255, 255, 255),
+        "grey" | "gray" => (128, 128, 128),
+        "black" => (0, 0, 0),
+        _ => return None,
+    };
+    Some(egui::Color32::from_rgb(rgb.0, rgb.1, rgb.2))
+}
+
+pub fn load_icon() -> egui::IconData {
+    let bytes = include_bytes!("../assets/Mac.png");
+    let image = image::load_from_memory(bytes)
+        .expect("load icon")
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    egui::IconData {
+        rgba: image.into_raw(),
+        width,
+        height,
+    }
+}
@@ -0,0 +1,409 @@
+//! The cue list, cue sheet editing, the script follower, history and the show report.
+
+use super::*;
+
+impl TheatreMixApp {
+    pub(super) fn edit_sheet_row(&mut self, number: String) {
+        let row = self.sheet.row(&number).cloned().unwrap_or_default();
+        self.sheet_editing = Some((number, row.description, row.notes));
+    }
+
+    // None reverts the cue to the sheet's text.
+    fn set_sheet_edit(&mut self, number: String, edit: Option<SheetEdit>) {
+        let edit = SheetEdit {
+            edited_at: Some(Utc::now()),
+            ..edit.unwrap_or_default()
+        };
+        self.sheet_edits.insert(number, edit);
+        self.apply_sheet_edits();
+    }
+
+    fn apply_sheet_edits(&mut self) {
+        self.sheet = self.sheet_base.with_edits(&self.sheet_edits);
+        if let Some(path) = sheet_edits_path()
+            && let Err(err) = save_sheet_edits(&path, &self.sheet_edits)
+        {
+            self.sheet_status = Some(format!("Edits not saved: {err}"));
+        }
+        self.publish_notes();
+    }
+
+    // What /api/notes serves to displays syncing with this one.
+    pub(super) fn publish_notes(&self) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.notes = SharedNotes {
+                edits: self.sheet_edits.clone(),
+                markers: self.markers.clone(),
+            };
+        }
+    }
+
+    pub(super) fn sync_notes(&mut self) {
+        let peer = self.notes_peer.trim().to_string();
+        self.notes_rx = None;
+        self.notes_status = None;
+        if peer.is_empty() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        spawn_notes_sync(peer, tx);
+        self.notes_rx = Some(rx);
+        self.notes_status = Some("Syncing...".to_string());
+    }
+
+    // Takes the other display's newer edits and the markers not seen here,
+    // which go in this display's session log and journal too.
+    pub(super) fn merge_notes(&mut self, theirs: SharedNotes) {
+        if merge_sheet_edits(&mut self.sheet_edits, theirs.edits) {
+            self.apply_sheet_edits();
+        }
+        let mut added = false;
+        for marker in theirs.markers {
+            if self.markers.contains(&marker) {
+                continue;
+            }
+            self.session_log.record("marker", &marker.text, marker.at);
+            let note = CueInfo {
+                text: marker.text.clone(),
+                ..CueInfo::default()
+            };
+            self.journal.record_at("marker", &note, marker.at);
+            self.markers.push(marker);
+            added = true;
+        }
+        if added {
+            self.publish_notes();
+        }
+    }
+
+    pub(super) fn sheet_edit_ui(&mut self, ctx: &egui::Context) {
+        let Some((number, description, notes)) = &mut self.sheet_editing else {
+            return;
+        };
+        let number = number.clone();
+        let edited = self
+            .sheet_edits
+            .get(&number)
+            .is_some_and(|edit| !edit.is_revert());
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new(format!("Cue {number}"))
+            .id(egui::Id::new("sheet_edit"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("Description");
+                ui.add(egui::TextEdit::multiline(description).desired_rows(2));
+                ui.label("Notes");
+                ui.add(egui::TextEdit::multiline(notes).desired_rows(3));
+                ui.weak("Saved on this display and any syncing notes with it; the published sheet is not changed.");
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        action = Some(true);
+                    }
+                    if ui
+                        .add_enabled(edited, egui::Button::new("Revert to sheet"))
+                        .clicked()
+                    {
+                        action = Some(false);
+                    }
+                });
+            });
+        match action {
+            Some(true) => {
+                let base = self.sheet_base.row(&number).cloned().unwrap_or_default();
+                let edit = SheetEdit {
+                    description: Some(description.trim().to_string())
+                        .filter(|d| *d != base.description),
+                    notes: Some(notes.trim().to_string()).filter(|n| *n != base.notes),
+                    edited_at: None,
+                };
+                let edit = (!edit.is_revert()).then_some(edit);
+                self.set_sheet_edit(number, edit);
+                self.sheet_editing = None;
+            }
+            Some(false) => {
+                self.set_sheet_edit(number, None);
+                self.sheet_editing = None;
+            }
+            None if !open => self.sheet_editing = None,
+            None => {}
+        }
+    }
+
+    pub(super) fn load_sheet(&mut self) {
+        let url = self.sheet_url.trim().to_string();
+        if url.is_empty() {
+            self.sheet_rx = None;
+            self.sheet_base = CueSheet::default();
+            self.sheet = self.sheet_base.with_edits(&self.sheet_edits);
+            self.sheet_status = None;
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let every = Duration::from_secs(u64::from(self.sheet_refresh_mins.max(1)) * 60);
+        spawn_sheet_fetcher(url, every, tx);
+        self.sheet_rx = Some(rx);
+        self.sheet_status = Some("Loading...".to_string());
+    }
+
+    pub(super) fn load_libretto(&mut self) {
+        let path = self.libretto_panel.path.trim().to_string();
+        self.libretto_for.clear();
+        if path.is_empty() {
+            self.libretto = Libretto::default();
+            self.libretto_status = None;
+            return;
+        }
+        match Libretto::load(std::path::Path::new(&path)) {
+            Ok(libretto) => {
+                self.libretto_status = Some(format!(
+                    "{} lines, {} cue markers",
+                    libretto.lines.len(),
+                    libretto.marker_count()
+                ));
+                self.libretto = libretto;
+            }
+            Err(err) => self.libretto_status = Some(err),
+        }
+    }
+
+    // Scrolls only when the cue moves on, so the caller can read ahead
+    // between cues.
+    pub(super) fn libretto_ui(&mut self, ctx: &egui::Context) {
+        let current = self.libretto.line_for(&self.state.current.number);
+        let next = self.libretto.line_for(&self.state.next.number);
+        let scroll = current.is_some() && self.libretto_for != self.state.current.number;
+        if scroll {
+            self.libretto_for = self.state.current.number.clone();
+        }
+        egui::SidePanel::left("libretto")
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.strong("Script");
+                if self.libretto.lines.is_empty() {
+                    ui.weak("Choose a script file in Settings → Script");
+                }
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for (idx, line) in self.libretto.lines.iter().enumerate() {
+                            let mut text = egui::RichText::new(&line.text);
+                            if line.heading {
+                                text = text.strong().size(20.0);
+                            } else if !line.cues.is_empty() {
+                                text = text.strong();
+                            }
+                            let fill = if Some(idx) == current {
+                                Some(ui.visuals().selection.bg_fill)
+                            } else if Some(idx) == next {
+                                Some(ui.visuals().faint_bg_color)
+                            } else {
+                                None
+                            };
+                            let response = match fill {
+                                Some(fill) => {
+                                    egui::Frame::new()
+                                        .fill(fill)
+                                        .show(ui, |ui| {
+                                            ui.set_width(ui.available_width());
+                                            ui.label(text);
+                                        })
+                                        .response
+                                }
+                                None => ui.label(text),
+                            };
+                            if scroll && Some(idx) == current {
+                                response.scroll_to_me(Some(egui::Align::Center));
+                            }
+                        }
+                    });
+            });
+    }
+
+    pub(super) fn cue_list_ui(&mut self, ctx: &egui::Context) {
+        // TheatreMix doesn't send its cue list, so the cue sheet stands in.
+        let current = self.sheet.position(&self.state.current.number);
+        let scroll = std::mem::take(&mut self.scroll_to_current);
+        egui::SidePanel::right("cue_list")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.strong("Cue List");
+                if self.sheet.order.is_empty() {
+                    ui.weak("Load a cue sheet in Settings to list the cues here");
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (idx, number) in self.sheet.order.iter().enumerate() {
+                        let is_current = Some(idx) == current;
+                        let description = self
+                            .sheet
+                            .row(number)
+                            .map_or("", |row| row.description.as_str());
+                        let label = format!("{number}  {description}");
+                        let response = ui.selectable_label(is_current, label);
+                        if is_current && scroll {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                        }
+                    }
+                });
+            });
+    }
+
+    pub(super) fn history_ui(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("history")
+            .resizable(true)
+            .default_height(140.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Cue History");
+                    if ui.small_button("Save show report").clicked() {
+                        self.save_show_report();
+                    }
+                    ui.menu_button("Report options", |ui| self.report_options_ui(ui));
+                    if let Some(status) = &self.report_status {
+                        ui.weak(status);
+                    }
+                });
+                if self.history.is_empty() {
+                    ui.weak("No cues fired yet");
+                }
+                egui::ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for (at, cue) in &self.history {
+                            ui.label(format!(
+                                "{}  {}  {}",
+                                self.display_time(*at).format("%H:%M:%S"),
+                                cue.number,
+                                cue.text
+                            ));
+                        }
+                    });
+            });
+    }
+
+    // Today's entries from the journal, so a restart during the show doesn't
+    // split the report.
+    fn save_show_report(&mut self) {
+        let today = Local::now().date_naive();
+        let entries: Vec<JournalEntry> = journal_dir()
+            .map(|dir| read_journal(&journal_path(&dir, today)))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entry| entry.time.date_naive() == today)
+            .collect();
+        let result = self
+            .report_cues()
+            .and_then(|cues| show_report(&entries, &self.show_report, cues.as_ref()))
+            .and_then(|rows| {
+                let path = report_path().ok_or_else(|| "No config directory".to_string())?;
+                write_show_report(&path, &rows).map(|_| path)
+            });
+        self.report_status = Some(match result {
+            Ok(path) => format!("Saved {}", path.display()),
+            Err(err) => format!("Report not saved: {err}"),
+        });
+    }
+
+    // The cues the report keeps to: those under the act's heading in the
+    // script, and in the cue range on the sheet. None for every cue.
+    fn report_cues(&self) -> Result<Option<HashSet<String>>, String> {
+        let report = &self.show_report;
+        let mut cues: Option<HashSet<String>> = None;
+        let act = report.act.trim();
+        if !act.is_empty() {
+            let under = self
+                .libretto
+                .cues_under(act)
+                .ok_or_else(|| format!("The script has no heading \"{act}\""))?;
+            cues = Some(under.into_iter().collect());
+        }
+        let (first, last) = (report.first_cue.trim(), report.last_cue.trim());
+        if !first.is_empty() || !last.is_empty() {
+            let range: HashSet<String> = self
+                .sheet
+                .range(first, last)
+                .ok_or("The cue range isn't on the cue sheet")?
+                .into_iter()
+                .collect();
+            cues = Some(match cues {
+                Some(act) => act.intersection(&range).cloned().collect(),
+                None => range,
+            });
+        }
+        Ok(cues)
+    }
+
+    fn report_options_ui(&mut self, ui: &mut egui::Ui) {
+        let headings: Vec<String> = self.libretto.headings().map(str::to_string).collect();
+        let report = &mut self.show_report;
+        ui.horizontal(|ui| {
+            ui.label("From");
+            ui.add(
+                egui::TextEdit::singleline(&mut report.from)
+                    .hint_text("19:30")
+                    .desired_width(50.0),
+            );
+            ui.label("to");
+            ui.add(
+                egui::TextEdit::singleline(&mut report.to)
+                    .hint_text("22:15")
+                    .desired_width(50.0),
+            );
+        })
+        .response
+        .on_hover_text("Leave blank for the whole day");
+        ui.separator();
+        ui.checkbox(&mut report.cues, "Cues fired");
+        ui.checkbox(&mut report.markers, "Session markers");
+        ui.checkbox(&mut report.connection, "Connects and disconnects");
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Act");
+            let selected = if report.act.is_empty() {
+                "Whole show"
+            } else {
+                report.act.as_str()
+            };
+            egui::ComboBox::from_id_salt("report_act")
+                .selected_text(selected.to_string())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut report.act, String::new(), "Whole show");
+                    for heading in &headings {
+                        ui.selectable_value(&mut report.act, heading.clone(), heading);
+                    }
+                });
+        })
+        .response
+        .on_hover_text("Headings in the script from Settings → Script");
+        ui.horizontal(|ui| {
+            ui.label("Cues");
+            ui.add(
+                egui::TextEdit::singleline(&mut report.first_cue)
+                    .hint_text("first")
+                    .desired_width(50.0),
+            );
+            ui.label("to");
+            ui.add(
+                egui::TextEdit::singleline(&mut report.last_cue)
+                    .hint_text("last")
+                    .desired_width(50.0),
+            );
+        })
+        .response
+        .on_hover_text("In the cue sheet's order; leave blank for its first or last cue");
+        ui.separator();
+        let columns = &mut report.columns;
+        ui.checkbox(&mut columns.time, "Time column");
+        ui.checkbox(&mut columns.event, "Event column");
+        ui.checkbox(&mut columns.cue, "Cue number column");
+        ui.checkbox(&mut columns.text, "Cue text column");
+        ui.checkbox(
+            &mut columns.since_previous,
+            "Time since previous cue column",
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Traffic stats, render latency and the diagnostics panel.
+
+use super::*;
+
+// One cue's time in each stage after it reached the socket.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct LatencySample {
+    decode: Duration,
+    handoff: Duration,
+    // Waiting in the channel for the UI's next frame.
+    poll: Duration,
+    render: Duration,
+}
+
+impl LatencySample {
+    fn total(&self) -> Duration {
+        self.decode + self.handoff + self.poll + self.render
+    }
+}
+
+impl TheatreMixApp {
+    // Pacing for this session only: cues per minute, the busiest ten-minute
+    // stretches and how long the gaps between cues were.
+    pub(super) fn stats_ui(&mut self, ctx: &egui::Context) {
+        let fires: Vec<DateTime<Local>> = self
+            .history
+            .iter()
+            .map(|(at, _)| *at)
+            .filter(|at| *at >= self.session_start)
+            .collect();
+        let mut open = self.show_stats;
+        egui::Window::new("Cue Statistics")
+            .open(&mut open)
+            .default_width(360.0)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let (Some(first), Some(last)) = (fires.first(), fires.last()) else {
+                    ui.weak("No cues fired this session yet");
+                    return;
+                };
+                let minutes = ((*last - *first).num_seconds() as f32 / 60.0).max(1.0);
+                ui.label(format!(
+                    "{} cues, {:.1} per minute on average",
+                    fires.len(),
+                    fires.len() as f32 / minutes
+                ));
+
+                ui.separator();
+                ui.strong("Cues per minute");
+                let mut per_minute = vec![0usize; minutes.ceil() as usize + 1];
+                let last_minute = per_minute.len() - 1;
+                for at in &fires {
+                    let minute = (*at - *first).num_minutes().max(0) as usize;
+                    per_minute[minute.min(last_minute)] += 1;
+                }
+                column_chart(ui, &per_minute);
+
+                ui.separator();
+                ui.strong("Busiest sections");
+                let mut windows: Vec<(DateTime<Local>, usize)> = fires
+                    .iter()
+                    .map(|start| {
+                        let end = *start + chrono::Duration::minutes(10);
+                        (
+                            *start,
+                            fires.iter().filter(|t| *t >= start && **t < end).count(),
+                        )
+                    })
+                    .collect();
+                windows.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+                let mut shown: Vec<DateTime<Local>> = Vec::new();
+                for (start, count) in windows {
+                    // Skip windows overlapping one already listed.
+                    if shown.iter().any(|s| (start - *s).num_minutes().abs() < 10) {
+                        continue;
+                    }
+                    ui.label(format!(
+                        "{}–{}  {count} cues",
+                        self.display_time(start).format("%H:%M"),
+                        self.display_time(start + chrono::Duration::minutes(10))
+                            .format("%H:%M"),
+                    ));
+                    shown.push(start);
+                    if shown.len() == 3 {
+                        break;
+                    }
+                }
+
+                ui.separator();
+                ui.strong("Gaps between cues");
+                const BUCKETS: &[(i64, &str)] = &[
+                    (5, "< 5 s"),
+                    (15, "5–15 s"),
+                    (30, "15–30 s"),
+                    (60, "30–60 s"),
+                    (120, "1–2 min"),
+                    (300, "2–5 min"),
+                    (i64::MAX, "5 min +"),
+                ];
+                let mut gaps = vec![0usize; BUCKETS.len()];
+                for pair in fires.windows(2) {
+                    let secs = (pair[1] - pair[0]).num_seconds();
+                    if let Some(idx) = BUCKETS.iter().position(|(max, _)| secs < *max) {
+                        gaps[idx] += 1;
+                    }
+                }
+                let most = gaps.iter().copied().max().unwrap_or(0).max(1);
+                egui::Grid::new("gap_histogram").show(ui, |ui| {
+                    for ((_, label), count) in BUCKETS.iter().zip(&gaps) {
+                        ui.label(*label);
+                        ui.add(
+                            egui::ProgressBar::new(*count as f32 / most as f32)
+                                .desired_width(180.0)
+                                .text(count.to_string()),
+                        );
+                        ui.end_row();
+                    }
+                });
+            });
+        self.show_stats = open;
+    }
+
+    pub(super) fn record_render(&mut self) {
+        let Some((timing, applied)) = self.unrendered.take() else {
+            return;
+        };
+        if self.latency.len() == LATENCY_LEN {
+            self.latency.pop_front();
+        }
+        self.latency.push_back(LatencySample {
+            decode: timing.decoded - timing.received,
+            handoff: timing.queued - timing.decoded,
+            poll: applied - timing.queued,
+            render: applied.elapsed(),
+        });
+    }
+
+    pub(super) fn latency_ui(&self, ui: &mut egui::Ui) {
+        if self.latency.is_empty() && self.round_trips.is_empty() {
+            ui.weak("No cues or subscriptions measured yet");
+            return;
+        }
+        let ms = |d: Duration| format!("{:.1} ms", d.as_secs_f32() * 1000.0);
+        egui::Grid::new("latency").striped(true).show(ui, |ui| {
+            ui.strong("Stage");
+            ui.strong("p50");
+            ui.strong("p95");
+            ui.strong("max");
+            ui.end_row();
+            let stage = |f: fn(&LatencySample) -> Duration| -> Vec<Duration> {
+                self.latency.iter().map(f).collect()
+            };
+            let rows = [
+                ("Decode", stage(|s| s.decode)),
+                ("Hand to UI", stage(|s| s.handoff)),
+                ("Wait for frame", stage(|s| s.poll)),
+                ("Render", stage(|s| s.render)),
+                ("Total", stage(LatencySample::total)),
+                (
+                    "Subscribe round trip",
+                    self.round_trips.iter().copied().collect(),
+                ),
+            ];
+            for (name, mut values) in rows {
+                if values.is_empty() {
+                    continue;
+                }
+                values.sort();
+                ui.label(name);
+                ui.label(ms(percentile(&values, 0.5)));
+                ui.label(ms(percentile(&values, 0.95)));
+                ui.label(ms(values[values.len() - 1]));
+                ui.end_row();
+            }
+        });
+        ui.weak(format!(
+            "{} cues. Times start when the packet is read off the socket.",
+            self.latency.len()
+        ));
+    }
+
+    pub(super) fn diagnostics_ui(&mut self, ui: &mut egui::Ui) {
+        let (Some((first_at, first)), Some((_, latest))) = (self.stats.front(), self.stats.back())
+        else {
+            ui.weak("Nothing sent or received yet");
+            return;
+        };
+        let total = latest.since(&self.stats_base);
+        let window = first_at.elapsed().as_secs_f32().max(1.0);
+        let recent = latest.since(first);
+        let per_min = |count: u64| format!("{:.0}/min", count as f32 * 60.0 / window);
+        egui::Grid::new("diagnostics").striped(true).show(ui, |ui| {
+            ui.strong("");
+            ui.strong("Total");
+            ui.strong("Rate");
+            ui.end_row();
+            let rows = [
+                ("Packets sent", total.sent, Some(recent.sent)),
+                ("Packets received", total.received, Some(recent.received)),
+                ("Thumps received", total.thumps, Some(recent.thumps)),
+                (
+                    "Thumps missed",
+                    total.missed_thumps,
+                    Some(recent.missed_thumps),
+                ),
+                ("Decode failures", total.decode_failures, None),
+            ];
+            for (name, count, rate) in rows {
+                ui.label(name);
+                ui.label(count.to_string());
+                ui.label(rate.map(per_min).unwrap_or_default());
+                ui.end_row();
+            }
+        });
+        let expected = total.thumps + total.missed_thumps;
+        if expected > 0 {
+            let loss = total.missed_thumps as f32 * 100.0 / expected as f32;
+            ui.label(format!("Thump loss: {loss:.1}%"));
+        }
+        ui.horizontal(|ui| {
+            ui.weak(format!("Rates over the last {:.0} s.", window));
+            if ui.small_button("Reset").clicked() {
+                self.stats_base = *latest;
+            }
+        });
+    }
+}
+
+// One bar per value, scaled to the largest, for small at-a-glance trends.
+fn column_chart(ui: &mut egui::Ui, values: &[usize]) {
+    let height = 60.0;
+    let width = ui.available_width().min(340.0);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let most = values.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bar = rect.width() / values.len().max(1) as f32;
+    for (idx, value) in values.iter().enumerate() {
+        let top = rect.bottom() - rect.height() * (*value as f32 / most);
+        let left = rect.left() + bar * idx as f32;
+        painter.rect_filled(
+            egui::Rect::from_min_max(
+                egui::pos2(left, top),
+                egui::pos2(left + (bar - 1.0).max(1.0), rect.bottom()),
+            ),
+            0.0,
+            ui.visuals().selection.bg_fill,
+        );
+    }
+}
+
+// Nearest-rank percentile of an already sorted, non-empty slice.
+fn percentile(sorted: &[Duration], p: f32) -> Duration {
+    let rank = ((sorted.len() as f32 * p).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_takes_the_nearest_rank() {
+        let sorted: Vec<Duration> = (1..=10).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 0.5), Duration::from_millis(5));
+        assert_eq!(percentile(&sorted, 0.95), Duration::from_millis(10));
+        assert_eq!(percentile(&sorted, 0.0), Duration::from_millis(1));
+        assert_eq!(percentile(&sorted[..1], 0.99), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn latency_total_adds_every_stage() {
+        let sample = LatencySample {
+            decode: Duration::from_micros(200),
+            handoff: Duration::from_micros(50),
+            poll: Duration::from_millis(4),
+            render: Duration::from_millis(2),
+        };
+        assert_eq!(sample.total(), Duration::from_micros(6250));
+    }
+}
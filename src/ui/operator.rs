@@ -0,0 +1,566 @@
+//! Operator controls: transport, buttons, the OSC monitor and console, markers and the command palette.
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    OpenSettings,
+    ToggleFullscreen,
+    ToggleKiosk,
+    ToggleAlwaysOnTop,
+    Reconnect,
+    ToggleHold,
+    ToggleCueList,
+    ToggleHistory,
+    ToggleStats,
+    ToggleButtons,
+    ToggleOscConsole,
+    ToggleOscMonitor,
+    ToggleLibretto,
+    AddMarker,
+    StartShow,
+}
+
+impl Action {
+    const ALL: &[Action] = &[
+        Action::OpenSettings,
+        Action::ToggleFullscreen,
+        Action::ToggleKiosk,
+        Action::ToggleAlwaysOnTop,
+        Action::Reconnect,
+        Action::ToggleHold,
+        Action::ToggleCueList,
+        Action::ToggleHistory,
+        Action::ToggleStats,
+        Action::ToggleButtons,
+        Action::ToggleOscConsole,
+        Action::ToggleOscMonitor,
+        Action::ToggleLibretto,
+        Action::AddMarker,
+        Action::StartShow,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Action::OpenSettings => "Open settings",
+            Action::ToggleFullscreen => "Toggle fullscreen",
+            Action::ToggleKiosk => "Toggle kiosk mode (F11)",
+            Action::ToggleAlwaysOnTop => "Toggle always on top",
+            Action::Reconnect => "Reconnect to host",
+            Action::ToggleHold => "Hold / release show",
+            Action::ToggleCueList => "Show / hide cue list",
+            Action::ToggleHistory => "Show / hide cue history",
+            Action::ToggleStats => "Show / hide cue statistics",
+            Action::ToggleButtons => "Show / hide button grid",
+            Action::ToggleOscConsole => "Show / hide OSC console",
+            Action::ToggleOscMonitor => "Show / hide OSC traffic monitor",
+            Action::ToggleLibretto => "Show / hide script",
+            Action::AddMarker => "Add session marker",
+            Action::StartShow => "Start show clock",
+        }
+    }
+}
+
+impl TheatreMixApp {
+    fn press_button(&mut self, page: usize, idx: usize) {
+        let Some(button) = self.button_pages.get(page).and_then(|p| p.buttons.get(idx)) else {
+            return;
+        };
+        match &button.action {
+            ButtonAction::Osc { address, args } => {
+                let args: Vec<OscType> = args.split_whitespace().map(osc_arg).collect();
+                let _ = self.cmd_tx.send(NetCmd::Send(osc_message(address, &args)));
+            }
+            ButtonAction::Page { page } => {
+                if let Some(target) = self.button_pages.iter().position(|p| &p.name == page) {
+                    self.button_page = target;
+                }
+            }
+            ButtonAction::Timer => {
+                if self.button_timers.remove(&(page, idx)).is_none() {
+                    self.button_timers.insert((page, idx), Instant::now());
+                }
+            }
+            ButtonAction::Blackout => self.blackout = true,
+        }
+    }
+
+    fn armed(&self) -> bool {
+        self.armed_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    // Sends /go, /back or /jump only while armed, and keeps the arming alive.
+    fn send_transport(&mut self, address: &str, args: &[OscType]) {
+        if !self.armed() {
+            return;
+        }
+        let _ = self.cmd_tx.send(NetCmd::Send(osc_message(address, args)));
+        self.armed_until = Some(Instant::now() + ARM_FOR);
+    }
+
+    pub(super) fn operator_ui(&mut self, ctx: &egui::Context) {
+        let armed = self.armed();
+        egui::TopBottomPanel::bottom("operator").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if armed {
+                    let left = self
+                        .armed_until
+                        .map(|until| until.saturating_duration_since(Instant::now()))
+                        .unwrap_or_default();
+                    let disarm = egui::Button::new(
+                        egui::RichText::new(format!("ARMED {}s", left.as_secs() + 1))
+                            .color(egui::Color32::WHITE),
+                    )
+                    .fill(egui::Color32::from_rgb(180, 30, 30));
+                    if ui.add(disarm).on_hover_text("Disarm").clicked() {
+                        self.armed_until = None;
+                    }
+                } else if ui
+                    .button("Arm")
+                    .on_hover_text("Enable GO and BACK for 30 seconds")
+                    .clicked()
+                {
+                    self.armed_until = Some(Instant::now() + ARM_FOR);
+                }
+                let size = egui::vec2(120.0, 48.0);
+                if ui
+                    .add_enabled(armed, egui::Button::new("BACK").min_size(size))
+                    .clicked()
+                {
+                    // QLab has no /back; stepping the playhead is the nearest.
+                    let back = match self.source {
+                        SourceKind::TheatreMix => "/back",
+                        SourceKind::Qlab => "/playhead/previous",
+                    };
+                    self.send_transport(back, &[]);
+                }
+                let go = egui::Button::new(egui::RichText::new("GO").heading().strong())
+                    .min_size(size)
+                    .fill(egui::Color32::from_rgb(30, 120, 50));
+                if ui.add_enabled(armed, go).clicked() {
+                    self.send_transport("/go", &[]);
+                }
+            });
+        });
+    }
+
+    pub(super) fn buttons_ui(&mut self, ctx: &egui::Context) {
+        let page = self.button_page.min(self.button_pages.len() - 1);
+        let mut pressed = None;
+        egui::SidePanel::left("buttons")
+            .resizable(true)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (idx, p) in self.button_pages.iter().enumerate() {
+                        if ui.selectable_label(idx == page, &p.name).clicked() {
+                            self.button_page = idx;
+                        }
+                    }
+                });
+                ui.separator();
+                let columns = self.button_pages[page].columns.max(1);
+                let gap = ui.spacing().item_spacing.x;
+                let width = (ui.available_width() - gap * (columns - 1) as f32) / columns as f32;
+                let size = egui::vec2(width, width.min(80.0));
+                for (row, chunk) in self.button_pages[page].buttons.chunks(columns).enumerate() {
+                    ui.horizontal(|ui| {
+                        for (col, button) in chunk.iter().enumerate() {
+                            let idx = row * columns + col;
+                            let label = match self.button_timers.get(&(page, idx)) {
+                                Some(started) => {
+                                    format!(
+                                        "{}\n{}",
+                                        button.label,
+                                        format_elapsed(started.elapsed())
+                                    )
+                                }
+                                None => button.label.clone(),
+                            };
+                            let text = egui::RichText::new(label).size(18.0);
+                            if ui.add_sized(size, egui::Button::new(text).wrap()).clicked() {
+                                pressed = Some(idx);
+                            }
+                        }
+                    });
+                }
+            });
+        if let Some(idx) = pressed {
+            self.press_button(page, idx);
+        }
+    }
+
+    pub(super) fn set_osc_monitor(&mut self, open: bool) {
+        if open != self.show_osc_monitor {
+            self.show_osc_monitor = open;
+            let _ = self.cmd_tx.send(NetCmd::SetMonitor(open));
+        }
+    }
+
+    pub(super) fn osc_monitor_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_osc_monitor;
+        egui::Window::new("OSC Monitor")
+            .open(&mut open)
+            .default_size([520.0, 360.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.traffic_paused, "Pause");
+                    if ui.button("Clear").clicked() {
+                        self.traffic.clear();
+                    }
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.traffic_filter)
+                            .hint_text("Filter, e.g. /subscribe")
+                            .desired_width(180.0),
+                    );
+                });
+                ui.separator();
+                let filter = self.traffic_filter.trim().to_ascii_lowercase();
+                egui::ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for entry in &self.traffic {
+                            if !filter.is_empty()
+                                && !entry.address.to_ascii_lowercase().contains(&filter)
+                                && !entry.args.to_ascii_lowercase().contains(&filter)
+                            {
+                                continue;
+                            }
+                            let (arrow, color) = if entry.outgoing {
+                                ("→", egui::Color32::from_rgb(110, 170, 255))
+                            } else {
+                                ("←", egui::Color32::from_rgb(120, 210, 120))
+                            };
+                            ui.horizontal(|ui| {
+                                ui.monospace(entry.at.format("%H:%M:%S%.3f").to_string());
+                                ui.colored_label(color, arrow);
+                                ui.monospace(&entry.address);
+                                ui.weak(&entry.args);
+                            });
+                        }
+                    });
+            });
+        self.set_osc_monitor(open);
+    }
+
+    fn send_osc_console(&mut self, address: String, args: String) {
+        let parsed: Vec<OscType> = args.split_whitespace().map(osc_arg).collect();
+        let _ = self
+            .cmd_tx
+            .send(NetCmd::Send(osc_message(&address, &parsed)));
+        self.osc_sent
+            .retain(|(_, a, b)| (a, b) != (&address, &args));
+        if self.osc_sent.len() == OSC_SENT_LEN {
+            self.osc_sent.pop_front();
+        }
+        self.osc_sent.push_back((Local::now(), address, args));
+    }
+
+    // Free-form OSC to the host, for exercising desk features during tech.
+    pub(super) fn osc_console_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_osc_console;
+        let mut send = None;
+        egui::Window::new("OSC Console")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.osc_address)
+                            .hint_text("/address")
+                            .desired_width(140.0),
+                    );
+                    let args = ui.add(
+                        egui::TextEdit::singleline(&mut self.osc_args)
+                            .hint_text("1 2.5 text")
+                            .desired_width(160.0),
+                    );
+                    let entered =
+                        args.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let valid = self.osc_address.trim().starts_with('/');
+                    if (ui.add_enabled(valid, egui::Button::new("Send")).clicked() || entered)
+                        && valid
+                    {
+                        send = Some((
+                            self.osc_address.trim().to_string(),
+                            self.osc_args.trim().to_string(),
+                        ));
+                    }
+                });
+                ui.weak("Numbers are sent as int or float, anything else as a string.");
+
+                ui.separator();
+                ui.strong("Presets");
+                let mut remove = None;
+                for (idx, preset) in self.osc_presets.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button(&preset.name).clicked() {
+                            send = Some((preset.address.clone(), preset.args.clone()));
+                        }
+                        ui.weak(format!("{} {}", preset.address, preset.args));
+                        if ui.small_button("Remove").clicked() {
+                            remove = Some(idx);
+                        }
+                    });
+                }
+                if let Some(idx) = remove {
+                    self.osc_presets.remove(idx);
+                }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.osc_preset_name)
+                            .hint_text("Preset name")
+                            .desired_width(140.0),
+                    );
+                    let name = self.osc_preset_name.trim();
+                    if ui
+                        .add_enabled(
+                            !name.is_empty() && self.osc_address.trim().starts_with('/'),
+                            egui::Button::new("Save current"),
+                        )
+                        .clicked()
+                    {
+                        self.osc_presets.push(OscPreset {
+                            name: name.to_string(),
+                            address: self.osc_address.trim().to_string(),
+                            args: self.osc_args.trim().to_string(),
+                        });
+                        self.osc_preset_name.clear();
+                    }
+                });
+
+                ui.separator();
+                ui.strong("Sent");
+                if self.osc_sent.is_empty() {
+                    ui.weak("Nothing sent yet");
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .show(ui, |ui| {
+                        for (at, address, args) in self.osc_sent.iter().rev() {
+                            ui.horizontal(|ui| {
+                                ui.weak(at.format("%H:%M:%S").to_string());
+                                if ui
+                                    .selectable_label(false, format!("{address} {args}"))
+                                    .on_hover_text("Click to edit and resend")
+                                    .clicked()
+                                {
+                                    self.osc_address = address.clone();
+                                    self.osc_args = args.clone();
+                                }
+                            });
+                        }
+                    });
+            });
+        if let Some((address, args)) = send {
+            self.send_osc_console(address, args);
+        }
+        if self.show_osc_console && !open {
+            self.save_config();
+        }
+        self.show_osc_console = open;
+    }
+
+    fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::OpenSettings => self.show_settings = true,
+            Action::ToggleFullscreen => {
+                let fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+            }
+            Action::ToggleKiosk => self.set_kiosk(ctx, !self.kiosk),
+            Action::ToggleAlwaysOnTop => self.set_always_on_top(ctx, !self.always_on_top),
+            Action::Reconnect => self.reconnect(),
+            Action::ToggleHold => self.toggle_hold(),
+            Action::ToggleCueList => self.show_cue_list = !self.show_cue_list,
+            Action::ToggleHistory => self.show_history = !self.show_history,
+            Action::ToggleStats => self.show_stats = !self.show_stats,
+            Action::ToggleButtons => self.show_buttons = !self.show_buttons,
+            Action::ToggleOscConsole => self.show_osc_console = !self.show_osc_console,
+            Action::ToggleOscMonitor => self.set_osc_monitor(!self.show_osc_monitor),
+            Action::ToggleLibretto => self.libretto_panel.open = !self.libretto_panel.open,
+            Action::AddMarker => self.start_marker(),
+            Action::StartShow => self.show_started = Some(Instant::now()),
+        }
+    }
+
+    pub(super) fn start_marker(&mut self) {
+        if self.pending_marker.is_none() {
+            self.pending_marker = Some(Local::now());
+            self.marker_text.clear();
+        }
+    }
+
+    pub(super) fn marker_ui(&mut self, ctx: &egui::Context, at: DateTime<Local>) {
+        let (enter, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        egui::Window::new("Session marker")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Marked at {}",
+                    self.display_time(at).format("%H:%M:%S")
+                ));
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut self.marker_text)
+                        .hint_text("Optional note, e.g. mic 7 crackle")
+                        .desired_width(320.0),
+                );
+                edit.request_focus();
+                ui.label("Enter to save, Esc to save without a note");
+            });
+        if enter || escape {
+            if escape {
+                self.marker_text.clear();
+            }
+            self.session_log
+                .record("marker", self.marker_text.trim(), at);
+            let note = CueInfo {
+                text: self.marker_text.trim().to_string(),
+                ..CueInfo::default()
+            };
+            self.journal.record_at("marker", &note, at);
+            self.markers.push(Marker {
+                at,
+                text: note.text,
+            });
+            self.publish_notes();
+            self.pending_marker = None;
+        }
+    }
+
+    pub(super) fn palette_ui(&mut self, ctx: &egui::Context) {
+        let (up, down, enter, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        if escape {
+            self.show_palette = false;
+            return;
+        }
+
+        let mut matches = Vec::new();
+        let mut chosen = None;
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut self.palette_query)
+                        .hint_text("Type a command...")
+                        .desired_width(320.0),
+                );
+                edit.request_focus();
+                if edit.changed() {
+                    self.palette_selected = 0;
+                }
+
+                let mut scored: Vec<(i32, Action)> = Action::ALL
+                    .iter()
+                    .filter_map(|&a| fuzzy_score(&self.palette_query, a.label()).map(|s| (s, a)))
+                    .collect();
+                scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+                matches = scored.into_iter().map(|(_, a)| a).collect();
+
+                if down {
+                    self.palette_selected += 1;
+                }
+                if up {
+                    self.palette_selected = self.palette_selected.saturating_sub(1);
+                }
+                self.palette_selected = self.palette_selected.min(matches.len().saturating_sub(1));
+
+                ui.separator();
+                if matches.is_empty() {
+                    ui.weak("No matching commands");
+                }
+                for (idx, action) in matches.iter().enumerate() {
+                    if ui
+                        .selectable_label(idx == self.palette_selected, action.label())
+                        .clicked()
+                    {
+                        chosen = Some(*action);
+                    }
+                }
+            });
+
+        if enter {
+            chosen = chosen.or_else(|| matches.get(self.palette_selected).copied());
+        }
+        if let Some(action) = chosen {
+            self.show_palette = false;
+            self.run_action(ctx, action);
+        }
+    }
+}
+
+// Case-insensitive subsequence match, rewarding consecutive characters and
+// word starts. Returns None when `query` can't be found in `label` at all.
+fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut prev_match = false;
+    let mut prev_char = ' ';
+    let mut chars = label.chars();
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let q = q.to_ascii_lowercase();
+        loop {
+            let c = chars.next()?;
+            let hit = c.to_ascii_lowercase() == q;
+            if hit {
+                score += 1;
+                if prev_match {
+                    score += 3;
+                }
+                if prev_char == ' ' {
+                    score += 2;
+                }
+            }
+            prev_match = hit;
+            prev_char = c;
+            if hit {
+                break;
+            }
+        }
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_needs_every_character_in_order() {
+        assert!(fuzzy_score("set", "Settings").is_some());
+        assert!(fuzzy_score("SET", "settings").is_some());
+        assert!(fuzzy_score("stg", "Settings").is_some());
+        assert_eq!(fuzzy_score("gnitteS", "Settings"), None);
+        assert_eq!(fuzzy_score("settingsx", "Settings"), None);
+        assert_eq!(fuzzy_score("", "Settings"), Some(0));
+    }
+
+    #[test]
+    fn fuzzy_prefers_runs_and_word_starts() {
+        let run = fuzzy_score("black", "Toggle blackout").unwrap();
+        let scattered = fuzzy_score("black", "Bring lights back").unwrap();
+        assert!(run > scattered);
+        let word_start = fuzzy_score("m", "Toggle osc monitor").unwrap();
+        let mid_word = fuzzy_score("n", "Toggle osc monitor").unwrap();
+        assert!(word_start > mid_word);
+        // Spaces in the query are ignored.
+        assert_eq!(fuzzy_score("os c", "OSC"), fuzzy_score("osc", "OSC"));
+    }
+}
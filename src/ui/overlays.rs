@@ -0,0 +1,442 @@
+//! Banners and overlays drawn over the cue display, and the stage display window.
+
+use super::*;
+
+impl TheatreMixApp {
+    pub(super) fn banners_ui(&mut self, ctx: &egui::Context) {
+        if let Some(refused) = self.no_replies {
+            self.firewall_help_ui(ctx, refused);
+        } else if self.rejected {
+            self.rejection_help_ui(ctx);
+        }
+
+        let mut dismiss_call = false;
+        if let Some((name, _)) = &self.active_call {
+            egui::TopBottomPanel::top("call_banner")
+                .frame(egui::Frame::new().fill(egui::Color32::from_rgb(20, 70, 160)))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new(format!("{name} please"))
+                                .size(32.0)
+                                .strong()
+                                .color(egui::Color32::WHITE),
+                        );
+                        if ui.button("Dismiss").clicked() {
+                            dismiss_call = true;
+                        }
+                    });
+                });
+        }
+        if dismiss_call {
+            self.active_call = None;
+        }
+
+        let mut acknowledged = false;
+        if let Some(page) = self.pager.current() {
+            let remaining = self.pager.remaining();
+            egui::TopBottomPanel::top("page")
+                .frame(egui::Frame::new().fill(egui::Color32::from_rgb(160, 100, 0)))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new(&page.text)
+                                .size(32.0)
+                                .strong()
+                                .color(egui::Color32::WHITE),
+                        );
+                        if ui.button("Got it").clicked() {
+                            acknowledged = true;
+                        }
+                        if let Some(left) = remaining {
+                            ui.label(format!("{} s", left.as_secs() + 1));
+                        }
+                    });
+                });
+        }
+        if acknowledged
+            && let Some(page) = self.pager.acknowledge()
+            && let Some(control) = &self.control
+        {
+            control.acknowledge(&page);
+        }
+
+        for (idx, reminder) in self.reminders.iter_mut().enumerate() {
+            if !reminder.active {
+                continue;
+            }
+            egui::TopBottomPanel::top(egui::Id::new("reminder").with(idx))
+                .frame(egui::Frame::new().fill(egui::Color32::from_rgb(110, 40, 150)))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            egui::RichText::new(format!("{} — {}", reminder.time, reminder.text))
+                                .size(28.0)
+                                .strong()
+                                .color(egui::Color32::WHITE),
+                        );
+                        if ui.button("Acknowledge").clicked() {
+                            reminder.active = false;
+                        }
+                        if ui.button("Snooze 5 min").clicked() {
+                            reminder.active = false;
+                            reminder.snoozed_until = Some(Instant::now() + REMINDER_SNOOZE);
+                        }
+                    });
+                });
+        }
+    }
+
+    fn firewall_help_ui(&mut self, ctx: &egui::Context, refused: bool) {
+        egui::TopBottomPanel::bottom("firewall_help").show(ctx, |ui| {
+            if refused {
+                ui.strong(format!(
+                    "{} refused the connection. Is TheatreMix running there with \
+                     remote control enabled?",
+                    self.host
+                ));
+            } else {
+                ui.strong("Requests are going out but nothing is coming back.");
+                ui.label(firewall_guidance());
+                ui.label("Also check remote control is enabled in TheatreMix.");
+            }
+            ui.horizontal(|ui| {
+                ui.label("Retry on fixed local port");
+                ui.add(egui::DragValue::new(&mut self.retry_port).range(1024..=65535));
+                if ui.button("Retry").clicked() {
+                    self.set_local_port(self.retry_port);
+                    self.no_replies = None;
+                }
+                if ui.button("Dismiss").clicked() {
+                    self.no_replies = None;
+                }
+            });
+        });
+    }
+
+    fn rejection_help_ui(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("rejection_help").show(ctx, |ui| {
+            ui.strong(format!("{} rejected the subscription.", self.host));
+            ui.label(
+                "Check remote displays are allowed in TheatreMix and that its \
+                 device limit hasn't been reached. Retrying less often until it accepts.",
+            );
+            if ui.button("Retry now").clicked() {
+                self.reconnect();
+            }
+        });
+    }
+
+    pub(super) fn attract_ui(&self, ui: &mut egui::Ui, open_at: chrono::NaiveDateTime, link: Link) {
+        let now = Local::now().naive_local();
+        let until =
+            |at: chrono::NaiveDateTime| format_elapsed((at - now).to_std().unwrap_or_default());
+        let curtain = NaiveTime::parse_from_str(self.curtain_edit.trim(), "%H:%M")
+            .ok()
+            .map(|t| now.date().and_time(t))
+            .filter(|at| *at > now);
+        let scale = (ui.available_width() / 700.0).clamp(0.6, 4.0);
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() * 0.2);
+            let name = if self.show_name.trim().is_empty() {
+                "TheatreMix"
+            } else {
+                self.show_name.trim()
+            };
+            ui.label(egui::RichText::new(name).size(56.0 * scale).strong());
+            ui.add_space(16.0 * scale);
+            ui.label(
+                egui::RichText::new(format!("House opens in {}", until(open_at)))
+                    .size(32.0 * scale)
+                    .monospace(),
+            );
+            if let Some(curtain) = curtain {
+                ui.label(
+                    egui::RichText::new(format!("Curtain in {}", until(curtain)))
+                        .size(24.0 * scale)
+                        .monospace(),
+                );
+            }
+            ui.add_space(24.0 * scale);
+            let (text, color) = match link {
+                Link::Connected => ("Connected to TheatreMix", egui::Color32::GREEN),
+                Link::Stale => ("Connection lost", egui::Color32::YELLOW),
+                Link::Disconnected => ("Not connected", egui::Color32::RED),
+            };
+            ui.label(egui::RichText::new(text).size(18.0 * scale).color(color));
+        });
+    }
+
+    pub(super) fn stage_display_viewport(&mut self, ctx: &egui::Context, brightness: f32) {
+        let stage = self.stage_display;
+        let current = &self.state.current;
+        let next = stage.show_next.then_some(&self.state.next);
+        let mut closed = false;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("stage_display"),
+            ViewportBuilder::default()
+                .with_title("TheatreMix Stage Display")
+                .with_inner_size([960.0, 540.0])
+                .with_fullscreen(stage.fullscreen),
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    // No second window on this backend; float it instead.
+                    egui::Window::new("Stage Display")
+                        .default_size([480.0, 270.0])
+                        .show(ctx, |ui| {
+                            stage_display_ui(ui, current, next, stage.text_scale)
+                        });
+                    return;
+                }
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    stage_display_ui(ui, current, next, stage.text_scale)
+                });
+                if brightness < 1.0 {
+                    dimmer_overlay(ctx, brightness);
+                }
+                closed = ctx.input(|i| i.viewport().close_requested());
+            },
+        );
+        if closed {
+            self.stage_display.enabled = false;
+        }
+    }
+}
+
+// Grey out everything already drawn in the panel so frozen data can't be
+// mistaken for a live feed.
+pub(super) fn stale_overlay(ui: &egui::Ui, since: Option<DateTime<Local>>) {
+    let rect = ui.max_rect();
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(170));
+    let since = since
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "—".to_string());
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        format!("STALE — no data since {since}"),
+        egui::FontId::proportional(24.0),
+        egui::Color32::LIGHT_GRAY,
+    );
+}
+
+// The banner covers the cue area but stays under the top bar and any open
+// windows so Settings remains reachable while disconnected.
+pub(super) fn loss_alert(ctx: &egui::Context, cue_area: egui::Rect, alert: LossAlert, link: Link) {
+    match alert {
+        LossAlert::Subtle => {}
+        LossAlert::Banner => {
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            painter.rect_filled(cue_area, 0.0, egui::Color32::from_rgb(170, 0, 0));
+            let text = match link {
+                Link::Stale => "CONNECTION LOST",
+                _ => "NOT CONNECTED",
+            };
+            painter.text(
+                cue_area.center(),
+                egui::Align2::CENTER_CENTER,
+                text,
+                egui::FontId::proportional(48.0),
+                egui::Color32::WHITE,
+            );
+        }
+        LossAlert::BorderPulse => {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("loss_alert"),
+            ));
+            let screen = ctx.content_rect();
+            let phase = (ctx.input(|i| i.time) * std::f64::consts::TAU).sin() * 0.5 + 0.5;
+            let color = egui::Color32::from_rgb(220, 0, 0).gamma_multiply(phase as f32);
+            painter.rect_stroke(
+                screen.shrink(4.0),
+                0.0,
+                egui::Stroke::new(8.0, color),
+                egui::StrokeKind::Inside,
+            );
+            ctx.request_repaint();
+        }
+    }
+}
+
+// Darkens everything, windows included, without taking any input.
+pub(super) fn dimmer_overlay(ctx: &egui::Context, brightness: f32) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Debug,
+        egui::Id::new("dimmer"),
+    ));
+    let alpha = ((1.0 - brightness.clamp(0.0, 1.0)) * 255.0) as u8;
+    painter.rect_filled(
+        ctx.content_rect(),
+        0.0,
+        egui::Color32::from_black_alpha(alpha),
+    );
+}
+
+pub(super) fn cue_flash(
+    ctx: &egui::Context,
+    cue_area: egui::Rect,
+    color: egui::Color32,
+    opacity: f32,
+) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("cue_flash"),
+    ));
+    painter.rect_filled(cue_area, 0.0, color.gamma_multiply(opacity));
+    ctx.request_repaint();
+}
+
+// Flashes on each beat counted from when the cue fired, in the top-right
+// corner of the cue area.
+pub(super) fn beat_indicator(ui: &egui::Ui, bpm: f32, elapsed: Duration) {
+    let beats = elapsed.as_secs_f32() * bpm / 60.0;
+    let on_beat = beats.fract() < 0.15;
+    let downbeat = (beats as u32).is_multiple_of(4);
+    let rect = ui.max_rect();
+    let center = egui::pos2(rect.right() - 50.0, rect.top() + 50.0);
+    let color = match (on_beat, downbeat) {
+        (true, true) => egui::Color32::from_rgb(255, 60, 60),
+        (true, false) => egui::Color32::from_rgb(255, 200, 0),
+        (false, _) => egui::Color32::from_gray(50),
+    };
+    ui.painter().circle_filled(center, 36.0, color);
+    ui.painter().text(
+        center + egui::vec2(0.0, 50.0),
+        egui::Align2::CENTER_TOP,
+        format!("{bpm:.0} BPM"),
+        egui::FontId::proportional(14.0),
+        egui::Color32::GRAY,
+    );
+    ui.ctx().request_repaint();
+}
+
+// Relative paths are looked up in the config directory's `images` folder.
+pub(super) fn load_cue_image(ctx: &egui::Context, path: &str) -> Option<egui::TextureHandle> {
+    let mut full = PathBuf::from(path);
+    if full.is_relative() {
+        full = dirs::config_dir()?
+            .join("theatremix-remote-display")
+            .join("images")
+            .join(full);
+    }
+    let image = image::open(&full).ok()?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    let pixels = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+    Some(ctx.load_texture("cue_image", pixels, egui::TextureOptions::LINEAR))
+}
+
+pub(super) fn image_overlay(
+    ctx: &egui::Context,
+    cue_area: egui::Rect,
+    texture: &egui::TextureHandle,
+) {
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    painter.rect_filled(cue_area, 0.0, egui::Color32::BLACK);
+    let size = texture.size_vec2();
+    let scale = (cue_area.width() / size.x).min(cue_area.height() / size.y);
+    let rect = egui::Rect::from_center_size(cue_area.center(), size * scale);
+    let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    painter.image(texture.id(), rect, uv, egui::Color32::WHITE);
+}
+
+pub(super) fn hold_overlay(ctx: &egui::Context, cue_area: egui::Rect, held_for: Duration) {
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    painter.rect_filled(cue_area, 0.0, egui::Color32::from_rgb(200, 120, 0));
+    painter.text(
+        cue_area.center(),
+        egui::Align2::CENTER_BOTTOM,
+        "SHOW HOLD",
+        egui::FontId::proportional(56.0),
+        egui::Color32::BLACK,
+    );
+    painter.text(
+        cue_area.center(),
+        egui::Align2::CENTER_TOP,
+        format_elapsed(held_for),
+        egui::FontId::monospace(32.0),
+        egui::Color32::BLACK,
+    );
+}
+
+// Covers the whole window in black until it's tapped. Returns true on tap.
+pub(super) fn blackout_overlay(ctx: &egui::Context) -> bool {
+    let rect = ctx.content_rect();
+    egui::Area::new(egui::Id::new("blackout"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(rect.min)
+        .show(ctx, |ui| {
+            let (rect, response) = ui.allocate_exact_size(rect.size(), egui::Sense::click());
+            ui.painter().rect_filled(rect, 0.0, egui::Color32::BLACK);
+            response.clicked()
+        })
+        .inner
+}
+
+// Sized to the window: cue number and text as big as will fit, the next cue
+// small underneath.
+fn stage_display_ui(ui: &mut egui::Ui, cue: &CueInfo, next: Option<&CueInfo>, scale: f32) {
+    let size = ui.available_size();
+    let fit = (size.x / 960.0).min(size.y / 540.0).max(0.3) * scale;
+    let fill = cue.color.as_deref().and_then(parse_cue_color);
+    if let Some(fill) = fill {
+        ui.painter().rect_filled(ui.max_rect(), 0.0, fill);
+        ui.visuals_mut().override_text_color = Some(contrast_text(fill));
+    }
+    ui.vertical_centered(|ui| {
+        ui.add_space(size.y * 0.08);
+        let number = if cue.number.is_empty() {
+            "—"
+        } else {
+            &cue.number
+        };
+        ui.label(egui::RichText::new(number).size(140.0 * fit).strong());
+        ui.add(egui::Label::new(egui::RichText::new(&cue.text).size(64.0 * fit)).wrap());
+        if let Some(next) = next
+            && !next.number.is_empty()
+        {
+            ui.add_space(24.0 * fit);
+            ui.label(
+                egui::RichText::new(format!("Next: {} {}", next.number, next.text))
+                    .size(28.0 * fit)
+                    .weak(),
+            );
+        }
+    });
+}
+
+// Black or white, whichever reads better on `fill` (WCAG relative luminance).
+pub(super) fn contrast_text(fill: egui::Color32) -> egui::Color32 {
+    let channel = |c: u8| {
+        let c = f32::from(c) / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let luminance =
+        0.2126 * channel(fill.r()) + 0.7152 * channel(fill.g()) + 0.0722 * channel(fill.b());
+    if luminance > 0.179 {
+        egui::Color32::BLACK
+    } else {
+        egui::Color32::WHITE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_contrasts_with_its_fill() {
+        assert_eq!(contrast_text(egui::Color32::WHITE), egui::Color32::BLACK);
+        assert_eq!(contrast_text(egui::Color32::YELLOW), egui::Color32::BLACK);
+        assert_eq!(contrast_text(egui::Color32::BLACK), egui::Color32::WHITE);
+        assert_eq!(
+            contrast_text(egui::Color32::from_rgb(20, 70, 160)),
+            egui::Color32::WHITE
+        );
+    }
+}
@@ -0,0 +1,1527 @@
+//! The settings window, profiles, the first-run wizard and settings mirrored from a main display.
+
+use super::*;
+
+impl TheatreMixApp {
+    pub(super) fn settings_ui(&mut self, ctx: &egui::Context) {
+        let mut settings_open = self.show_settings;
+        let mut close_clicked = false;
+        egui::Window::new("Settings")
+            .open(&mut settings_open)
+            .collapsible(false)
+            .resizable(false)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                self.connection_settings(ui, &mut close_clicked);
+                self.network_settings(ui);
+                self.display_settings(ui);
+                self.integration_settings(ui);
+                self.output_settings(ui);
+                self.show_prep_settings(ui);
+            });
+        if close_clicked {
+            settings_open = false;
+        }
+        if self.show_settings && !settings_open {
+            self.save_config();
+        }
+        self.show_settings = settings_open;
+    }
+
+    fn connection_settings(&mut self, ui: &mut egui::Ui, close_clicked: &mut bool) {
+        ui.horizontal(|ui| {
+            ui.label("TheatreMix Host");
+            if self.host_edit.is_empty() {
+                self.host_edit = self.host.clone();
+            }
+            ui.text_edit_singleline(&mut self.host_edit);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Address");
+            let mut address = self.address;
+            egui::ComboBox::from_id_salt("addr_family")
+                .selected_text(address.family.label())
+                .show_ui(ui, |ui| {
+                    for family in AddrFamily::ALL {
+                        ui.selectable_value(&mut address.family, *family, family.label());
+                    }
+                });
+            let pinned = address
+                .pinned
+                .map_or("Automatic".to_string(), |ip| ip.to_string());
+            egui::ComboBox::from_id_salt("addr_pinned")
+                .selected_text(pinned)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut address.pinned, None, "Automatic");
+                    for ip in &self.resolved {
+                        ui.selectable_value(&mut address.pinned, Some(*ip), ip.to_string());
+                    }
+                })
+                .response
+                .on_hover_text("Automatic moves on to the next address if one goes unanswered");
+            if let Some(ip) = self.using_addr {
+                ui.weak(format!("using {ip}"));
+            }
+            if address != self.address {
+                self.set_address(address);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Apply").clicked() {
+                let new_host = self.host_edit.trim().to_string();
+                if host_is_valid(&new_host) && new_host != self.host {
+                    self.set_host(new_host);
+                    self.save_config();
+                }
+            }
+            if ui.button("Close").clicked() {
+                *close_clicked = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let running = self.conn_test.is_some();
+            if ui
+                .add_enabled(!running, egui::Button::new("Test connection"))
+                .clicked()
+            {
+                self.start_conn_test();
+            }
+            if running {
+                ui.spinner();
+            }
+        });
+        ui.horizontal(|ui| {
+            let running = self.discovery.is_some();
+            if ui
+                .add_enabled(!running, egui::Button::new("Find hosts"))
+                .clicked()
+            {
+                let (tx, rx) = mpsc::channel();
+                spawn_discovery(tx);
+                self.discovery = Some(rx);
+                self.found_hosts.clear();
+            }
+            if running {
+                ui.spinner();
+            } else if self.found_hosts.is_empty() {
+                ui.weak("No hosts found yet");
+            }
+        });
+        let mut connect = None;
+        for found in &self.found_hosts {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} ({})", found.name, found.addr));
+                if ui.small_button("Connect").clicked() {
+                    connect = Some(found.addr.to_string());
+                }
+            });
+        }
+        if let Some(host) = connect
+            && host != self.host
+        {
+            self.host_edit = host.clone();
+            self.set_host(host);
+            self.save_config();
+        }
+
+        self.conn_test_ui(ui);
+
+        egui::CollapsingHeader::new("Latency").show(ui, |ui| self.latency_ui(ui));
+        egui::CollapsingHeader::new("Diagnostics").show(ui, |ui| self.diagnostics_ui(ui));
+    }
+
+    fn network_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("OSC monitor").clicked() {
+                self.set_osc_monitor(true);
+            }
+            if ui.button("Save traffic capture").clicked()
+                && let Some(path) = capture_path()
+            {
+                let _ = self.cmd_tx.send(NetCmd::SaveCapture(path));
+            }
+            if let Some(status) = &self.capture_status {
+                ui.weak(status);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Source");
+            let mut source = self.source;
+            egui::ComboBox::from_id_salt("source")
+                .selected_text(source.label())
+                .show_ui(ui, |ui| {
+                    for s in SourceKind::ALL {
+                        ui.selectable_value(&mut source, *s, s.label());
+                    }
+                });
+            if source != self.source {
+                self.set_source(source, self.qlab_passcode.clone());
+            }
+        });
+        if self.source == SourceKind::Qlab {
+            ui.horizontal(|ui| {
+                ui.label("Workspace passcode");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.qlab_passcode_edit)
+                        .password(true)
+                        .desired_width(80.0),
+                )
+                .on_hover_text("Leave empty if the workspace has none");
+                if ui.button("Set").clicked() && self.qlab_passcode_edit != self.qlab_passcode {
+                    self.set_source(self.source, self.qlab_passcode_edit.clone());
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{} OSC port", self.source.label()));
+            ui.add(egui::DragValue::new(&mut self.osc_port_edit).range(1..=65535))
+                .on_hover_text(format!(
+                    "{} uses {}",
+                    self.source.label(),
+                    self.source.default_port()
+                ));
+            if ui.button("Set").clicked() && self.osc_port_edit != self.osc_port {
+                self.set_osc_port(self.osc_port_edit);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Transport");
+            let mut transport = self.transport;
+            egui::ComboBox::from_id_salt("transport")
+                .selected_text(transport.label())
+                .show_ui(ui, |ui| {
+                    for t in Transport::ALL {
+                        ui.selectable_value(&mut transport, *t, t.label());
+                    }
+                })
+                .response
+                .on_hover_text("Try TCP where managed switches drop UDP");
+            if transport != self.transport {
+                self.set_transport(transport);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Resubscribe every");
+            let before = self.retry;
+            ui.add(
+                egui::DragValue::new(&mut self.retry.unreachable_secs)
+                    .range(1..=60)
+                    .suffix(" s"),
+            )
+            .on_hover_text("While the host isn't answering");
+            ui.label("up to");
+            ui.add(
+                egui::DragValue::new(&mut self.retry.unreachable_max_secs)
+                    .range(1..=600)
+                    .suffix(" s"),
+            );
+            ui.label("or after a rejection");
+            ui.add(
+                egui::DragValue::new(&mut self.retry.rejected_secs)
+                    .range(1..=600)
+                    .suffix(" s"),
+            );
+            ui.label("up to");
+            ui.add(
+                egui::DragValue::new(&mut self.retry.rejected_max_secs)
+                    .range(1..=3600)
+                    .suffix(" s"),
+            );
+            if self.retry != before {
+                let _ = self.cmd_tx.send(NetCmd::SetRetryPolicy(self.retry));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Local UDP port");
+            ui.add(egui::DragValue::new(&mut self.local_port_edit).range(0..=65535))
+                .on_hover_text("0 picks a free port each time");
+            if ui.button("Set").clicked() && self.local_port_edit != self.local_port {
+                self.set_local_port(self.local_port_edit);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Network interface");
+            let mut interface = self.interface;
+            let selected = match interface {
+                None => "Any".to_string(),
+                Some(ip) => self
+                    .interfaces
+                    .iter()
+                    .find(|iface| iface.addr == ip)
+                    .map_or(ip.to_string(), LocalInterface::label),
+            };
+            egui::ComboBox::from_id_salt("interface")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut interface, None, "Any");
+                    for iface in &self.interfaces {
+                        ui.selectable_value(&mut interface, Some(iface.addr), iface.label());
+                    }
+                })
+                .response
+                .on_hover_text("Send from this address, e.g. the show network rather than Wi-Fi");
+            if ui.button("Refresh").clicked() {
+                self.interfaces = local_interfaces();
+            }
+            if interface != self.interface {
+                self.set_interface(interface);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut enabled = self.listen;
+            let toggled = ui
+                .checkbox(&mut enabled, "Accept pushed OSC on port")
+                .on_hover_text("For TheatreMix set to send to this machine; no subscription needed")
+                .changed();
+            ui.add_enabled(
+                !enabled,
+                egui::DragValue::new(&mut self.listen_port).range(1..=65535),
+            );
+            if toggled {
+                self.set_listen(enabled);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Backup route (local IP)");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.backup_route_edit)
+                    .hint_text("off")
+                    .desired_width(120.0),
+            );
+            if ui.button("Set").clicked() {
+                let trimmed = self.backup_route_edit.trim();
+                let route = if trimmed.is_empty() {
+                    Some(None)
+                } else {
+                    trimmed.parse::<IpAddr>().ok().map(Some)
+                };
+                if let Some(route) = route {
+                    self.backup_route = route;
+                    let _ = self.cmd_tx.send(NetCmd::SetBackupRoute(route));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Backup host");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.backup_host_edit)
+                    .hint_text("off")
+                    .desired_width(120.0),
+            );
+            if ui.button("Set").clicked() {
+                let trimmed = self.backup_host_edit.trim();
+                let backup = (!trimmed.is_empty()).then(|| trimmed.to_string());
+                if backup.as_deref().is_none_or(host_is_valid) {
+                    self.backup_host = backup.clone();
+                    self.on_backup_host = false;
+                    let _ = self.cmd_tx.send(NetCmd::SetBackupHost(backup));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("X32/M32 console");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.console_edit)
+                    .hint_text("off")
+                    .desired_width(120.0),
+            )
+            .on_hover_text("Shows the console's scene while TheatreMix isn't answering");
+            if ui.button("Set").clicked() {
+                let trimmed = self.console_edit.trim();
+                let console = (!trimmed.is_empty()).then(|| trimmed.to_string());
+                if console.as_deref().is_none_or(host_is_valid) {
+                    self.console = console.clone();
+                    self.on_console = false;
+                    let _ = self.cmd_tx.send(NetCmd::SetConsole(console));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Forward cues to");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.forward_edit)
+                    .hint_text("off")
+                    .desired_width(200.0),
+            )
+            .on_hover_text(
+                "IP:port targets, separated by commas; each gets /cuefired and /nextcue",
+            );
+            if ui.button("Set").clicked() {
+                match parse_targets(&self.forward_edit) {
+                    Ok(targets) => {
+                        self.forward_error = None;
+                        self.forward_edit = join_targets(&targets);
+                        self.forward_to = targets.clone();
+                        let _ = self.cmd_tx.send(NetCmd::SetForwardTargets(targets));
+                    }
+                    Err(err) => self.forward_error = Some(err),
+                }
+            }
+            if let Some(err) = &self.forward_error {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+        });
+    }
+
+    fn display_settings(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        ui.separator();
+
+        let mut on_top = self.always_on_top;
+        if ui.checkbox(&mut on_top, "Always on top").changed() {
+            self.set_always_on_top(&ctx, on_top);
+        }
+
+        let mut kiosk = self.kiosk;
+        if ui
+            .checkbox(&mut kiosk, "Kiosk mode (F11)")
+            .on_hover_text("Fullscreen cues; the top bar appears when the pointer is at the top")
+            .changed()
+        {
+            self.set_kiosk(&ctx, kiosk);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Theme");
+            let before = (self.theme, self.text_scale);
+            egui::ComboBox::from_id_salt("theme")
+                .selected_text(self.theme.label())
+                .show_ui(ui, |ui| {
+                    for theme in Theme::ALL {
+                        ui.selectable_value(&mut self.theme, *theme, theme.label());
+                    }
+                });
+            ui.label("Interface size");
+            ui.add(
+                egui::DragValue::new(&mut self.text_scale)
+                    .range(0.75..=2.5)
+                    .speed(0.05)
+                    .fixed_decimals(2)
+                    .suffix("×"),
+            );
+            if (self.theme, self.text_scale) != before {
+                self.apply_appearance(&ctx);
+            }
+        });
+
+        ui.checkbox(&mut self.show_cue_timer, "Time-in-cue stopwatch");
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.flash.enabled, "Flash when a cue fires");
+            ui.add_enabled(
+                self.flash.enabled,
+                egui::Slider::new(&mut self.flash.intensity, 0.05..=1.0)
+                    .text("intensity")
+                    .fixed_decimals(2),
+            );
+            ui.add_enabled(
+                self.flash.enabled,
+                egui::DragValue::new(&mut self.flash.duration_ms)
+                    .range(100..=3000)
+                    .speed(10)
+                    .suffix(" ms"),
+            );
+        });
+
+        ui.checkbox(
+            &mut self.notify_cues,
+            "Desktop notification when a cue fires while the window is hidden",
+        );
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.chime.enabled, "Chime when a cue fires");
+            if ui.button("Test").clicked() {
+                self.play_chime(self.chime.sound_for(&self.state.current));
+            }
+            ui.weak(format!(
+                "{} color sounds in config.toml",
+                self.chime.colors.len()
+            ));
+        });
+
+        if ui
+            .checkbox(&mut self.operator, "Operator mode (GO / BACK buttons)")
+            .on_hover_text("Sends /go and /back to TheatreMix after arming")
+            .changed()
+        {
+            self.armed_until = None;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Cue text size");
+            ui.add(
+                egui::Slider::new(&mut self.cue_text_scale, 0.5..=4.0)
+                    .fixed_decimals(2)
+                    .suffix("×"),
+            );
+            ui.checkbox(&mut self.auto_fit, "Auto-fit to window");
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_translation, "Show translated cue text");
+            if ui.button("Reload translations").clicked() {
+                self.translations = translations_path()
+                    .map(|p| load_translations(&p))
+                    .unwrap_or_default();
+            }
+            ui.weak(format!("{} loaded", self.translations.len()));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Brightness");
+            ui.add(
+                egui::Slider::new(&mut self.brightness, 0.05..=1.0)
+                    .fixed_decimals(2)
+                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Dim to");
+            ui.add(
+                egui::Slider::new(&mut self.auto_dim_level, 0.05..=1.0)
+                    .fixed_decimals(2)
+                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0)),
+            );
+            ui.label("after");
+            ui.add(
+                egui::DragValue::new(&mut self.auto_dim_mins)
+                    .range(0..=240)
+                    .custom_formatter(|v, _| {
+                        if v == 0.0 {
+                            "never".to_string()
+                        } else {
+                            format!("{v} min")
+                        }
+                    }),
+            )
+            .on_hover_text("Minutes without input or cues");
+        });
+    }
+
+    fn integration_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut enabled = self.control.is_some();
+            let toggled = ui
+                .checkbox(&mut enabled, "OSC display control on port")
+                .on_hover_text(
+                    "/display/brightness 0.0-1.0, /display/layout standard or cue-list, \
+                     /display/message text (none to clear)",
+                )
+                .changed();
+            ui.add_enabled(
+                !enabled,
+                egui::DragValue::new(&mut self.control_port).range(1..=65535),
+            );
+            if toggled {
+                self.set_control(enabled);
+            }
+            if let Some(err) = &self.control_error {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut enabled = self.web_server.is_some();
+            let toggled = ui.checkbox(&mut enabled, "Web display on port").changed();
+            ui.add_enabled(
+                !enabled,
+                egui::DragValue::new(&mut self.web_port).range(1..=65535),
+            );
+            if toggled {
+                self.set_web_server(enabled);
+            }
+            if let Some(err) = &self.web_error {
+                ui.colored_label(egui::Color32::RED, err);
+            }
+        });
+
+        if let Some(server) = &self.web_server
+            && let Ok(mut pairing) = server.pairing.lock()
+        {
+            ui.horizontal(|ui| {
+                let mut accepting = pairing.is_some();
+                if ui
+                    .checkbox(&mut accepting, "Accept settings from a main display")
+                    .changed()
+                {
+                    *pairing = accepting.then(pairing_code);
+                }
+                if let Some(code) = pairing.as_ref() {
+                    ui.label("Pairing code");
+                    ui.monospace(code);
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Spare display");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.spare_edit)
+                    .hint_text("192.168.1.20:8080")
+                    .desired_width(140.0),
+            )
+            .on_hover_text("The spare must have its web display turned on");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.pairing_edit)
+                    .hint_text("Pairing code")
+                    .desired_width(80.0),
+            )
+            .on_hover_text("Shown on the spare while it accepts settings");
+            let spare = self.spare_edit.trim();
+            let code = self.pairing_edit.trim();
+            if ui
+                .add_enabled(
+                    self.mirror.is_none() && !spare.is_empty() && !code.is_empty(),
+                    egui::Button::new("Mirror to spare"),
+                )
+                .clicked()
+            {
+                let (tx, rx) = mpsc::channel();
+                spawn_mirror(format!("http://{spare}/mirror"), code.to_string(), tx);
+                self.mirror = Some(rx);
+                self.mirror_status = None;
+            }
+            if self.mirror.is_some() {
+                ui.spinner();
+            } else if let Some(status) = &self.mirror_status {
+                ui.weak(status);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Reload webhooks").clicked() {
+                self.webhooks = webhooks_path()
+                    .map(|p| load_webhooks(&p))
+                    .unwrap_or_default();
+            }
+            ui.weak(format!("{} configured", self.webhooks.len()));
+            let commands = [
+                &self.command_hooks.cue,
+                &self.command_hooks.connect,
+                &self.command_hooks.disconnect,
+            ];
+            let commands = commands.iter().filter(|c| c.is_some()).count();
+            ui.weak(format!("{commands} command hooks in config.toml"));
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Reload OSC map").clicked() {
+                self.load_osc_map();
+            }
+            ui.weak(format!("{} mappings in osc-map.txt", self.osc_map_len));
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Reload script").clicked() {
+                let _ = self.cmd_tx.send(NetCmd::LoadScript(script_path()));
+            }
+            match &self.script_status {
+                Some(Ok(true)) => ui.weak("script.rhai running"),
+                Some(Err(err)) => ui.colored_label(egui::Color32::RED, err),
+                _ => ui.weak("No script.rhai"),
+            };
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Clock offset");
+            ui.add(
+                egui::DragValue::new(&mut self.clock_offset_minutes)
+                    .range(-24 * 60..=24 * 60)
+                    .suffix(" min"),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Connection loss alert");
+            egui::ComboBox::from_id_salt("loss_alert")
+                .selected_text(self.loss_alert.label())
+                .show_ui(ui, |ui| {
+                    for &alert in LossAlert::ALL {
+                        ui.selectable_value(&mut self.loss_alert, alert, alert.label());
+                    }
+                });
+            ui.label("after");
+            let changed = ui
+                .add(
+                    egui::DragValue::new(&mut self.lost_after_secs)
+                        .range(3..=120)
+                        .suffix(" s"),
+                )
+                .on_hover_text("Without a thump or any other message")
+                .changed();
+            if changed {
+                self.state.lost_after = Duration::from_secs(self.lost_after_secs.into());
+                let _ = self
+                    .cmd_tx
+                    .send(NetCmd::SetLostAfter(self.state.lost_after));
+            }
+        });
+    }
+
+    fn output_settings(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Second display").show(ui, |ui| {
+            ui.checkbox(&mut self.stage_display.enabled, "Open stage display window")
+                .on_hover_text("Drag it to the second monitor, then tick fullscreen");
+            ui.checkbox(&mut self.stage_display.show_next, "Show next cue");
+            ui.checkbox(&mut self.stage_display.fullscreen, "Fullscreen");
+            ui.horizontal(|ui| {
+                ui.label("Text size");
+                ui.add(
+                    egui::Slider::new(&mut self.stage_display.text_scale, 0.5..=3.0)
+                        .fixed_decimals(2)
+                        .suffix("×"),
+                );
+            });
+        });
+
+        egui::CollapsingHeader::new("Split display").show(ui, |ui| {
+            let mut enabled = self.split_pane.is_some();
+            ui.horizontal(|ui| {
+                let toggled = ui
+                    .checkbox(&mut enabled, "Show a second source on the right")
+                    .changed();
+                if toggled {
+                    self.set_split(enabled);
+                }
+                if let Some(err) = &self.split_error {
+                    ui.colored_label(egui::Color32::RED, err);
+                }
+            });
+            ui.add_enabled_ui(!enabled, |ui| {
+                egui::Grid::new("split_source").show(ui, |ui| {
+                    ui.label("Heading");
+                    ui.add(egui::TextEdit::singleline(&mut self.split.label).desired_width(120.0));
+                    ui.end_row();
+                    ui.label("Host");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.split.host)
+                            .hint_text("192.168.1.20")
+                            .desired_width(120.0),
+                    );
+                    ui.end_row();
+                    ui.label("Source");
+                    let before = self.split.source;
+                    egui::ComboBox::from_id_salt("split_kind")
+                        .selected_text(before.label())
+                        .show_ui(ui, |ui| {
+                            for s in SourceKind::ALL {
+                                ui.selectable_value(&mut self.split.source, *s, s.label());
+                            }
+                        });
+                    if self.split.source != before && self.split.osc_port == before.default_port() {
+                        self.split.osc_port = self.split.source.default_port();
+                    }
+                    ui.end_row();
+                    ui.label("OSC port");
+                    ui.add(egui::DragValue::new(&mut self.split.osc_port).range(1..=65535));
+                    ui.end_row();
+                    if self.split.source == SourceKind::Qlab {
+                        ui.label("Workspace passcode");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.split.qlab_passcode)
+                                .password(true)
+                                .desired_width(80.0),
+                        );
+                        ui.end_row();
+                    }
+                    let mut listen = self.split.listen_port.is_some();
+                    let mut port = self.split.listen_port.unwrap_or(32101);
+                    ui.checkbox(&mut listen, "Accept pushed OSC on port")
+                        .on_hover_text("For desks like Eos that send OSC; see osc-map.txt");
+                    ui.add(egui::DragValue::new(&mut port).range(1..=65535));
+                    self.split.listen_port = listen.then_some(port);
+                    ui.end_row();
+                });
+            });
+        });
+
+        egui::CollapsingHeader::new("MQTT").show(ui, |ui| {
+            let mut enabled = self.mqtt_publisher.is_some();
+            ui.horizontal(|ui| {
+                let toggled = ui
+                    .checkbox(&mut enabled, "Publish cues to broker")
+                    .changed();
+                ui.add_enabled(
+                    !enabled,
+                    egui::TextEdit::singleline(&mut self.mqtt.broker)
+                        .hint_text("192.168.1.5:1883")
+                        .desired_width(140.0),
+                );
+                if toggled {
+                    self.set_mqtt(enabled);
+                }
+                if let Some(err) = &self.mqtt_error {
+                    ui.colored_label(egui::Color32::RED, err);
+                }
+            });
+            egui::Grid::new("mqtt_topics").show(ui, |ui| {
+                let topics = [
+                    ("Number topic", &mut self.mqtt.number_topic),
+                    ("Text topic", &mut self.mqtt.text_topic),
+                    ("Color topic", &mut self.mqtt.color_topic),
+                ];
+                for (label, topic) in topics {
+                    ui.label(label);
+                    ui.add_enabled(!enabled, egui::TextEdit::singleline(topic));
+                    ui.end_row();
+                }
+            });
+            ui.add_enabled(
+                !enabled,
+                egui::Checkbox::new(&mut self.mqtt.retain, "Retain the latest cue"),
+            )
+            .on_hover_text("Anything subscribing later gets the current cue at once");
+        });
+
+        egui::CollapsingHeader::new("MIDI").show(ui, |ui| {
+            let ports = self.midi_ports.get_or_insert_with(midi_ports).clone();
+            let mut enabled = self.midi_tx.is_some();
+            ui.horizontal(|ui| {
+                let toggled = ui.checkbox(&mut enabled, "Send on each cue to").changed();
+                ui.add_enabled_ui(!enabled, |ui| {
+                    egui::ComboBox::from_id_salt("midi_port")
+                        .selected_text(if self.midi.port.is_empty() {
+                            "choose a port"
+                        } else {
+                            &self.midi.port
+                        })
+                        .show_ui(ui, |ui| {
+                            for port in &ports {
+                                ui.selectable_value(&mut self.midi.port, port.clone(), port);
+                            }
+                        });
+                });
+                if ui.button("Refresh").clicked() {
+                    self.midi_ports = None;
+                }
+                if toggled {
+                    self.set_midi(enabled);
+                }
+                if let Some(err) = &self.midi_error {
+                    ui.colored_label(egui::Color32::RED, err);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Message");
+                egui::ComboBox::from_id_salt("midi_message")
+                    .selected_text(self.midi.message.label())
+                    .show_ui(ui, |ui| {
+                        for message in MidiMessage::ALL {
+                            ui.selectable_value(&mut self.midi.message, message, message.label());
+                        }
+                    });
+                if ui.button("Test").clicked() {
+                    self.send_midi(&self.state.current);
+                }
+            });
+            ui.horizontal(|ui| match self.midi.message {
+                MidiMessage::MscGo => {
+                    ui.label("Device ID");
+                    ui.add(egui::DragValue::new(&mut self.midi.device_id).range(0..=127))
+                        .on_hover_text("127 addresses every device");
+                    ui.label("Command format");
+                    ui.add(egui::DragValue::new(&mut self.midi.command_format).range(0..=127))
+                        .on_hover_text("127 all types, 1 lighting, 16 sound, 48 video");
+                }
+                MidiMessage::Note => {
+                    ui.label("Channel");
+                    ui.add(egui::DragValue::new(&mut self.midi.channel).range(1..=16));
+                    ui.label("Note");
+                    ui.add(egui::DragValue::new(&mut self.midi.note).range(0..=127));
+                    ui.label("Velocity");
+                    ui.add(egui::DragValue::new(&mut self.midi.velocity).range(1..=127));
+                }
+                MidiMessage::ProgramChange => {
+                    ui.label("Channel");
+                    ui.add(egui::DragValue::new(&mut self.midi.channel).range(1..=16));
+                    ui.weak("Program is the cue number, for cues 0 to 127");
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Art-Net").show(ui, |ui| {
+            let mut enabled = self.art_net_tx.is_some();
+            ui.horizontal(|ui| {
+                let toggled = ui.checkbox(&mut enabled, "Send DMX to").changed();
+                ui.add_enabled(
+                    !enabled,
+                    egui::TextEdit::singleline(&mut self.art_net_target_edit)
+                        .hint_text("255.255.255.255")
+                        .desired_width(120.0),
+                )
+                .on_hover_text("A node's address, or a broadcast address for every node");
+                ui.label("universe");
+                ui.add_enabled(
+                    !enabled,
+                    egui::DragValue::new(&mut self.art_net.universe).range(0..=32767),
+                );
+                if toggled {
+                    match self.art_net_target_edit.trim().parse() {
+                        Ok(target) => {
+                            self.art_net.target = target;
+                            self.set_art_net(enabled);
+                        }
+                        Err(_) => self.art_net_error = Some("Not an IP address".to_string()),
+                    }
+                }
+                if let Some(err) = &self.art_net_error {
+                    ui.colored_label(egui::Color32::RED, err);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Cue color to channels");
+                let changed = ui
+                    .add(
+                        egui::DragValue::new(&mut self.art_net.color_channel)
+                            .range(0..=510)
+                            .custom_formatter(|n, _| {
+                                if n == 0.0 {
+                                    "off".to_string()
+                                } else {
+                                    format!("{n}-{}", n + 2.0)
+                                }
+                            }),
+                    )
+                    .on_hover_text("Red, green and blue from this channel on")
+                    .changed();
+                ui.weak(format!(
+                    "{} cue rules in config.toml",
+                    self.art_net.rules.len()
+                ));
+                if changed && let Some(tx) = &self.art_net_tx {
+                    let _ = tx.send(cue_levels(&self.art_net, &self.state.current));
+                }
+            });
+        });
+
+        if GPIO_SUPPORTED {
+            egui::CollapsingHeader::new("GPIO").show(ui, |ui| {
+                let mut enabled = self.gpio_tx.is_some();
+                ui.horizontal(|ui| {
+                    let toggled = ui.checkbox(&mut enabled, "Pulse pins on").changed();
+                    let mut chip = self.gpio.chip.display().to_string();
+                    if ui
+                        .add_enabled(
+                            !enabled,
+                            egui::TextEdit::singleline(&mut chip).desired_width(140.0),
+                        )
+                        .changed()
+                    {
+                        self.gpio.chip = PathBuf::from(chip);
+                    }
+                    if toggled {
+                        self.set_gpio(enabled);
+                    }
+                    if let Some(err) = &self.gpio_error {
+                        ui.colored_label(egui::Color32::RED, err);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.weak(format!("{} pulses in config.toml", self.gpio.pulses.len()));
+                    if ui
+                        .add_enabled(enabled, egui::Button::new("Test cue"))
+                        .clicked()
+                    {
+                        self.pulse_gpio(GpioEvent::Cue);
+                    }
+                    if ui
+                        .add_enabled(enabled, egui::Button::new("Test lost"))
+                        .clicked()
+                    {
+                        self.pulse_gpio(GpioEvent::Lost);
+                    }
+                });
+            });
+        }
+    }
+
+    fn show_prep_settings(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Cue sheet").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("CSV URL or file");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.sheet_url)
+                        .hint_text("Google Sheet CSV link or path to a .csv")
+                        .desired_width(260.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Refresh every");
+                ui.add(
+                    egui::DragValue::new(&mut self.sheet_refresh_mins)
+                        .range(1..=120)
+                        .suffix(" min"),
+                );
+                if ui.button("Load").clicked() {
+                    self.load_sheet();
+                }
+            });
+            if let Some(status) = &self.sheet_status {
+                ui.weak(status);
+            }
+            ui.weak("Or drop a .csv file on the window.");
+            ui.checkbox(&mut self.show_beat, "Beat indicator for cues with a BPM");
+            ui.horizontal(|ui| {
+                ui.label("Sync notes with");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.notes_peer)
+                        .hint_text("192.168.1.20:8080")
+                        .desired_width(140.0),
+                )
+                .on_hover_text("Another display with its web display on");
+                if ui.button("Sync").clicked() {
+                    self.sync_notes();
+                }
+            });
+            if let Some(status) = &self.notes_status {
+                ui.weak(status);
+            }
+        });
+
+        egui::CollapsingHeader::new("Script").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.libretto_panel.path)
+                        .hint_text("path to a .txt or .md script")
+                        .desired_width(260.0),
+                );
+                if ui.button("Load").clicked() {
+                    self.load_libretto();
+                }
+            });
+            if let Some(status) = &self.libretto_status {
+                ui.weak(status);
+            }
+            ui.weak(
+                "Mark cues with [Q 12] anywhere in a line. The script scrolls to each \
+                 cue's marker as it fires.",
+            );
+            ui.checkbox(&mut self.libretto_panel.open, "Show the script panel");
+        });
+
+        egui::CollapsingHeader::new("Profiles").show(ui, |ui| {
+            if !self.profiles.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label("Switch to");
+                    self.profile_switcher(ui, "settings_profile");
+                });
+            }
+            let mut remove = None;
+            for (idx, profile) in self.profiles.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} ({})", profile.name, profile.host));
+                    ui.checkbox(&mut profile.auto_select, "Automatic")
+                        .on_hover_text(
+                            "Load this profile when its host answers, or when it is \
+                             found on the network while the current host is silent",
+                        );
+                    if ui.small_button("Remove").clicked() {
+                        remove = Some(idx);
+                    }
+                });
+            }
+            if let Some(idx) = remove {
+                let removed = self.profiles.remove(idx);
+                if self.active_profile.as_ref() == Some(&removed.name) {
+                    self.active_profile = None;
+                }
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.profile_name_edit)
+                        .hint_text("Matinee")
+                        .desired_width(120.0),
+                );
+                let name = self.profile_name_edit.trim().to_string();
+                if ui
+                    .add_enabled(
+                        !name.is_empty(),
+                        egui::Button::new("Save current as profile"),
+                    )
+                    .on_hover_text("Host, OSC port, cue sheet, cue list layout and theme")
+                    .clicked()
+                {
+                    let mut profile = self.current_profile(name);
+                    self.active_profile = Some(profile.name.clone());
+                    match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+                        Some(existing) => {
+                            profile.auto_select = existing.auto_select;
+                            *existing = profile;
+                        }
+                        None => self.profiles.push(profile),
+                    }
+                    self.profile_name_edit.clear();
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Reminders").show(ui, |ui| {
+            let mut remove = None;
+            for (idx, reminder) in self.reminders.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut reminder.time)
+                            .hint_text("19:25")
+                            .desired_width(50.0),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut reminder.text)
+                            .hint_text("Check radio mics on")
+                            .desired_width(200.0),
+                    );
+                    // Split as typed but untrimmed, so the text round-trips
+                    // while it is being edited.
+                    let mut groups = reminder.groups.join(",");
+                    if ui
+                        .add(
+                            egui::TextEdit::singleline(&mut groups)
+                                .hint_text("All displays")
+                                .desired_width(110.0),
+                        )
+                        .on_hover_text("Display groups, comma separated")
+                        .changed()
+                    {
+                        reminder.groups = if groups.is_empty() {
+                            Vec::new()
+                        } else {
+                            groups.split(',').map(str::to_string).collect()
+                        };
+                    }
+                    if ui.small_button("Remove").clicked() {
+                        remove = Some(idx);
+                    }
+                });
+            }
+            if let Some(idx) = remove {
+                self.reminders.remove(idx);
+            }
+            if ui.button("Add reminder").clicked() {
+                self.reminders.push(Reminder::default());
+            }
+        });
+
+        egui::CollapsingHeader::new("Show calls").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Show name");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.show_name)
+                        .hint_text("shown before house open")
+                        .desired_width(200.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("House open (HH:MM)");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.house_open_edit)
+                        .hint_text("19:00")
+                        .desired_width(60.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Curtain (HH:MM)");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.curtain_edit)
+                        .hint_text("19:30")
+                        .desired_width(60.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("This display's group");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.display_group)
+                        .hint_text("Dressing rooms")
+                        .desired_width(140.0),
+                )
+                .on_hover_text("Calls aimed at groups show only on displays in them");
+            });
+            let mut remove = None;
+            for (idx, call) in self.calls.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut call.name).desired_width(120.0));
+                    ui.add(
+                        egui::DragValue::new(&mut call.minutes_before)
+                            .range(0..=240)
+                            .suffix(" min before"),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(&mut call.groups)
+                            .hint_text("All displays")
+                            .desired_width(110.0),
+                    )
+                    .on_hover_text("Display groups, comma separated");
+                    ui.checkbox(&mut call.speak, "Speak");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut call.sound)
+                            .hint_text("Sound file")
+                            .desired_width(140.0),
+                    );
+                    if ui.small_button("Remove").clicked() {
+                        remove = Some(idx);
+                    }
+                });
+            }
+            if let Some(idx) = remove {
+                self.calls.remove(idx);
+            }
+            if ui.button("Add call").clicked() {
+                self.calls.push(ShowCall::new("Call", 0));
+            }
+        });
+    }
+
+    fn current_profile(&self, name: String) -> Profile {
+        Profile {
+            name,
+            host: self.host.clone(),
+            osc_port: self.osc_port,
+            sheet_url: self.sheet_url.clone(),
+            layout: if self.show_cue_list {
+                LayoutPreset::CueList
+            } else {
+                LayoutPreset::Standard
+            },
+            theme: self.theme,
+            auto_select: false,
+        }
+    }
+
+    fn apply_profile(&mut self, ctx: &egui::Context, idx: usize) {
+        let Some(profile) = self.profiles.get(idx).cloned() else {
+            return;
+        };
+        if profile.osc_port != self.osc_port {
+            self.set_osc_port(profile.osc_port);
+        }
+        if profile.host != self.host {
+            self.host_edit = profile.host.clone();
+            self.set_host(profile.host);
+        }
+        if profile.sheet_url != self.sheet_url {
+            self.sheet_url = profile.sheet_url;
+            self.load_sheet();
+        }
+        self.show_cue_list = profile.layout == LayoutPreset::CueList;
+        if profile.theme != self.theme {
+            self.theme = profile.theme;
+            self.apply_appearance(ctx);
+        }
+        self.active_profile = Some(profile.name);
+        self.save_config();
+    }
+
+    // Profiles marked for it need no clicks: one whose host answers is loaded
+    // straight away, and while our host is silent the network is searched for
+    // theirs.
+    pub(super) fn auto_select_profile(&mut self, ctx: &egui::Context) {
+        if self.pending_profile.is_some() || !self.profiles.iter().any(|p| p.auto_select) {
+            return;
+        }
+        if self.state.link() == Link::Connected {
+            let active_host = self
+                .profiles
+                .iter()
+                .find(|p| self.active_profile.as_ref() == Some(&p.name))
+                .map(|p| p.host.as_str());
+            if active_host == Some(self.host.as_str()) {
+                return;
+            }
+            if let Some(idx) = self
+                .profiles
+                .iter()
+                .position(|p| p.auto_select && p.host == self.host)
+            {
+                tracing::info!(
+                    "Loading profile {} for {}",
+                    self.profiles[idx].name,
+                    self.host
+                );
+                self.apply_profile(ctx, idx);
+            }
+            return;
+        }
+        if self
+            .auto_profile_at
+            .is_some_and(|at| at.elapsed() < AUTO_PROFILE_AFTER)
+        {
+            return;
+        }
+        self.auto_profile_at = Some(Instant::now());
+        let found = self.profiles.iter().position(|p| {
+            p.auto_select
+                && p.host != self.host
+                && self.found_hosts.iter().any(|found| found.matches(&p.host))
+        });
+        if let Some(idx) = found {
+            tracing::info!(
+                "{} not answering; found {}, switching to profile {}",
+                self.host,
+                self.profiles[idx].host,
+                self.profiles[idx].name
+            );
+            self.apply_profile(ctx, idx);
+        } else if self.discovery.is_none() {
+            let (tx, rx) = mpsc::channel();
+            spawn_discovery(tx);
+            self.discovery = Some(rx);
+            self.found_hosts.clear();
+        }
+    }
+
+    // Switching while connected asks first, as it drops the connection.
+    pub(super) fn profile_switcher(&mut self, ui: &mut egui::Ui, id: &str) {
+        let mut chosen = None;
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(self.active_profile.as_deref().unwrap_or("Profile"))
+            .show_ui(ui, |ui| {
+                for (idx, profile) in self.profiles.iter().enumerate() {
+                    let current = self.active_profile.as_ref() == Some(&profile.name);
+                    if ui.selectable_label(current, &profile.name).clicked() {
+                        chosen = Some(idx);
+                    }
+                }
+            });
+        if let Some(idx) = chosen {
+            if self.state.connected {
+                self.pending_profile = Some(idx);
+            } else {
+                self.apply_profile(ui.ctx(), idx);
+            }
+        }
+    }
+
+    pub(super) fn profile_confirm_ui(&mut self, ctx: &egui::Context, idx: usize) {
+        let Some(name) = self.profiles.get(idx).map(|p| p.name.clone()) else {
+            self.pending_profile = None;
+            return;
+        };
+        egui::Window::new("Switch profile?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Switching to \"{name}\" will disconnect from {}.",
+                    self.host
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Switch").clicked() {
+                        self.apply_profile(ctx, idx);
+                        self.pending_profile = None;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.pending_profile = None;
+                    }
+                });
+            });
+    }
+
+    pub(super) fn wizard_ui(&mut self, ctx: &egui::Context, step: WizardStep) {
+        let mut next = Some(step);
+        egui::Window::new("Welcome")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| match step {
+                WizardStep::Host => {
+                    ui.label(
+                        "Enter the host name or IP address of the machine running TheatreMix.",
+                    );
+                    ui.text_edit_singleline(&mut self.wizard_host);
+                    let host = self.wizard_host.trim().to_string();
+                    let valid = host_is_valid(&host);
+                    if !host.is_empty() && !valid {
+                        ui.colored_label(egui::Color32::RED, "Not a valid host name or address");
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(valid, egui::Button::new("Next")).clicked() {
+                            self.set_host(host);
+                            self.start_conn_test();
+                            next = Some(WizardStep::Test);
+                        }
+                        if ui.button("Skip").clicked() {
+                            next = None;
+                        }
+                    });
+                }
+                WizardStep::Test => {
+                    ui.label(format!("Testing connection to {}...", self.host));
+                    if self.state.connected {
+                        ui.colored_label(egui::Color32::GREEN, "Subscribed to TheatreMix");
+                    } else {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(&self.status);
+                        });
+                        ui.weak(
+                            "Check TheatreMix has remote control enabled and that \
+                             both machines are on the same network.",
+                        );
+                    }
+                    self.conn_test_ui(ui);
+                    ui.horizontal(|ui| {
+                        if ui.button("Back").clicked() {
+                            next = Some(WizardStep::Host);
+                        }
+                        if ui
+                            .add_enabled(self.conn_test.is_none(), egui::Button::new("Test again"))
+                            .clicked()
+                        {
+                            self.start_conn_test();
+                        }
+                        let finish = if self.state.connected {
+                            "Finish"
+                        } else {
+                            "Finish anyway"
+                        };
+                        if ui.button(finish).clicked() {
+                            self.save_config();
+                            next = None;
+                        }
+                    });
+                }
+            });
+        self.wizard = next;
+    }
+
+    // Results also go to the log, for when someone sends it in.
+    fn start_conn_test(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        spawn_connection_test(
+            self.host.clone(),
+            self.osc_port,
+            self.address,
+            self.interface,
+            tx,
+        );
+        self.conn_test = Some(rx);
+        self.conn_test_results.clear();
+    }
+
+    fn conn_test_ui(&self, ui: &mut egui::Ui) {
+        for step in &self.conn_test_results {
+            match &step.outcome {
+                Ok(detail) => {
+                    ui.colored_label(egui::Color32::GREEN, format!("✔ {}: {detail}", step.name))
+                }
+                Err(detail) => {
+                    ui.colored_label(egui::Color32::RED, format!("✘ {}: {detail}", step.name))
+                }
+            };
+            if let Some(advice) = &step.advice {
+                ui.label(advice);
+            }
+        }
+    }
+
+    pub(super) fn check_mirrored(&mut self) {
+        let received = self
+            .web_server
+            .as_ref()
+            .and_then(|w| w.mirrored.lock().ok()?.take());
+        if let Some(files) = received {
+            tracing::info!("{} files received from main display", files.len());
+            self.pending_mirror = Some(files);
+        }
+    }
+
+    pub(super) fn mirror_confirm_ui(&mut self, ctx: &egui::Context) {
+        let Some(files) = &self.pending_mirror else {
+            return;
+        };
+        let mut names: Vec<&String> = files.keys().collect();
+        names.sort();
+        let mut apply = false;
+        let mut discard = false;
+        egui::Window::new("Settings from main display")
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Check what was sent before using it. Command hooks are never taken.");
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        for name in names {
+                            ui.collapsing(name.as_str(), |ui| {
+                                ui.monospace(&files[name]);
+                            });
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+        if apply && let Some(files) = self.pending_mirror.take() {
+            self.mirror_status = Some(match apply_mirror(&files) {
+                Ok(_) => {
+                    // The mirrored config.toml is for the next start; saving
+                    // ours on exit would throw it away.
+                    self.config_path = None;
+                    let note = "Settings from main display saved; restart to use them";
+                    tracing::info!("{note}");
+                    note.to_string()
+                }
+                Err(err) => format!("Mirror not saved: {err}"),
+            });
+        } else if discard {
+            self.pending_mirror = None;
+        }
+    }
+}
+
+// Forwarding targets as typed in Settings: IP:port, comma or space separated.
+fn parse_targets(text: &str) -> Result<Vec<SocketAddr>, String> {
+    text.split([',', ' '])
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| t.parse().map_err(|_| format!("Not an IP:port: {t}")))
+        .collect()
+}
+
+pub(super) fn join_targets(targets: &[SocketAddr]) -> String {
+    targets
+        .iter()
+        .map(SocketAddr::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_round_trip() {
+        let targets = parse_targets("10.0.0.5:9000, 10.0.0.6:9001 [::1]:53000").unwrap();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(
+            join_targets(&targets),
+            "10.0.0.5:9000, 10.0.0.6:9001, [::1]:53000"
+        );
+        assert_eq!(parse_targets(" , ").unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn targets_need_a_port() {
+        assert_eq!(
+            parse_targets("10.0.0.5:9000, 10.0.0.6"),
+            Err("Not an IP:port: 10.0.0.6".to_string())
+        );
+        assert!(parse_targets("console.local:9000").is_err());
+    }
+}
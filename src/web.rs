@@ -0,0 +1,761 @@
+//! The HTTP status page, webhooks and cold-spare mirroring.
+
+use crate::event::{CueInfo, NetEvent, Route};
+use crate::journal::Marker;
+use crate::paging::{Page, PageStatus, page_timeout};
+use crate::protocol::ControlMsg;
+use crate::sheet::SheetEdit;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tungstenite::protocol::{Role, WebSocket};
+
+// What the built-in web page sees; refreshed by the UI after each frame's
+// events so the server thread never touches app state directly.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Snapshot {
+    pub current: CueInfo,
+    pub next: CueInfo,
+    pub connected: bool,
+    pub status: String,
+    pub last_rx_at: Option<String>,
+    // Set while the cues are the ones restored at start.
+    pub last_known: Option<String>,
+    pub page: Option<PageStatus>,
+    // Seconds the show has been held for, while it is.
+    pub held_secs: Option<u64>,
+    // Served on its own at /api/history.
+    #[serde(skip)]
+    pub history: Vec<PastCue>,
+    // Served on its own at /api/notes.
+    #[serde(skip)]
+    pub notes: SharedNotes,
+}
+
+// Cue sheet edits and this session's markers, fetched by other displays
+// from /api/notes and merged there.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SharedNotes {
+    pub edits: HashMap<String, SheetEdit>,
+    pub markers: Vec<Marker>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PastCue {
+    pub time: DateTime<Local>,
+    #[serde(flatten)]
+    pub cue: CueInfo,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookEvent {
+    Cue,
+    Connect,
+    Disconnect,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::Cue => "cue",
+            HookEvent::Connect => "connect",
+            HookEvent::Disconnect => "disconnect",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    // None matches every event (`*` in the file).
+    pub event: Option<HookEvent>,
+    pub url: String,
+    pub template: String,
+}
+
+impl Webhook {
+    pub const DEFAULT_TEMPLATE: &str = r#"{"text": "{event}: Cue {cue_number} {cue_text}"}"#;
+}
+
+pub fn webhooks_path() -> Option<PathBuf> {
+    let base = dirs::config_dir()?;
+    Some(base.join("theatremix-remote-display").join("webhooks.txt"))
+}
+
+// One webhook per line: `<event> <url> [json template]`, where event is
+// `cue`, `connect`, `disconnect` or `*`. Blank lines and `#` comments are
+// ignored.
+pub fn load_webhooks(path: &Path) -> Vec<Webhook> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let event = match parts.next()? {
+                "cue" => Some(HookEvent::Cue),
+                "connect" => Some(HookEvent::Connect),
+                "disconnect" => Some(HookEvent::Disconnect),
+                "*" => None,
+                _ => return None,
+            };
+            let url = parts.next()?.to_string();
+            let template = parts
+                .next()
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .unwrap_or(Webhook::DEFAULT_TEMPLATE)
+                .to_string();
+            Some(Webhook {
+                event,
+                url,
+                template,
+            })
+        })
+        .collect()
+}
+
+// Escapes a value for splicing between the quotes of a JSON template.
+pub fn json_escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Replaces each `{name}` in a hook template with its escaped value.
+pub fn fill_placeholders(
+    template: &str,
+    values: &[(&str, &str)],
+    escape: fn(&str) -> String,
+) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{name}}}"), &escape(value))
+        })
+}
+
+const WEB_PAGE: &str = r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>TheatreMix Remote Display</title>
+<style>
+  body { margin: 0; font-family: system-ui, sans-serif; background: #111; color: #eee; }
+  #status { padding: 8px 12px; font-size: 14px; background: #600; }
+  #status.live { background: #063; }
+  #hold { display: none; padding: 16px 12px; font-size: 8vw; font-weight: bold; text-align: center; background: #a60; }
+  .label { padding: 12px 12px 0; color: #999; text-transform: uppercase; font-size: 13px; }
+  .cue { margin: 6px 12px; padding: 12px; border-radius: 6px; text-shadow: 0 0 4px #000; }
+  .num { font-size: 12vw; font-weight: bold; }
+  .text { font-size: 6vw; }
+  .next .num { font-size: 7vw; }
+  .next .text { font-size: 4vw; }
+</style>
+</head>
+<body>
+<div id="status">Connecting...</div>
+<div id="hold"></div>
+<div class="label">Current cue</div>
+<div class="cue" id="current"><div class="num"></div><div class="text"></div></div>
+<div class="label">Next cue</div>
+<div class="cue next" id="next"><div class="num"></div><div class="text"></div></div>
+<script>
+function show(el, cue) {
+  el.querySelector(".num").textContent = cue.number ? "Cue " + cue.number : "—";
+  el.querySelector(".text").textContent = cue.text || "";
+  el.style.background = cue.color || "transparent";
+}
+async function poll() {
+  const status = document.getElementById("status");
+  try {
+    const state = await (await fetch("/state")).json();
+    show(document.getElementById("current"), state.current);
+    show(document.getElementById("next"), state.next);
+    const hold = document.getElementById("hold");
+    hold.style.display = state.held_secs == null ? "none" : "block";
+    if (state.held_secs != null) {
+      const secs = state.held_secs;
+      hold.textContent = "HOLD " + Math.floor(secs / 60) + ":" + String(secs % 60).padStart(2, "0");
+    }
+    if (state.last_known) {
+      status.textContent = "Last known cues from " + new Date(state.last_known).toLocaleTimeString() + " — waiting for the host";
+      status.className = "";
+    } else {
+      status.textContent = state.connected ? "Live" : "Not connected — " + state.status;
+      status.className = state.connected ? "live" : "";
+    }
+  } catch (e) {
+    status.textContent = "Display app unreachable";
+    status.className = "";
+  }
+  setTimeout(poll, 500);
+}
+poll();
+</script>
+</body>
+</html>
+"#;
+
+// Serves a self-refreshing page mirroring the current state so phones and
+// tablets can follow along without running the app. Dropping it stops the
+// server thread.
+pub struct WebServer {
+    pub server: Arc<tiny_http::Server>,
+    // The code a main display must send with a mirror. None, the default,
+    // refuses every mirror; a wrong code sets it back to None.
+    pub pairing: Arc<Mutex<Option<String>>>,
+    // Files a main display has sent, held until the operator applies them.
+    pub mirrored: Arc<Mutex<Option<HashMap<String, String>>>>,
+    // Every NetEvent the UI applies, as JSON, for /api/events clients.
+    events: broadcast::Sender<String>,
+}
+
+// Events a slow WebSocket client may fall behind by before it skips ahead.
+const EVENT_BACKLOG: usize = 256;
+
+impl WebServer {
+    pub fn start(
+        port: u16,
+        snapshot: Arc<Mutex<Snapshot>>,
+        control: Sender<ControlMsg>,
+    ) -> Result<WebServer, String> {
+        let server =
+            tiny_http::Server::http(("0.0.0.0", port)).map_err(|e| format!("Port {port}: {e}"))?;
+        let server = Arc::new(server);
+        let worker = server.clone();
+        let pairing = Arc::new(Mutex::new(None::<String>));
+        let mirrored = Arc::new(Mutex::new(None));
+        let expected = pairing.clone();
+        let received = mirrored.clone();
+        let (events, _) = broadcast::channel(EVENT_BACKLOG);
+        let feed = events.clone();
+        thread::spawn(move || {
+            for mut request in worker.incoming_requests() {
+                if request.url() == "/api/events" {
+                    let key = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("Sec-WebSocket-Key"))
+                        .map(|h| h.value.as_str().to_string());
+                    let Some(key) = key else {
+                        let _ = request.respond(
+                            tiny_http::Response::from_string("WebSocket only")
+                                .with_status_code(426),
+                        );
+                        continue;
+                    };
+                    let accept = tungstenite::handshake::derive_accept_key(key.as_bytes());
+                    let header = tiny_http::Header::from_bytes("Sec-WebSocket-Accept", accept)
+                        .expect("base64 header");
+                    let response = tiny_http::Response::empty(101).with_header(header);
+                    let stream = request.upgrade("websocket", response);
+                    let events = feed.subscribe();
+                    thread::spawn(move || stream_events(stream, events));
+                    continue;
+                }
+                if request.url() == "/mirror" && *request.method() == tiny_http::Method::Post {
+                    let code = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("X-Pairing-Code"))
+                        .map(|h| h.value.as_str().to_string());
+                    let Ok(mut expected) = expected.lock() else {
+                        continue;
+                    };
+                    let paired = expected.is_some() && *expected == code;
+                    // One code, one try: it has to be read off this screen
+                    // again, so it can't be guessed over the network.
+                    *expected = None;
+                    drop(expected);
+                    let mut body = String::new();
+                    let status = if !paired {
+                        403
+                    } else if request.as_reader().read_to_string(&mut body).is_err() {
+                        400
+                    } else {
+                        match check_mirror(&body) {
+                            Ok(files) => {
+                                if let Ok(mut received) = received.lock() {
+                                    *received = Some(files);
+                                }
+                                200
+                            }
+                            Err(_) => 400,
+                        }
+                    };
+                    let _ = request.respond(tiny_http::Response::empty(status));
+                    continue;
+                }
+                // Control posts have to be JSON. A browser won't send that to
+                // another site without asking first, so no web page the
+                // operator has open can page or hold the displays.
+                if matches!(request.url(), "/page" | "/hold")
+                    && *request.method() == tiny_http::Method::Post
+                    && !is_json(request.headers())
+                {
+                    let _ = request.respond(tiny_http::Response::empty(415));
+                    continue;
+                }
+                if request.url() == "/page" && *request.method() != tiny_http::Method::Get {
+                    let mut body = String::new();
+                    let msg = match request.method() {
+                        tiny_http::Method::Post => request
+                            .as_reader()
+                            .read_to_string(&mut body)
+                            .ok()
+                            .and_then(|_| parse_page(&body)),
+                        tiny_http::Method::Delete => Some(ControlMsg::ClearPage),
+                        _ => None,
+                    };
+                    let Some(msg) = msg else {
+                        let _ = request.respond(tiny_http::Response::empty(400));
+                        continue;
+                    };
+                    // The id is what to look for in GET /page.
+                    let reply = match &msg {
+                        ControlMsg::Page(page) => format!(r#"{{"id": {}}}"#, page.id),
+                        _ => "{}".to_string(),
+                    };
+                    let _ = control.send(msg);
+                    let header = tiny_http::Header::from_bytes("Content-Type", "application/json")
+                        .expect("static header");
+                    let _ = request
+                        .respond(tiny_http::Response::from_string(reply).with_header(header));
+                    continue;
+                }
+                // POST holds every display, DELETE releases them.
+                if request.url() == "/hold" && *request.method() != tiny_http::Method::Get {
+                    let status = match request.method() {
+                        tiny_http::Method::Post => {
+                            let _ = control.send(ControlMsg::Hold(true));
+                            200
+                        }
+                        tiny_http::Method::Delete => {
+                            let _ = control.send(ControlMsg::Hold(false));
+                            200
+                        }
+                        _ => 400,
+                    };
+                    let _ = request.respond(tiny_http::Response::empty(status));
+                    continue;
+                }
+                let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+                let (body, content_type) = match path {
+                    "/" | "/index.html" => (WEB_PAGE.to_string(), "text/html; charset=utf-8"),
+                    "/state" | "/api/state" => {
+                        let body = snapshot
+                            .lock()
+                            .ok()
+                            .and_then(|s| serde_json::to_string(&*s).ok());
+                        (body.unwrap_or_default(), "application/json")
+                    }
+                    // Oldest first; ?limit=N keeps only the latest N.
+                    "/api/history" => {
+                        let limit = query
+                            .split('&')
+                            .find_map(|pair| pair.strip_prefix("limit="))
+                            .and_then(|n| n.parse::<usize>().ok());
+                        let body = snapshot.lock().ok().and_then(|s| {
+                            let skip = limit.map_or(0, |n| s.history.len().saturating_sub(n));
+                            serde_json::to_string(&s.history[skip..]).ok()
+                        });
+                        (body.unwrap_or_default(), "application/json")
+                    }
+                    "/api/notes" => {
+                        let body = snapshot
+                            .lock()
+                            .ok()
+                            .and_then(|s| serde_json::to_string(&s.notes).ok());
+                        (body.unwrap_or_default(), "application/json")
+                    }
+                    "/page" => {
+                        let page = snapshot.lock().ok().and_then(|s| s.page.clone());
+                        (
+                            serde_json::to_string(&page).unwrap_or_default(),
+                            "application/json",
+                        )
+                    }
+                    _ => {
+                        let _ = request.respond(tiny_http::Response::empty(404));
+                        continue;
+                    }
+                };
+                let header = tiny_http::Header::from_bytes("Content-Type", content_type)
+                    .expect("static header");
+                // Dashboards on other origins may poll the API from a browser.
+                let cors = tiny_http::Header::from_bytes("Access-Control-Allow-Origin", "*")
+                    .expect("static header");
+                let _ = request.respond(
+                    tiny_http::Response::from_string(body)
+                        .with_header(header)
+                        .with_header(cors),
+                );
+            }
+        });
+        Ok(WebServer {
+            server,
+            pairing,
+            mirrored,
+            events,
+        })
+    }
+
+    pub fn publish(&self, ev: &NetEvent) {
+        if self.events.receiver_count() > 0 {
+            let _ = self.events.send(event_json(ev).to_string());
+        }
+    }
+}
+
+// Pushes events until the client goes away. Nothing is read back, so a closed
+// socket shows up on the next write; thumps keep those coming.
+fn stream_events(
+    stream: Box<dyn tiny_http::ReadWrite + Send>,
+    mut events: broadcast::Receiver<String>,
+) {
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    loop {
+        let json = match events.blocking_recv() {
+            Ok(json) => json,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!("WebSocket client fell {missed} events behind");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if socket.send(tungstenite::Message::text(json)).is_err() {
+            break;
+        }
+    }
+    let _ = socket.close(None);
+}
+
+// One object per event, named in snake_case under "event".
+fn event_json(ev: &NetEvent) -> serde_json::Value {
+    use serde_json::json;
+    match ev {
+        NetEvent::CueFired(cue) => json!({"event": "cue_fired", "cue": cue}),
+        NetEvent::NextCue(cue) => json!({"event": "next_cue", "cue": cue}),
+        NetEvent::CurrentCue(cue) => json!({"event": "current_cue", "cue": cue}),
+        NetEvent::SubscribeOk(secs) => json!({"event": "subscribe_ok", "expires_in": secs}),
+        NetEvent::SubscribeFail => json!({"event": "subscribe_fail"}),
+        NetEvent::Thump => json!({"event": "thump"}),
+        NetEvent::Resumed => json!({"event": "resumed"}),
+        NetEvent::RouteChanged(route) => json!({
+            "event": "route_changed",
+            "route": match route {
+                Route::Primary => "primary",
+                Route::Backup => "backup",
+            },
+        }),
+        NetEvent::BackupHostActive(active) => {
+            json!({"event": "backup_host_active", "active": active})
+        }
+        NetEvent::ConsoleActive(active) => json!({"event": "console_active", "active": active}),
+        NetEvent::Hold(on) => json!({"event": "hold", "active": on}),
+        NetEvent::NoReplies { refused } => json!({"event": "no_replies", "refused": refused}),
+        NetEvent::Resolved { addrs, using } => {
+            json!({"event": "resolved", "addrs": addrs, "using": using})
+        }
+        NetEvent::Pushed(active) => json!({"event": "pushed", "active": active}),
+        NetEvent::ConnectionLost => json!({"event": "connection_lost"}),
+        NetEvent::RetryAt(at) => json!({
+            "event": "retry_at",
+            "in_ms": at.saturating_duration_since(Instant::now()).as_millis() as u64,
+        }),
+        NetEvent::Stats(stats) => json!({
+            "event": "stats",
+            "sent": stats.sent,
+            "received": stats.received,
+            "thumps": stats.thumps,
+            "missed_thumps": stats.missed_thumps,
+            "decode_failures": stats.decode_failures,
+        }),
+        NetEvent::BindFailed(err) => json!({"event": "bind_failed", "error": err}),
+        NetEvent::CaptureSaved(Ok(path)) => json!({"event": "capture_saved", "path": path}),
+        NetEvent::CaptureSaved(Err(err)) => json!({"event": "capture_saved", "error": err}),
+        NetEvent::CueTiming(timing) => json!({
+            "event": "cue_timing",
+            "decode_ms": (timing.decoded - timing.received).as_secs_f64() * 1000.0,
+            "queue_ms": (timing.queued - timing.decoded).as_secs_f64() * 1000.0,
+        }),
+        NetEvent::RoundTrip(rtt) => {
+            json!({"event": "round_trip", "ms": rtt.as_secs_f64() * 1000.0})
+        }
+        NetEvent::Traffic(entry) => json!({
+            "event": "traffic",
+            "at": entry.at,
+            "outgoing": entry.outgoing,
+            "address": entry.address,
+            "args": entry.args,
+        }),
+        NetEvent::ScriptLoaded(Ok(running)) => {
+            json!({"event": "script_loaded", "running": running})
+        }
+        NetEvent::ScriptLoaded(Err(err)) => json!({"event": "script_loaded", "error": err}),
+        NetEvent::ScriptWidget { name, value } => {
+            json!({"event": "script_widget", "name": name, "value": value})
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PageRequest {
+    text: String,
+    seconds: Option<f32>,
+}
+
+// Takes `{"text": "...", "seconds": 30}`. An empty message clears the page.
+fn parse_page(body: &str) -> Option<ControlMsg> {
+    let req = serde_json::from_str::<PageRequest>(body).ok()?;
+    if req.text.trim().is_empty() {
+        Some(ControlMsg::ClearPage)
+    } else {
+        let timeout = req.seconds.and_then(page_timeout);
+        Some(ControlMsg::Page(Page::new(&req.text, timeout)))
+    }
+}
+
+fn is_json(headers: &[tiny_http::Header]) -> bool {
+    headers
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .and_then(|h| h.value.as_str().split(';').next())
+        .is_some_and(|kind| kind.trim().eq_ignore_ascii_case("application/json"))
+}
+
+impl Drop for WebServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+// Config files copied to a cold spare. Once the spare's operator applies them
+// they are used from its next start, so a swap mid-show comes up on the same
+// host and settings.
+const MIRRORED_FILES: &[&str] = &[
+    "config.toml",
+    "translations.txt",
+    "webhooks.txt",
+    "sheet-edits.toml",
+    "script.rhai",
+    "osc-map.txt",
+];
+
+// Shown on the spare and typed in on the main display. Eight characters from
+// an alphabet without lookalikes.
+pub fn pairing_code() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut bits = RandomState::new().build_hasher().finish();
+    (0..8)
+        .map(|_| {
+            let c = ALPHABET[(bits % 32) as usize];
+            bits /= 32;
+            char::from(c)
+        })
+        .collect()
+}
+
+pub fn spawn_mirror(url: String, code: String, tx: Sender<Result<usize, String>>) {
+    thread::spawn(move || {
+        let dir = dirs::config_dir().map(|d| d.join("theatremix-remote-display"));
+        let files: HashMap<&str, String> = MIRRORED_FILES
+            .iter()
+            .filter_map(|name| {
+                let contents = fs::read_to_string(dir.as_ref()?.join(name)).ok()?;
+                if *name == "config.toml" {
+                    return Some((*name, replace_hooks(&contents, None).ok()?));
+                }
+                Some((*name, contents))
+            })
+            .collect();
+        let body = serde_json::to_string(&files).unwrap_or_default();
+        let result = ureq::post(&url)
+            .config()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build()
+            .header("Content-Type", "application/json")
+            .header("X-Pairing-Code", code.trim().to_uppercase())
+            .send(&body)
+            .map(|_| files.len())
+            .map_err(|e| match e {
+                ureq::Error::StatusCode(403) => {
+                    "Mirror refused: turn on accepting on the spare and enter its code".to_string()
+                }
+                e => format!("Mirror failed: {e}"),
+            });
+        let _ = tx.send(result);
+    });
+}
+
+// How often another display's notes are fetched.
+const NOTES_SYNC_EVERY: Duration = Duration::from_secs(5);
+
+// Fetches the notes of the display at `peer` (host:port of its web display)
+// until the app drops the receiver.
+pub fn spawn_notes_sync(peer: String, tx: Sender<Result<SharedNotes, String>>) {
+    thread::spawn(move || {
+        let url = format!("http://{peer}/api/notes");
+        loop {
+            let result = ureq::get(&url)
+                .config()
+                .timeout_global(Some(Duration::from_secs(5)))
+                .build()
+                .call()
+                .and_then(|mut resp| resp.body_mut().read_to_string())
+                .map_err(|e| format!("Sync failed: {e}"))
+                .and_then(|body| {
+                    serde_json::from_str::<SharedNotes>(&body)
+                        .map_err(|e| format!("Sync failed: {e}"))
+                });
+            if tx.send(result).is_err() {
+                break;
+            }
+            thread::sleep(NOTES_SYNC_EVERY);
+        }
+    });
+}
+
+// Only the known config files are taken; anything else is rejected. Hooks run
+// shell commands, so they never come over the wire.
+fn check_mirror(body: &str) -> Result<HashMap<String, String>, String> {
+    let mut files: HashMap<String, String> =
+        serde_json::from_str(body).map_err(|e| e.to_string())?;
+    if files
+        .keys()
+        .any(|name| !MIRRORED_FILES.contains(&name.as_str()))
+    {
+        return Err("unexpected file".to_string());
+    }
+    if let Some(config) = files.get_mut("config.toml") {
+        *config = replace_hooks(config, None)?;
+    }
+    Ok(files)
+}
+
+// Writes mirrored files once the operator has looked them over. The spare
+// keeps its own hooks.
+pub fn apply_mirror(files: &HashMap<String, String>) -> Result<usize, String> {
+    let dir = dirs::config_dir()
+        .ok_or("no config directory")?
+        .join("theatremix-remote-display");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    for (name, contents) in files {
+        let path = dir.join(name);
+        let contents = if name == "config.toml" {
+            let hooks = fs::read_to_string(&path)
+                .ok()
+                .and_then(|local| local.parse::<toml::Table>().ok())
+                .and_then(|mut local| local.remove("hooks"));
+            replace_hooks(contents, hooks)?
+        } else {
+            contents.clone()
+        };
+        fs::write(path, contents).map_err(|e| e.to_string())?;
+    }
+    Ok(files.len())
+}
+
+fn replace_hooks(config: &str, hooks: Option<toml::Value>) -> Result<String, String> {
+    let mut table: toml::Table = config.parse().map_err(|e| format!("config.toml: {e}"))?;
+    table.remove("hooks");
+    if let Some(hooks) = hooks {
+        table.insert("hooks".to_string(), hooks);
+    }
+    toml::to_string(&table).map_err(|e| e.to_string())
+}
+
+// Fire and forget: a slow or dead endpoint must never stall the UI.
+pub fn post_webhook(url: String, body: String) {
+    thread::spawn(move || {
+        let sent = ureq::post(&url)
+            .config()
+            .timeout_global(Some(Duration::from_secs(5)))
+            .build()
+            .header("Content-Type", "application/json")
+            .send(&body);
+        if let Err(err) = sent {
+            tracing::warn!("Webhook {url} failed: {err}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirror_rejects_unknown_files() {
+        let body = r#"{"config.toml": "", "../../.bashrc": "echo hi"}"#;
+        assert!(check_mirror(body).is_err());
+        assert!(check_mirror("not json").is_err());
+    }
+
+    #[test]
+    fn mirror_never_takes_hooks() {
+        let body = serde_json::json!({
+            "config.toml": "host = \"10.0.0.1\"\n[hooks]\ncue = \"rm -rf ~\"\n",
+            "script.rhai": "",
+        })
+        .to_string();
+        let files = check_mirror(&body).unwrap();
+        let config: toml::Table = files["config.toml"].parse().unwrap();
+        assert!(!config.contains_key("hooks"));
+        assert_eq!(config["host"].as_str(), Some("10.0.0.1"));
+    }
+
+    #[test]
+    fn local_hooks_are_kept() {
+        let local: toml::Table = "[hooks]\ncue = \"say cue\"\n".parse().unwrap();
+        let merged = replace_hooks("host = \"a\"\n", local.get("hooks").cloned()).unwrap();
+        let merged: toml::Table = merged.parse().unwrap();
+        assert_eq!(merged["hooks"]["cue"].as_str(), Some("say cue"));
+    }
+
+    #[test]
+    fn pairing_codes_differ() {
+        let code = pairing_code();
+        assert_eq!(code.len(), 8);
+        assert_ne!(code, pairing_code());
+    }
+
+    #[test]
+    fn pages_come_as_json() {
+        assert!(matches!(
+            parse_page(r#"{"text": "Hold", "seconds": 30}"#),
+            Some(ControlMsg::Page(_))
+        ));
+        assert!(matches!(
+            parse_page(r#"{"text": " "}"#),
+            Some(ControlMsg::ClearPage)
+        ));
+        assert!(parse_page("Hold").is_none());
+
+        let header = |value: &str| tiny_http::Header::from_bytes("Content-Type", value).unwrap();
+        assert!(is_json(&[header("application/json; charset=utf-8")]));
+        assert!(!is_json(&[header("text/plain")]));
+        assert!(!is_json(&[]));
+    }
+}
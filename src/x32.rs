@@ -0,0 +1,72 @@
+//! Behringer X32 and Midas M32 scenes, shown in place of cues while
+//! TheatreMix isn't answering, so sound checks still get a display.
+
+use crate::event::CueInfo;
+use crate::protocol::osc_message;
+use rosc::{OscMessage, OscType};
+use std::collections::HashMap;
+
+pub const X32_PORT: u16 = 10023;
+
+#[derive(Default)]
+pub struct X32 {
+    // The scene loaded on the console; None until it says, or while it
+    // has none loaded.
+    scene: Option<i32>,
+    names: HashMap<i32, String>,
+    // Name queries for the network thread to send.
+    pub queries: Vec<OscMessage>,
+}
+
+impl X32 {
+    // /xremote keeps updates coming for ten seconds; the scene is asked for
+    // each time too, so the console answers even when nothing changes.
+    pub fn keep_alive(&self) -> Vec<OscMessage> {
+        vec![
+            osc_message("/xremote", &[]),
+            osc_message("/-show/prepos/current", &[]),
+        ]
+    }
+
+    // The loaded scene as a cue, once its name is known.
+    pub fn current(&self) -> Option<CueInfo> {
+        let scene = self.scene?;
+        Some(CueInfo {
+            number: scene.to_string(),
+            text: self.names.get(&scene)?.clone(),
+            color: None,
+        })
+    }
+
+    // Whether the scene to show changed.
+    pub fn handle_message(&mut self, msg: &OscMessage) -> bool {
+        let segments: Vec<&str> = msg.addr.split('/').skip(1).collect();
+        match (segments.as_slice(), msg.args.first()) {
+            (["-show", "prepos", "current"], Some(OscType::Int(scene))) => {
+                let scene = (*scene >= 0).then_some(*scene);
+                if scene == self.scene {
+                    return false;
+                }
+                self.scene = scene;
+                match scene {
+                    Some(scene) if !self.names.contains_key(&scene) => {
+                        self.queries.push(osc_message(
+                            &format!("/-show/showfile/scene/{scene:03}/name"),
+                            &[],
+                        ));
+                        false
+                    }
+                    _ => true,
+                }
+            }
+            (["-show", "showfile", "scene", index, "name"], Some(OscType::String(name))) => {
+                let Ok(index) = index.parse::<i32>() else {
+                    return false;
+                };
+                let renamed = self.names.insert(index, name.clone()).as_ref() != Some(name);
+                renamed && self.scene == Some(index)
+            }
+            _ => false,
+        }
+    }
+}